@@ -1,33 +1,48 @@
 use arc_swap::{access::Map, ArcSwap};
-use std::{io::stdout, sync::Arc};
+use std::{
+    io::{stdout, Stdout},
+    sync::Arc,
+};
 
 use anyhow::{Context, Error, Result};
-use crossterm::{event::Event as CrosstermEvent, execute, terminal};
+use crossterm::{
+    cursor,
+    event::{DisableMouseCapture, EnableMouseCapture, Event as CrosstermEvent},
+    execute, terminal,
+};
 use futures_util::Stream;
 use signal_hook::consts::signal;
 use signal_hook_tokio::Signals;
 
 use crate::{
     config::Config,
+    core::syntax,
+    loader,
+    lsp::{self, lsp as lsp_types},
     term::{
         args::Args,
         compositor::{self, Compositor},
+        job::{self, Jobs},
         keymap::Keymaps,
-        ui::editor::EditorView,
+        ui::{self, editor::EditorView},
+    },
+    tui::{
+        backend::{crossterm::CrosstermBackend, Backend},
+        terminal::Terminal,
     },
-    tui::backend::crossterm::CrosstermBackend,
     view::{
         editor::{Action, Editor},
-        graphics::CursorKind,
+        graphics::Rect,
+        input::Event,
+        theme,
     },
 };
 
-type Terminal = crate::tui::terminal::Terminal<CrosstermBackend<std::io::Stdout>>;
-
-pub struct Application {
+pub struct Application<B: Backend = CrosstermBackend<Stdout>> {
     compositor: Compositor,
-    terminal: Terminal,
+    terminal: Terminal<B>,
     pub editor: Editor,
+    pub jobs: Jobs,
 
     config: Arc<ArcSwap<Config>>,
 
@@ -36,23 +51,53 @@ pub struct Application {
 
 fn restore_term() -> Result<(), Error> {
     let mut stdout = stdout();
+    // reset cursor shape and visibility
+    execute!(stdout, cursor::SetCursorStyle::DefaultUserShape, cursor::Show)?;
+    execute!(stdout, DisableMouseCapture)?;
     execute!(stdout, terminal::LeaveAlternateScreen)?;
     terminal::disable_raw_mode()?;
     Ok(())
 }
 
 impl Application {
-    pub fn new(args: Args, config: Config) -> Result<Self> {
-        let backend = CrosstermBackend::new(stdout());
+    pub fn new(args: Args, config: Config, syn_loader_conf: syntax::Configuration) -> Result<Self> {
+        let backend = CrosstermBackend::new(stdout(), &config.editor);
+        Self::with_backend(args, config, syn_loader_conf, backend)
+    }
+}
+
+impl<B: Backend> Application<B> {
+    /// Creates an application drawing to `backend`, e.g. a [`TestBackend`] in tests.
+    ///
+    /// [`TestBackend`]: crate::tui::backend::test::TestBackend
+    pub fn with_backend(args: Args, config: Config, syn_loader_conf: syntax::Configuration, backend: B) -> Result<Self> {
         let terminal = Terminal::new(backend)?;
         let area = terminal.size().expect("Couldn't get terminal size");
         let mut compositor = Compositor::new(area);
 
+        let mut theme_dirs = vec![loader::config_dir()];
+        theme_dirs.extend(loader::runtime_dirs());
+        let theme_loader = Arc::new(theme::Loader::new(&theme_dirs).with_color_support(terminal.backend().color_support()));
+
+        let theme = config.theme.as_ref().and_then(|theme| {
+            theme_loader
+                .load(theme)
+                .map_err(|e| tracing::warn!("failed to load theme `{theme}` - {e}"))
+                .ok()
+        });
+
+        let syn_loader = Arc::new(syntax::Loader::new(syn_loader_conf));
+
         let config = Arc::new(ArcSwap::from_pointee(config));
         let mut editor = Editor::new(
             area,
+            theme_loader,
+            syn_loader,
             Arc::new(Map::new(Arc::clone(&config), |config: &Config| &config.editor)),
         );
+        if let Some(theme) = theme {
+            editor.set_theme(theme);
+        }
 
         let keys = Box::new(Map::new(Arc::clone(&config), |config: &Config| &config.keys));
         let mut editor_view = Box::new(EditorView::new(Keymaps::new(keys)));
@@ -61,7 +106,10 @@ impl Application {
         if !args.files.is_empty() {
             let first = &args.files[0].0; // we know it's not empty
             if first.is_dir() {
-                anyhow::bail!("directory handling not implemented");
+                std::env::set_current_dir(first).context("set current dir")?;
+                editor.new_file(Action::VerticalSplit);
+                let picker = ui::file_picker(".".into(), &editor);
+                compositor.push(Box::new(ui::overlaid(picker)));
             } else {
                 let nr_of_files = args.files.len();
                 for (i, (file, pos)) in args.files.into_iter().enumerate() {
@@ -79,11 +127,13 @@ impl Application {
                             }
                         };
                         let doc_id = editor
-                            .open(&file, action)
+                            .open_with_encoding(&file, action, args.encoding)
                             .context(format!("open '{}'", file.to_string_lossy()))?;
                     }
                 }
             }
+        } else {
+            editor.new_file(Action::VerticalSplit);
         }
 
         let signals = Signals::new([signal::SIGTSTP]).context("build signal handler")?;
@@ -91,6 +141,7 @@ impl Application {
         Ok(Self {
             compositor,
             editor,
+            jobs: Jobs::new(),
             config,
             terminal,
             signals,
@@ -115,6 +166,10 @@ impl Application {
 
         self.event_loop(input_stream).await;
 
+        if self.editor.close_language_servers(None).await.is_err() {
+            tracing::error!("Timed out waiting for language servers to shutdown");
+        };
+
         restore_term()?;
 
         Ok(self.editor.exit_code)
@@ -126,6 +181,9 @@ impl Application {
         let mut stdout = stdout();
         execute!(stdout, terminal::EnterAlternateScreen,)?;
         execute!(stdout, terminal::Clear(terminal::ClearType::All),)?;
+        if self.config.load().editor.mouse {
+            execute!(stdout, EnableMouseCapture)?;
+        }
 
         Ok(())
     }
@@ -148,23 +206,46 @@ impl Application {
         S: Stream<Item = crossterm::Result<crossterm::event::Event>> + Unpin,
     {
         loop {
-            // TODO: handle should_close
+            if self.editor.should_close() {
+                return false;
+            }
 
             use futures_util::StreamExt;
 
+            let redraw_handle = self.editor.redraw_handle.clone();
+
             tokio::select! {
                 biased;
 
-                Some(event) = input_stream.next() => {
-                    self.handle_terminal_events(event).await;
+                event = input_stream.next() => match event {
+                    Some(event) => self.handle_terminal_events(event).await,
+                    // the input stream was closed
+                    None => return false,
+                },
+                Some((id, call)) = self.editor.language_servers.incoming.next() => {
+                    self.handle_language_server_message(call, id).await;
+                    self.render().await;
+                }
+                Some(callback) = self.jobs.futures.next() => self.handle_job_callback(callback).await,
+                _ = redraw_handle.notified() => self.render().await,
+                _ = &mut self.editor.idle_timer => self.handle_idle_timeout().await,
+                _ = &mut self.editor.status_timer => {
+                    self.editor.handle_status_timeout();
+                    self.render().await;
                 }
             }
         }
     }
 
+    pub fn backend(&self) -> &B {
+        self.terminal.backend()
+    }
+
     async fn render(&mut self) {
         let mut cx = compositor::Context {
             editor: &mut self.editor,
+            jobs: &mut self.jobs,
+            scroll: None,
         };
 
         let area = self.terminal.autoresize().expect("Unable to determine terminal size");
@@ -173,19 +254,248 @@ impl Application {
 
         self.compositor.render(area, surface, &mut cx);
 
-        // TODO: handle cursor
+        let (pos, kind) = self.compositor.cursor(area, &self.editor);
+        let pos = pos.map(|pos| (pos.col as u16, pos.row as u16));
+        self.terminal.draw(pos, kind).unwrap();
+    }
+
+    /// Runs the callback of a finished job, see [`Jobs::handle_callback`].
+    pub async fn handle_job_callback(&mut self, callback: Result<Option<job::Callback>>) {
+        self.jobs.handle_callback(&mut self.editor, &mut self.compositor, callback);
+        self.render().await;
+    }
+
+    /// Lets the components react to the editor being idle, e.g. to request completions.
+    pub async fn handle_idle_timeout(&mut self) {
+        // the timer fires once, until the next key resets it
+        self.editor.clear_idle_timer();
+
+        let mut cx = compositor::Context {
+            editor: &mut self.editor,
+            jobs: &mut self.jobs,
+            scroll: None,
+        };
+        if self.compositor.handle_event(&Event::IdleTimeout, &mut cx) {
+            self.render().await;
+        }
+    }
+
+    pub async fn handle_language_server_message(&mut self, call: lsp::Call, server_id: usize) {
+        use lsp::{Call, MethodCall, Notification};
+
+        match call {
+            Call::Notification(lsp::jsonrpc::Notification { method, params, .. }) => {
+                let notification = match Notification::parse(&method, params) {
+                    Ok(notification) => notification,
+                    Err(lsp::Error::Unhandled) => {
+                        tracing::info!("Ignoring Unhandled notification from Language Server: {method}");
+                        return;
+                    }
+                    Err(err) => {
+                        tracing::error!("Ignoring unknown notification from Language Server: {err}");
+                        return;
+                    }
+                };
+
+                match notification {
+                    Notification::Initialized => {
+                        let Some(language_server) = self.editor.language_servers.get_by_id(server_id) else {
+                            tracing::warn!("can't find language server with id `{server_id}`");
+                            return;
+                        };
 
-        self.terminal.draw(None, CursorKind::Block).unwrap();
+                        if let Some(config) = language_server.config() {
+                            if let Err(err) = language_server.did_change_configuration(config.clone()) {
+                                tracing::error!("failed to send the configuration to the language server: {err}");
+                            }
+                        }
+
+                        // open the documents using the server, it ignored them until now
+                        let docs = self
+                            .editor
+                            .documents()
+                            .filter(|doc| doc.language_server().map(|server| server.id()) == Some(server_id));
+                        for doc in docs {
+                            let Some(url) = doc.url() else {
+                                continue;
+                            };
+                            let language_id = doc.language_id().map(ToOwned::to_owned).unwrap_or_default();
+                            if let Err(err) = language_server.text_document_did_open(url, doc.version(), doc.text(), language_id)
+                            {
+                                tracing::error!("failed to open the document in the language server: {err}");
+                            }
+                        }
+                    }
+                    Notification::PublishDiagnostics(mut params) => {
+                        let path = match params.uri.to_file_path() {
+                            Ok(path) => path,
+                            Err(_) => {
+                                tracing::error!("Unsupported file URI: {}", params.uri);
+                                return;
+                            }
+                        };
+                        let Some(offset_encoding) = self
+                            .editor
+                            .language_servers
+                            .get_by_id(server_id)
+                            .map(|server| server.offset_encoding())
+                        else {
+                            tracing::warn!("can't find language server with id `{server_id}`");
+                            return;
+                        };
+
+                        if let Some(doc) = self.editor.document_by_path_mut(&path) {
+                            // the positions don't match the text if it changed since
+                            if let Some(version) = params.version.filter(|&version| version != doc.version()) {
+                                tracing::info!(
+                                    "dropping the diagnostics of version {version} for {path:?}, the document is at version {}",
+                                    doc.version()
+                                );
+                                return;
+                            }
+
+                            let diagnostics = params
+                                .diagnostics
+                                .iter()
+                                .filter_map(|diagnostic| {
+                                    lsp::util::lsp_diagnostic_to_diagnostic(doc.text(), diagnostic, offset_encoding)
+                                })
+                                .collect();
+                            doc.set_diagnostics(diagnostics);
+                        }
+
+                        // Keep the diagnostics of the server for the workspace picker, they are
+                        // converted when picked as the file may not be open. The severities are
+                        // defined in decreasing order.
+                        if params.diagnostics.is_empty() {
+                            self.editor.diagnostics.remove(&params.uri);
+                        } else {
+                            params
+                                .diagnostics
+                                .sort_unstable_by_key(|diagnostic| (diagnostic.severity, diagnostic.range.start));
+                            let diagnostics = params
+                                .diagnostics
+                                .into_iter()
+                                .map(|diagnostic| (diagnostic, server_id))
+                                .collect();
+                            self.editor.diagnostics.insert(params.uri, diagnostics);
+                        }
+                    }
+                    Notification::ShowMessage(params) => {
+                        if self.editor.config().lsp.display_message {
+                            match params.typ {
+                                lsp_types::MessageType::ERROR => self.editor.set_error(params.message),
+                                _ => self.editor.set_status(params.message),
+                            }
+                        }
+                    }
+                    Notification::LogMessage(params) => {
+                        tracing::info!("window/logMessage: {params:?}");
+                    }
+                    Notification::ProgressMessage(params) => {
+                        tracing::debug!("$/progress: {params:?}");
+                    }
+                    Notification::Exit => {
+                        self.editor.set_status("Language server exited");
+
+                        // the documents still using the server are no longer synchronized
+                        for doc in self.editor.documents_mut() {
+                            if doc.language_server().map(|server| server.id()) == Some(server_id) {
+                                doc.set_language_server(None);
+                                doc.set_diagnostics(Vec::new());
+                            }
+                        }
+                        for diagnostics in self.editor.diagnostics.values_mut() {
+                            diagnostics.retain(|(_, id)| *id != server_id);
+                        }
+                        self.editor.diagnostics.retain(|_, diagnostics| !diagnostics.is_empty());
+
+                        // remove the language server from the registry
+                        self.editor.language_servers.remove_by_id(server_id);
+                    }
+                }
+            }
+            Call::MethodCall(lsp::jsonrpc::MethodCall { method, params, id, .. }) => {
+                let reply = match MethodCall::parse(&method, params) {
+                    Err(lsp::Error::Unhandled) => {
+                        tracing::error!("Language Server: Method {method} not found in request {id}");
+                        Err(lsp::jsonrpc::Error {
+                            code: lsp::jsonrpc::ErrorCode::MethodNotFound,
+                            message: format!("Method not found: {method}"),
+                            data: None,
+                        })
+                    }
+                    Err(err) => {
+                        tracing::error!("Language Server: Received malformed method call {method} in request {id}: {err}");
+                        Err(lsp::jsonrpc::Error {
+                            code: lsp::jsonrpc::ErrorCode::ParseError,
+                            message: format!("Malformed method call: {method}"),
+                            data: None,
+                        })
+                    }
+                    Ok(MethodCall::WorkDoneProgressCreate(params)) => {
+                        tracing::debug!("window/workDoneProgress/create: {params:?}");
+                        Ok(serde_json::Value::Null)
+                    }
+                    Ok(MethodCall::WorkspaceFolders) => {
+                        let Some(language_server) = self.editor.language_servers.get_by_id(server_id) else {
+                            return;
+                        };
+                        Ok(serde_json::json!(language_server.workspace_folders()))
+                    }
+                    Ok(MethodCall::WorkspaceConfiguration(params)) => {
+                        let Some(language_server) = self.editor.language_servers.get_by_id(server_id) else {
+                            return;
+                        };
+                        // the sections are looked up in the `config` of the language
+                        let result: Vec<_> = params
+                            .items
+                            .iter()
+                            .map(|item| {
+                                let mut config = language_server.config()?;
+                                if let Some(section) = item.section.as_ref() {
+                                    for part in section.split('.') {
+                                        config = config.get(part)?;
+                                    }
+                                }
+                                Some(config.clone())
+                            })
+                            .map(|config| config.unwrap_or(serde_json::Value::Null))
+                            .collect();
+                        Ok(serde_json::json!(result))
+                    }
+                };
+
+                let Some(language_server) = self.editor.language_servers.get_by_id(server_id) else {
+                    tracing::warn!("can't find language server with id `{server_id}`");
+                    return;
+                };
+                if let Err(err) = language_server.reply(id, reply) {
+                    tracing::error!("failed to reply to the language server: {err}");
+                }
+            }
+            Call::Invalid { id } => tracing::error!("LSP invalid method call id={id:?}"),
+        }
     }
 
     pub async fn handle_terminal_events(&mut self, event: Result<CrosstermEvent, crossterm::ErrorKind>) {
         let mut cx = compositor::Context {
             editor: &mut self.editor,
+            jobs: &mut self.jobs,
+            scroll: None,
         };
 
         let should_redraw = match event.unwrap() {
-            CrosstermEvent::Resize(_, _) => {
-                todo!("handle resize event");
+            CrosstermEvent::Resize(width, height) => {
+                self.terminal
+                    .resize(Rect::new(0, 0, width, height))
+                    .expect("Unable to resize terminal");
+
+                let area = self.terminal.size().expect("couldn't get terminal size");
+
+                self.compositor.resize(area);
+
+                self.compositor.handle_event(&Event::Resize(width, height), &mut cx)
             }
             CrosstermEvent::Key(crossterm::event::KeyEvent {
                 kind: crossterm::event::KeyEventKind::Release,
@@ -1,11 +1,19 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io, path::Path};
+
+use serde::Deserialize;
 
 use crate::{
     term::keymap::{self, Keymap},
     view::{self, document::Mode},
 };
 
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    /// Name of the theme to load, the embedded one if unset.
+    pub theme: Option<String>,
+    /// The keymaps aren't configurable yet, the default ones are always used.
+    #[serde(skip)]
     pub keys: HashMap<Mode, Keymap>,
     pub editor: view::editor::Config,
 }
@@ -13,8 +21,109 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            theme: None,
             keys: keymap::default(),
             editor: view::editor::Config::default(),
         }
     }
 }
+
+impl Config {
+    /// Loads the `config.toml` at `path`, the options it leaves out keeping their default value.
+    /// A missing file is the default configuration.
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        match std::fs::read_to_string(path) {
+            Ok(config) => Ok(toml::from_str(&config)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::view::editor::{BufferLine, GutterType, LineNumber, StatusLineElement, WhitespaceRenderValue};
+
+    #[test]
+    fn parse_editor_config() {
+        let config: Config = toml::from_str(
+            r#"
+            theme = "onedark"
+
+            [editor]
+            mouse = false
+            scroll-lines = 5
+            line-number = "relative"
+            gutters = ["line-numbers", "spacer"]
+            cursorline = true
+            idle-timeout = 50
+            true-color = true
+
+            [editor.file-picker]
+            hidden = false
+
+            [editor.whitespace]
+            render = { space = "all" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.theme.as_deref(), Some("onedark"));
+        let editor = &config.editor;
+        assert!(!editor.mouse && editor.cursorline && editor.true_color);
+        assert!(!editor.cursorcolumn && !editor.undercurl);
+        assert_eq!(editor.scroll_lines, 5);
+        assert_eq!(editor.line_number, LineNumber::Relative);
+        assert_eq!(editor.gutters, [GutterType::LineNumbers, GutterType::Spacer]);
+        assert_eq!(editor.idle_timeout, Duration::from_millis(50));
+        assert!(!editor.file_picker.hidden);
+        assert_eq!(editor.whitespace.render.space(), WhitespaceRenderValue::All);
+        assert_eq!(editor.whitespace.render.tab(), WhitespaceRenderValue::None);
+        // the options left out keep their default value
+        assert_eq!(editor.scrolloff, 5);
+        assert_eq!(editor.status_timeout, Duration::from_secs(5));
+        assert!(config.keys.contains_key(&Mode::Normal));
+
+        assert!(toml::from_str::<Config>("[editor]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn parse_statusline_and_bufferline() {
+        let config: Config = toml::from_str(
+            r#"
+            [editor]
+            bufferline = "multiple"
+
+            [editor.statusline]
+            left = ["mode", "file-base-name"]
+            right = ["position", "file-type"]
+            separator = "|"
+            mode = { normal = "NORMAL" }
+            "#,
+        )
+        .unwrap();
+
+        let editor = &config.editor;
+        assert_eq!(editor.bufferline, BufferLine::Multiple);
+        let statusline = &editor.statusline;
+        assert_eq!(statusline.left, [StatusLineElement::Mode, StatusLineElement::FileBaseName]);
+        assert!(statusline.center.is_empty());
+        assert_eq!(statusline.right, [StatusLineElement::Position, StatusLineElement::FileType]);
+        assert_eq!(statusline.separator, "|");
+        assert_eq!(statusline.mode.normal, "NORMAL");
+        assert_eq!(statusline.mode.insert, "INS");
+
+        assert!(toml::from_str::<Config>("[editor]\nbufferline = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn parse_rulers() {
+        let config: Config = toml::from_str("[editor]\nrulers = [80, 120]").unwrap();
+        assert_eq!(config.editor.rulers, [80, 120]);
+        assert!(Config::default().editor.rulers.is_empty());
+        assert!(toml::from_str::<Config>("[editor]\nrulers = [-1]").is_err());
+    }
+}
@@ -1,6 +1,8 @@
 pub use encoding_rs as encoding;
 
 pub mod chars;
+pub mod diagnostic;
+pub mod diff;
 pub mod doc_formatter;
 pub mod graphemes;
 pub mod line_ending;
@@ -11,6 +13,7 @@ pub mod position;
 pub mod selection;
 pub mod syntax;
 pub mod text_annotations;
+pub mod transaction;
 
 pub mod unicode {
     pub use unicode_general_category as category;
@@ -22,10 +25,12 @@ pub use ropey::{str_utils, Rope, RopeBuilder, RopeSlice};
 
 pub use smartstring::SmartString;
 
-pub use line_ending::LineEnding;
+pub use line_ending::{LineEnding, DEFAULT_LINE_ENDING};
 
 pub use selection::{Range, Selection};
 
-pub use position::VisualOffsetError;
+pub use position::{coords_at_pos, Position, VisualOffsetError};
+
+pub use transaction::{Assoc, Change, ChangeSet, Operation, Transaction};
 
 pub type Tendril = SmartString<smartstring::LazyCompact>;
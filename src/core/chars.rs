@@ -26,3 +26,9 @@ pub fn char_is_whitespace(ch: char) -> bool {
         _ => false,
     }
 }
+
+/// Whether a character is part of a word: alphanumeric or an underscore.
+#[inline]
+pub fn char_is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
@@ -0,0 +1,35 @@
+//! LSP diagnostic utility types.
+
+/// Describes the severity level of a [`Diagnostic`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    #[default]
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+/// A range of `char`s within the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Range {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Corresponds to [`lsp_types::Diagnostic`](https://docs.rs/lsp-types/0.94.0/lsp_types/struct.Diagnostic.html)
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub range: Range,
+    /// The line of `range.start`.
+    pub line: usize,
+    pub message: String,
+    pub severity: Option<Severity>,
+}
+
+impl Diagnostic {
+    /// The severity of the diagnostic, a diagnostic without one is shown as a warning.
+    pub fn severity(&self) -> Severity {
+        self.severity.unwrap_or(Severity::Warning)
+    }
+}
@@ -0,0 +1,77 @@
+//! Line based diffing of two texts, used to show changes against the version on disk.
+
+use std::ops::Range;
+
+use imara_diff::{intern::InternedInput, Algorithm};
+use ropey::RopeSlice;
+
+/// A change between two texts, as ranges of lines in the old and the new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub before: Range<u32>,
+    pub after: Range<u32>,
+}
+
+impl Hunk {
+    /// Lines were only added.
+    pub fn is_pure_insertion(&self) -> bool {
+        self.before.is_empty()
+    }
+
+    /// Lines were only removed, `after.start` is the line following the removal.
+    pub fn is_pure_removal(&self) -> bool {
+        self.after.is_empty()
+    }
+}
+
+struct RopeLines<'a>(RopeSlice<'a>);
+
+impl<'a> imara_diff::intern::TokenSource for RopeLines<'a> {
+    type Token = RopeSlice<'a>;
+    type Tokenizer = ropey::iter::Lines<'a>;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        self.0.lines()
+    }
+
+    fn estimate_tokens(&self) -> u32 {
+        self.0.len_lines() as u32
+    }
+}
+
+/// Computes the hunks turning `before` into `after`, ordered by position.
+pub fn compare_ropes(before: RopeSlice, after: RopeSlice) -> Vec<Hunk> {
+    let input = InternedInput::new(RopeLines(before), RopeLines(after));
+
+    let mut hunks = Vec::new();
+    imara_diff::diff(Algorithm::Histogram, &input, |before: Range<u32>, after: Range<u32>| {
+        hunks.push(Hunk { before, after })
+    });
+    hunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ropey::Rope;
+
+    #[test]
+    fn hunks() {
+        let before = Rope::from("a\nb\nc\nd\n");
+        let after = Rope::from("a\nB\nc\nnew\nd\n");
+
+        assert_eq!(
+            compare_ropes(before.slice(..), after.slice(..)),
+            vec![
+                Hunk {
+                    before: 1..2,
+                    after: 1..2
+                },
+                Hunk {
+                    before: 3..3,
+                    after: 3..4
+                },
+            ]
+        );
+    }
+}
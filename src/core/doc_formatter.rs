@@ -42,6 +42,13 @@ impl<'a> FormattedGrapheme<'a> {
         matches!(self.source, GraphemeSource::VirtualText { .. })
     }
 
+    pub fn placeholder() -> Self {
+        FormattedGrapheme {
+            grapheme: Grapheme::Other { g: " ".into() },
+            source: GraphemeSource::Document { codepoints: 0 },
+        }
+    }
+
     pub fn doc_chars(&self) -> usize {
         match self.source {
             GraphemeSource::Document { codepoints } => codepoints as usize,
@@ -52,6 +59,14 @@ impl<'a> FormattedGrapheme<'a> {
     pub fn width(&self) -> usize {
         self.grapheme.width()
     }
+
+    pub fn is_whitespace(&self) -> bool {
+        self.grapheme.is_whitespace()
+    }
+
+    pub fn is_word_boundary(&self) -> bool {
+        self.grapheme.is_word_boundary()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -150,9 +165,6 @@ impl<'t> DocumentFormatter<'t> {
     }
 
     fn next_inline_annotation_grapheme(&mut self) -> Option<(&'t str, Option<Highlight>)> {
-        return None;
-        // TODO
-        /*
         loop {
             if let Some(&mut (ref mut annotation, highlight)) = self.inline_anntoation_graphemes.as_mut() {
                 if let Some(grapheme) = annotation.next() {
@@ -160,13 +172,9 @@ impl<'t> DocumentFormatter<'t> {
                 }
             }
 
-            // if let Some((annotation, highlight)) = self.annotations.next_inline_annotation_at(self.char_pos) {
-            //     self.inline_anntoation_graphemes = Some((UnicodeSegmentation::graphemes(&*annotation.text, true), highlight))
-            // } else {
-            //     return None;
-            // }
+            let (annotation, highlight) = self.annotations.next_inline_annotation_at(self.char_pos)?;
+            self.inline_anntoation_graphemes = Some((annotation.text.graphemes(true), highlight));
         }
-        */
     }
 
     fn advance_grapheme(&mut self, col: usize) -> Option<FormattedGrapheme<'t>> {
@@ -203,6 +211,105 @@ impl<'t> DocumentFormatter<'t> {
         Some(grapheme)
     }
 
+    /// Move a word to the next visual line
+    fn wrap_word(&mut self, virtual_lines_before_word: usize) -> usize {
+        // softwrap this word to the next line
+        let indent_carry_over = if let Some(indent) = self.indent_level {
+            if indent as u16 <= self.text_fmt.max_indent_retain {
+                indent as u16
+            } else {
+                0
+            }
+        } else {
+            // ensure the indent stays 0
+            self.indent_level = Some(0);
+            0
+        };
+
+        self.visual_pos.col = indent_carry_over as usize;
+        self.virtual_lines -= virtual_lines_before_word;
+        self.visual_pos.row += 1 + virtual_lines_before_word;
+        let mut i = 0;
+        let mut word_width = 0;
+        let wrap_indicator = UnicodeSegmentation::graphemes(&*self.text_fmt.wrap_indicator, true).map(|g| {
+            i += 1;
+            let grapheme = FormattedGrapheme::new(
+                g.into(),
+                self.visual_pos.col + word_width,
+                self.text_fmt.tab_width,
+                GraphemeSource::VirtualText {
+                    highlight: self.text_fmt.wrap_indicator_highlight,
+                },
+            );
+            word_width += grapheme.width();
+            grapheme
+        });
+        self.word_buf.splice(0..0, wrap_indicator);
+
+        for grapheme in &mut self.word_buf[i..] {
+            let visual_x = self.visual_pos.col + word_width;
+            grapheme.grapheme.change_position(visual_x, self.text_fmt.tab_width);
+            word_width += grapheme.width();
+        }
+        word_width
+    }
+
+    fn advance_to_next_word(&mut self) {
+        self.word_buf.clear();
+        let mut word_width = 0;
+        let virtual_lines_before_word = self.virtual_lines;
+        let mut virtual_lines_before_grapheme = self.virtual_lines;
+
+        loop {
+            // softwrap word if necessary
+            if word_width + self.visual_pos.col >= self.text_fmt.viewport_width as usize {
+                // wrapping this word would move too much text to the next line
+                // split the word at the line end instead
+                if word_width > self.text_fmt.max_wrap as usize {
+                    // Usually we stop accomulating graphemes as soon as softwrapping becomes necessary.
+                    // However if the last grapheme is multiple columns wide it might extend beyond the EOL.
+                    // The condition below ensures that this grapheme is not cutoff and instead wrapped to the next line
+                    if word_width + self.visual_pos.col > self.text_fmt.viewport_width as usize {
+                        self.peeked_grapheme = self
+                            .word_buf
+                            .pop()
+                            .map(|grapheme| (grapheme, self.virtual_lines - virtual_lines_before_grapheme));
+                        self.virtual_lines = virtual_lines_before_grapheme;
+                    }
+                    return;
+                }
+
+                word_width = self.wrap_word(virtual_lines_before_word);
+            }
+
+            virtual_lines_before_grapheme = self.virtual_lines;
+
+            let grapheme = if let Some((grapheme, virtual_lines)) = self.peeked_grapheme.take() {
+                self.virtual_lines += virtual_lines;
+                grapheme
+            } else if let Some(grapheme) = self.advance_grapheme(self.visual_pos.col + word_width) {
+                grapheme
+            } else {
+                return;
+            };
+
+            // Track indentation
+            if !grapheme.is_whitespace() && self.indent_level.is_none() {
+                self.indent_level = Some(self.visual_pos.col);
+            } else if grapheme.grapheme == Grapheme::Newline {
+                self.indent_level = None;
+            }
+
+            let is_word_boundary = grapheme.is_word_boundary();
+            word_width += grapheme.width();
+            self.word_buf.push(grapheme);
+
+            if is_word_boundary {
+                return;
+            }
+        }
+    }
+
     /// returns the document line pos of the **next** grapheme that will be yielded
     pub fn line_pos(&self) -> usize {
         self.line_pos
@@ -214,14 +321,13 @@ impl<'t> Iterator for DocumentFormatter<'t> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let grapheme = if self.text_fmt.soft_wrap {
-            todo!()
-            // if self.word_i >= self.word_buf.len() {
-            //     self.advance_to_next_word();
-            //     self.word_i = 0;
-            // }
-            // let grapheme = replace(self.word_buf.get_mut(self.word_i)?, FormattedGrapheme::placeholder());
-            // self.word_i += 1;
-            // grapheme
+            if self.word_i >= self.word_buf.len() {
+                self.advance_to_next_word();
+                self.word_i = 0;
+            }
+            let grapheme = replace(self.word_buf.get_mut(self.word_i)?, FormattedGrapheme::placeholder());
+            self.word_i += 1;
+            grapheme
         } else {
             self.advance_grapheme(self.visual_pos.col)?
         };
@@ -12,7 +12,7 @@ use ropey::{iter::Chunks, str_utils::byte_to_char_idx, RopeSlice};
 use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
 use unicode_width::UnicodeWidthStr;
 
-use super::chars::char_is_whitespace;
+use super::chars::{char_is_whitespace, char_is_word};
 use crate::core::LineEnding;
 
 #[inline]
@@ -37,10 +37,23 @@ impl<'a> Grapheme<'a> {
             _ => Grapheme::Other { g },
         }
     }
+
+    pub fn change_position(&mut self, visual_x: usize, tab_width: u16) {
+        if let Grapheme::Tab { width } = self {
+            *width = tab_width_at(visual_x, tab_width)
+        }
+    }
+
     pub fn is_whitespace(&self) -> bool {
         !matches!(&self, Grapheme::Other { g } if !g.chars().all(char_is_whitespace))
     }
 
+    /// Whether soft wrapping may break the line after this grapheme. Only words are kept
+    /// together, which works well for code and prose.
+    pub fn is_word_boundary(&self) -> bool {
+        !matches!(&self, Grapheme::Other { g } if g.chars().all(char_is_word))
+    }
+
     /// Returns the a visual width of this grapheme,
     #[inline]
     pub fn width(&self) -> usize {
@@ -1,11 +1,100 @@
+use crate::core::{Rope, RopeSlice};
+
+#[cfg(target_os = "windows")]
+pub const DEFAULT_LINE_ENDING: LineEnding = LineEnding::Crlf;
+#[cfg(not(target_os = "windows"))]
+pub const DEFAULT_LINE_ENDING: LineEnding = LineEnding::LF;
+
 /// Represents one of the valid Unicode line endings.
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum LineEnding {
     Crlf, // CarriageReturn followed by LineFeed
     LF,   // U+000A -- LineFeed
+    #[cfg(feature = "unicode-lines")]
+    VT, // U+000B -- VerticalTab
+    #[cfg(feature = "unicode-lines")]
+    FF, // U+000C -- FormFeed
+    #[cfg(feature = "unicode-lines")]
+    CR, // U+000D -- CarriageReturn
+    #[cfg(feature = "unicode-lines")]
+    Nel, // U+0085 -- NextLine
+    #[cfg(feature = "unicode-lines")]
+    LS, // U+2028 -- Line Separator
+    #[cfg(feature = "unicode-lines")]
+    PS, // U+2029 -- ParagraphSeparator
 }
 
 impl LineEnding {
+    #[inline]
+    pub const fn len_chars(&self) -> usize {
+        match self {
+            Self::Crlf => 2,
+            _ => 1,
+        }
+    }
+
+    #[inline]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Crlf => "\u{000D}\u{000A}",
+            Self::LF => "\u{000A}",
+            #[cfg(feature = "unicode-lines")]
+            Self::VT => "\u{000B}",
+            #[cfg(feature = "unicode-lines")]
+            Self::FF => "\u{000C}",
+            #[cfg(feature = "unicode-lines")]
+            Self::CR => "\u{000D}",
+            #[cfg(feature = "unicode-lines")]
+            Self::Nel => "\u{0085}",
+            #[cfg(feature = "unicode-lines")]
+            Self::LS => "\u{2028}",
+            #[cfg(feature = "unicode-lines")]
+            Self::PS => "\u{2029}",
+        }
+    }
+
+    /// Short human readable name, used by the statusline and the `:line-ending` command.
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Crlf => "CRLF",
+            Self::LF => "LF",
+            #[cfg(feature = "unicode-lines")]
+            Self::VT => "VT",
+            #[cfg(feature = "unicode-lines")]
+            Self::FF => "FF",
+            #[cfg(feature = "unicode-lines")]
+            Self::CR => "CR",
+            #[cfg(feature = "unicode-lines")]
+            Self::Nel => "NEL",
+            #[cfg(feature = "unicode-lines")]
+            Self::LS => "LS",
+            #[cfg(feature = "unicode-lines")]
+            Self::PS => "PS",
+        }
+    }
+
+    #[inline]
+    pub const fn from_char(ch: char) -> Option<LineEnding> {
+        match ch {
+            '\u{000A}' => Some(LineEnding::LF),
+            #[cfg(feature = "unicode-lines")]
+            '\u{000B}' => Some(LineEnding::VT),
+            #[cfg(feature = "unicode-lines")]
+            '\u{000C}' => Some(LineEnding::FF),
+            #[cfg(feature = "unicode-lines")]
+            '\u{000D}' => Some(LineEnding::CR),
+            #[cfg(feature = "unicode-lines")]
+            '\u{0085}' => Some(LineEnding::Nel),
+            #[cfg(feature = "unicode-lines")]
+            '\u{2028}' => Some(LineEnding::LS),
+            #[cfg(feature = "unicode-lines")]
+            '\u{2029}' => Some(LineEnding::PS),
+            // Not a line ending
+            _ => None,
+        }
+    }
+
     // Normally we'd want to implement the FromStr trait, but in this case
     // that would force us into a different return type than from_char or
     // or from_rope_slice, which would be weird.
@@ -15,8 +104,154 @@ impl LineEnding {
         match g {
             "\u{000D}\u{000A}" => Some(LineEnding::Crlf),
             "\u{000A}" => Some(LineEnding::LF),
+            #[cfg(feature = "unicode-lines")]
+            "\u{000B}" => Some(LineEnding::VT),
+            #[cfg(feature = "unicode-lines")]
+            "\u{000C}" => Some(LineEnding::FF),
+            #[cfg(feature = "unicode-lines")]
+            "\u{000D}" => Some(LineEnding::CR),
+            #[cfg(feature = "unicode-lines")]
+            "\u{0085}" => Some(LineEnding::Nel),
+            #[cfg(feature = "unicode-lines")]
+            "\u{2028}" => Some(LineEnding::LS),
+            #[cfg(feature = "unicode-lines")]
+            "\u{2029}" => Some(LineEnding::PS),
             // Not a line ending
             _ => None,
         }
     }
+
+    /// Parses the name of a line ending as written by the user, e.g. `crlf` or `lf`.
+    pub fn from_name(name: &str) -> Option<LineEnding> {
+        match name.to_ascii_lowercase().as_str() {
+            "crlf" => Some(LineEnding::Crlf),
+            "lf" => Some(LineEnding::LF),
+            #[cfg(feature = "unicode-lines")]
+            "vt" => Some(LineEnding::VT),
+            #[cfg(feature = "unicode-lines")]
+            "ff" => Some(LineEnding::FF),
+            #[cfg(feature = "unicode-lines")]
+            "cr" => Some(LineEnding::CR),
+            #[cfg(feature = "unicode-lines")]
+            "nel" => Some(LineEnding::Nel),
+            #[cfg(feature = "unicode-lines")]
+            "ls" => Some(LineEnding::LS),
+            #[cfg(feature = "unicode-lines")]
+            "ps" => Some(LineEnding::PS),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn from_rope_slice(g: &RopeSlice) -> Option<LineEnding> {
+        if let Some(text) = g.as_str() {
+            LineEnding::from_str(text)
+        } else {
+            // Non-contiguous, so it can't be a line ending.
+            // Specifically, Ropey guarantees that CRLF is always
+            // contiguous.  And the remaining line endings are all
+            // single `char`s, and therefore trivially contiguous.
+            None
+        }
+    }
+}
+
+#[inline]
+pub fn str_is_line_ending(s: &str) -> bool {
+    LineEnding::from_str(s).is_some()
+}
+
+/// Attempts to detect what line ending the passed document uses.
+///
+/// Every line ending of the document is counted and the most frequent one wins.
+/// On a tie the line ending that appears first is preferred.
+pub fn auto_detect_line_ending(doc: &Rope) -> Option<LineEnding> {
+    // (line ending, count) in order of first appearance
+    let mut counts: Vec<(LineEnding, usize)> = Vec::new();
+
+    for line in doc.lines() {
+        if let Some(ending) = get_line_ending(&line) {
+            match counts.iter_mut().find(|(le, _)| *le == ending) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((ending, 1)),
+            }
+        }
+    }
+
+    // `max_by_key` returns the last maximum element, so iterate in reverse to prefer the first.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(ending, _)| ending)
+}
+
+/// Returns the passed line's line ending, if any.
+pub fn get_line_ending(line: &RopeSlice) -> Option<LineEnding> {
+    // Last character as str.
+    let g1 = line.slice(line.len_chars().saturating_sub(1)..).as_str().unwrap();
+
+    // Last two characters as str, or empty str if they're not contiguous.
+    // It's fine to punt on the non-contiguous case, because Ropey guarantees
+    // that CRLF is always contiguous.
+    let g2 = line.slice(line.len_chars().saturating_sub(2)..).as_str().unwrap_or("");
+
+    // First check the two-character case for CRLF, then check the single-character case.
+    LineEnding::from_str(g2).or_else(|| LineEnding::from_str(g1))
+}
+
+/// Returns the char index of the end of the given line, not including its line ending.
+pub fn line_end_char_index(slice: &RopeSlice, line: usize) -> usize {
+    slice.line_to_char(line + 1) - get_line_ending(&slice.line(line)).map(|le| le.len_chars()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod line_ending_tests {
+    use super::*;
+
+    #[test]
+    fn line_ending_autodetect() {
+        assert_eq!(auto_detect_line_ending(&Rope::from_str("\n")), Some(LineEnding::LF));
+        assert_eq!(auto_detect_line_ending(&Rope::from_str("\r\n")), Some(LineEnding::Crlf));
+        assert_eq!(auto_detect_line_ending(&Rope::from_str("Hello")), None);
+        assert_eq!(auto_detect_line_ending(&Rope::from_str("")), None);
+    }
+
+    #[test]
+    fn line_ending_autodetect_picks_dominant() {
+        let text = Rope::from_str("a\r\nb\nc\r\nd\r\n");
+        assert_eq!(auto_detect_line_ending(&text), Some(LineEnding::Crlf));
+
+        let text = Rope::from_str("a\nb\r\nc\nd");
+        assert_eq!(auto_detect_line_ending(&text), Some(LineEnding::LF));
+
+        // ties are won by the line ending that appears first
+        let text = Rope::from_str("a\r\nb\nc");
+        assert_eq!(auto_detect_line_ending(&text), Some(LineEnding::Crlf));
+    }
+
+    #[cfg(feature = "unicode-lines")]
+    #[test]
+    fn line_ending_autodetect_unicode_separators() {
+        let text = Rope::from_str("a\u{2028}b\u{2028}c\n");
+        assert_eq!(auto_detect_line_ending(&text), Some(LineEnding::LS));
+    }
+
+    #[test]
+    fn str_to_line_ending() {
+        assert_eq!(LineEnding::from_str("\r\n"), Some(LineEnding::Crlf));
+        assert_eq!(LineEnding::from_str("\n"), Some(LineEnding::LF));
+        assert_eq!(LineEnding::from_str("hello\n"), None);
+        assert_eq!(LineEnding::from_name("CRLF"), Some(LineEnding::Crlf));
+        assert_eq!(LineEnding::from_name("lf"), Some(LineEnding::LF));
+    }
+
+    #[test]
+    fn line_end_char_index_rope_slice() {
+        let text = Rope::from_str("Hello\r\nworld\nfoo");
+        let s = &text.slice(..);
+        assert_eq!(line_end_char_index(s, 0), 5);
+        assert_eq!(line_end_char_index(s, 1), 12);
+        assert_eq!(line_end_char_index(s, 2), 16);
+    }
 }
@@ -9,7 +9,7 @@ use crate::core::{
 
 use super::{
     graphemes::prev_grapheme_boundary,
-    position::{char_idx_at_visual_block_offset, visual_offset_from_block},
+    position::{char_idx_at_visual_block_offset, char_idx_at_visual_offset, visual_offset_from_block},
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -36,7 +36,30 @@ pub fn move_vertically_visual(
     if !text_fmt.soft_wrap {
         return move_vertically(slice, range, dir, count, behaviour, text_fmt, annotations);
     }
-    todo!()
+    let pos = range.cursor(slice);
+
+    // Compute the current position's 2d coordinates.
+    let (visual_pos, block_off) = visual_offset_from_block(slice, pos, pos, text_fmt, annotations);
+    let new_col = range.old_visual_position.map_or(visual_pos.col as u32, |(_, col)| col);
+
+    // Compute the new position.
+    let mut row_off = match dir {
+        Direction::Forward => count as isize,
+        Direction::Backward => -(count as isize),
+    };
+
+    // Compute visual offset relative to block start to avoid trasversing the block twice
+    row_off += visual_pos.row as isize;
+    let new_pos = char_idx_at_visual_offset(slice, block_off, row_off, new_col as usize, text_fmt, annotations).0;
+
+    // Special-case to avoid moving to the end of the last non-empty line.
+    if behaviour == Movement::Extend && slice.line(slice.char_to_line(new_pos)).len_chars() == 0 {
+        return range;
+    }
+
+    let mut new_range = range.put_cursor(slice, new_pos, behaviour == Movement::Extend);
+    new_range.old_visual_position = Some((0, new_col));
+    new_range
 }
 
 pub fn move_vertically(
@@ -28,6 +28,20 @@ pub fn get_canonicalized_path(path: &Path) -> std::io::Result<PathBuf> {
     Ok(get_normalized_path(path.as_path()))
 }
 
+/// Returns the path relative to the current working directory if it is below it, otherwise the
+/// path unchanged.
+pub fn get_relative_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        if let Ok(cwd) = std::env::current_dir() {
+            if let Ok(relative) = get_normalized_path(path).strip_prefix(get_normalized_path(&cwd)) {
+                return relative.to_path_buf();
+            }
+        }
+    }
+
+    path.to_path_buf()
+}
+
 /// Normalize a path, removing things like `.` and `..`.
 ///
 /// CAUTION: This does not resolve symlinks (unlike
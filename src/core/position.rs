@@ -4,6 +4,7 @@ use ropey::RopeSlice;
 
 use super::{
     doc_formatter::{DocumentFormatter, TextFormat},
+    graphemes::RopeGraphemes,
     text_annotations::TextAnnotations,
 };
 
@@ -20,6 +21,17 @@ impl Position {
     }
 }
 
+/// Convert a character index to (line, column) coordinates.
+///
+/// The column is a count of graphemes, to be used for row:column display in the status line.
+/// `pos` is expected to be on a grapheme boundary.
+pub fn coords_at_pos(text: RopeSlice, pos: usize) -> Position {
+    let line = text.char_to_line(pos);
+    let line_start = text.line_to_char(line);
+    let col = RopeGraphemes::new(text.slice(line_start..pos)).count();
+    Position::new(line, col)
+}
+
 /// Returns the visual offset from the start of the first visual line
 /// in the block that contains anchor.
 /// Text is always wrapped at blocks, they usually correspond to
@@ -223,3 +235,20 @@ pub fn char_idx_at_visual_block_offset(
 
     (char_idx, 0)
 }
+
+#[cfg(test)]
+mod test {
+    use ropey::Rope;
+
+    use super::*;
+
+    #[test]
+    fn coords_count_graphemes() {
+        let text = Rope::from("ab\ne\u{301}x\n");
+        let text = text.slice(..);
+        assert_eq!(coords_at_pos(text, 0), Position::new(0, 0));
+        assert_eq!(coords_at_pos(text, 2), Position::new(0, 2));
+        // the combining accent is part of the grapheme before it
+        assert_eq!(coords_at_pos(text, 5), Position::new(1, 1));
+    }
+}
@@ -1,7 +1,10 @@
 use ropey::RopeSlice;
 use smallvec::{smallvec, SmallVec};
 
-use super::graphemes::{next_grapheme_boundary, nth_next_grapheme_boundary, prev_grapheme_boundary};
+use super::{
+    graphemes::{next_grapheme_boundary, prev_grapheme_boundary},
+    transaction::{Assoc, ChangeSet},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Range {
@@ -27,6 +30,67 @@ impl Range {
         Self::new(head, head)
     }
 
+    /// Start of the range.
+    #[inline]
+    #[must_use]
+    pub fn from(&self) -> usize {
+        std::cmp::min(self.anchor, self.head)
+    }
+
+    /// End of the range.
+    #[inline]
+    #[must_use]
+    pub fn to(&self) -> usize {
+        std::cmp::max(self.anchor, self.head)
+    }
+
+    /// Check two ranges for overlap.
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        // cursor overlap is checked differently
+        self.from() == other.from() || (self.to() > other.from() && other.to() > self.from())
+    }
+
+    /// Returns the smallest range that covers both ranges, reversed if both were.
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        if self.anchor > self.head && other.anchor > other.head {
+            Range::new(self.anchor.max(other.anchor), self.head.min(other.head))
+        } else {
+            Range::new(self.from().min(other.from()), self.to().max(other.to()))
+        }
+    }
+
+    /// Map a range through a set of changes. Returns a new range representing the same position
+    /// after the changes are applied.
+    pub fn map(self, changes: &ChangeSet) -> Self {
+        use std::cmp::Ordering;
+        let (anchor, head) = match self.anchor.cmp(&self.head) {
+            Ordering::Equal => (
+                changes.map_pos(self.anchor, Assoc::After),
+                changes.map_pos(self.head, Assoc::After),
+            ),
+            Ordering::Less => (
+                changes.map_pos(self.anchor, Assoc::After),
+                changes.map_pos(self.head, Assoc::Before),
+            ),
+            Ordering::Greater => (
+                changes.map_pos(self.anchor, Assoc::Before),
+                changes.map_pos(self.head, Assoc::After),
+            ),
+        };
+
+        // We want to return a new `Range` with `old_visual_position == None` every time,
+        // even if the anchor and head haven't changed, because we don't
+        // know if the *visual* position hasn't changed due to
+        // character-width or grapheme changes earlier in the text.
+        Self {
+            anchor,
+            head,
+            old_visual_position: None,
+        }
+    }
+
     pub fn grapheme_aligned(&self, _slice: RopeSlice) -> Self {
         // TODO: handle grapheme boundary
         Range {
@@ -71,11 +135,29 @@ impl Range {
         }
     }
 
+    /// Gets the line the block cursor is on.
+    #[inline]
+    pub fn cursor_line(self, text: RopeSlice) -> usize {
+        text.char_to_line(self.cursor(text))
+    }
+
+    /// Moves the block cursor to `char_idx`, extending the range if `extend` is set. The anchor is
+    /// moved by a grapheme when the range changes direction so the original cell stays selected.
     pub fn put_cursor(self, text: RopeSlice, char_idx: usize, extend: bool) -> Range {
         if extend {
-            // TODO: handle other case;
-            let anchor = self.anchor;
-            Range::new(anchor, char_idx)
+            let anchor = if self.head >= self.anchor && char_idx < self.anchor {
+                next_grapheme_boundary(text, self.anchor)
+            } else if self.head < self.anchor && char_idx >= self.anchor {
+                prev_grapheme_boundary(text, self.anchor)
+            } else {
+                self.anchor
+            };
+
+            if anchor <= char_idx {
+                Range::new(anchor, next_grapheme_boundary(text, char_idx))
+            } else {
+                Range::new(anchor, char_idx)
+            }
         } else {
             Range::point(char_idx)
         }
@@ -97,6 +179,51 @@ impl Selection {
         self.ranges[self.primary_index]
     }
 
+    #[inline]
+    #[must_use]
+    pub fn primary_mut(&mut self) -> &mut Range {
+        &mut self.ranges[self.primary_index]
+    }
+
+    #[inline]
+    pub fn primary_index(&self) -> usize {
+        self.primary_index
+    }
+
+    /// Adds a new range to the selection and makes it the new primary range.
+    pub fn push(mut self, range: Range) -> Self {
+        self.ranges.push(range);
+        self.primary_index = self.ranges.len() - 1;
+        self.normalize()
+    }
+
+    #[inline]
+    pub fn ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+
+    #[inline]
+    #[allow(clippy::len_without_is_empty)] // a Selection is never empty
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Map selections over a set of changes. Useful for adjusting the selection position after
+    /// applying changes to a document.
+    pub fn map(self, changes: &ChangeSet) -> Self {
+        if changes.is_empty() {
+            return self;
+        }
+
+        self.transform(|range| range.map(changes))
+    }
+
+    /// Takes a closure and maps each `Range` over the closure to a cursor (zero width range)
+    /// positioned at the block cursor of the range.
+    pub fn cursors(self, text: RopeSlice) -> Self {
+        self.transform(|range| Range::point(range.cursor(text)))
+    }
+
     /// Ensures the selection adheres to the following invariants:
     /// 1. All ranges are grapheme aligned.
     /// 2. All ranges are at least 1charater wide, unless at the very end of the document.
@@ -107,6 +234,14 @@ impl Selection {
         self.transform(|r| r.min_width_1(text)).normalize()
     }
 
+    /// Create a new selection from a list of ranges and the index of the primary one.
+    pub fn new(ranges: SmallVec<[Range; 1]>, primary_index: usize) -> Self {
+        assert!(!ranges.is_empty());
+        debug_assert!(primary_index < ranges.len());
+
+        Self { ranges, primary_index }.normalize()
+    }
+
     /// Constructs a selection holding a single range.
     pub fn single(anchor: usize, head: usize) -> Self {
         Self {
@@ -119,13 +254,38 @@ impl Selection {
         }
     }
 
+    /// Constructs a selection holding a single cursor.
+    pub fn point(pos: usize) -> Self {
+        Self::single(pos, pos)
+    }
+
     #[inline(always)]
     pub fn iter(&self) -> std::slice::Iter<'_, Range> {
         self.ranges.iter()
     }
 
-    fn normalize(self) -> Self {
-        // TODO: sort and handle overlap
+    /// Sorts the ranges and merges overlapping ones, keeping track of the primary range.
+    fn normalize(mut self) -> Self {
+        if self.len() < 2 {
+            return self;
+        }
+        let mut primary = self.ranges[self.primary_index];
+        self.ranges.sort_unstable_by_key(Range::from);
+
+        self.ranges.dedup_by(|curr_range, prev_range| {
+            if prev_range.overlaps(curr_range) {
+                let new_range = curr_range.merge(*prev_range);
+                if prev_range == &primary || curr_range == &primary {
+                    primary = new_range;
+                }
+                *prev_range = new_range;
+                true
+            } else {
+                false
+            }
+        });
+
+        self.primary_index = self.ranges.iter().position(|&range| range == primary).unwrap();
         self
     }
 
@@ -139,3 +299,58 @@ impl Selection {
         self.normalize()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use ropey::Rope;
+
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        // sorted by start, overlapping ranges are merged and the primary range is tracked
+        let selection = Selection::new(smallvec![Range::new(5, 7), Range::new(0, 2), Range::new(1, 3)], 0);
+        assert_eq!(selection.ranges(), [Range::new(0, 3), Range::new(5, 7)]);
+        assert_eq!(selection.primary_index(), 1);
+
+        let selection = Selection::new(smallvec![Range::new(5, 7), Range::new(0, 2), Range::new(1, 3)], 2);
+        assert_eq!(selection.primary(), Range::new(0, 3));
+
+        // cursors at the same position are merged, adjacent ranges are kept apart
+        let selection = Selection::new(smallvec![Range::point(2), Range::new(2, 4), Range::new(4, 6)], 0);
+        assert_eq!(selection.ranges(), [Range::new(2, 4), Range::new(4, 6)]);
+        assert_eq!(selection.primary_index(), 0);
+    }
+
+    #[test]
+    fn merge() {
+        assert_eq!(Range::new(0, 3).merge(Range::new(2, 5)), Range::new(0, 5));
+        // the merge of two reversed ranges stays reversed
+        assert_eq!(Range::new(3, 0).merge(Range::new(5, 2)), Range::new(5, 0));
+        assert_eq!(Range::new(3, 0).merge(Range::new(2, 5)), Range::new(0, 5));
+    }
+
+    #[test]
+    fn push() {
+        let selection = Selection::single(0, 3).push(Range::new(5, 6));
+        assert_eq!(selection.ranges(), [Range::new(0, 3), Range::new(5, 6)]);
+        assert_eq!(selection.primary_index(), 1);
+
+        // an overlapping range is merged into the new primary range
+        let selection = selection.push(Range::new(2, 4));
+        assert_eq!(selection.ranges(), [Range::new(0, 4), Range::new(5, 6)]);
+        assert_eq!(selection.primary_index(), 0);
+    }
+
+    #[test]
+    fn put_cursor() {
+        let text = Rope::from("hello world\n");
+        let text = text.slice(..);
+        assert_eq!(Range::new(2, 3).put_cursor(text, 5, false), Range::point(5));
+        // the cell under the cursor is part of the selection
+        assert_eq!(Range::new(2, 3).put_cursor(text, 5, true), Range::new(2, 6));
+        // changing direction keeps the cell of the anchor selected
+        assert_eq!(Range::new(2, 3).put_cursor(text, 0, true), Range::new(3, 0));
+        assert_eq!(Range::new(3, 0).put_cursor(text, 4, true), Range::new(2, 5));
+    }
+}
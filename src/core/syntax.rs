@@ -1,14 +1,102 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
 use serde::{Deserialize, Serialize};
 
 /// Indicates which highlight should be applied to a region of source code.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Highlight(pub usize);
 
+/// The contents of `languages.toml`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Configuration {
+    pub language: Vec<LanguageConfiguration>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct LanguageConfiguration {
     #[serde(rename = "name")]
     pub language_id: String, //c-sharp, rust
+
+    /// File names, like `Makefile`, or extensions, like `rs`, of the files of the language.
+    pub file_types: Vec<String>,
+    /// Files marking the root of a workspace, like `Cargo.toml`.
+    #[serde(default)]
+    pub roots: Vec<String>,
+
+    /// Overrides the editor's `rulers` for this language.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rulers: Option<Vec<u16>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_server: Option<LanguageServerConfiguration>,
+    /// Settings passed to the language server as initialization options and
+    /// `workspace/configuration` results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LanguageServerConfiguration {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Environment variables set for the server.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub environment: HashMap<String, String>,
+    /// Seconds to wait for the response to a request.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    20
+}
+
+/// Finds the configuration of the language of a file.
+#[derive(Debug)]
+pub struct Loader {
+    language_configs: Vec<Arc<LanguageConfiguration>>,
+    /// Indices into `language_configs` by file name or extension.
+    language_config_ids_by_file_type: HashMap<String, usize>,
+}
+
+impl Loader {
+    pub fn new(config: Configuration) -> Self {
+        let mut language_config_ids_by_file_type = HashMap::new();
+        for (id, config) in config.language.iter().enumerate() {
+            for file_type in &config.file_types {
+                // the first language claiming a file type wins
+                language_config_ids_by_file_type.entry(file_type.clone()).or_insert(id);
+            }
+        }
+
+        Self {
+            language_configs: config.language.into_iter().map(Arc::new).collect(),
+            language_config_ids_by_file_type,
+        }
+    }
+
+    /// The language of the file at `path`, matched by file name first and then by extension.
+    pub fn language_config_for_file_name(&self, path: &Path) -> Option<Arc<LanguageConfiguration>> {
+        let by_file_type = |file_type: Option<&std::ffi::OsStr>| {
+            let file_type = file_type?.to_str()?;
+            self.language_config_ids_by_file_type.get(file_type)
+        };
+
+        by_file_type(path.file_name())
+            .or_else(|| by_file_type(path.extension()))
+            .map(|&id| self.language_configs[id].clone())
+    }
+
+    pub fn language_config_for_language_id(&self, id: &str) -> Option<Arc<LanguageConfiguration>> {
+        self.language_configs.iter().find(|config| config.language_id == id).cloned()
+    }
+
+    pub fn language_configs(&self) -> impl Iterator<Item = &Arc<LanguageConfiguration>> {
+        self.language_configs.iter()
+    }
 }
 
 /// Represents a single step in rendering a syntax-highlighted document.
@@ -52,9 +140,16 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
             return Some(event);
         }
 
-        // TODO: handle range if offscreen case
-
         match (self.next_event, &self.next_span) {
+            // this happens when range is partially or fully offscreen
+            (Some(Source { start, .. }), Some((span, range))) if start > range.start => {
+                if start >= range.end {
+                    self.next_span = self.spans.next();
+                } else {
+                    self.next_span = Some((*span, start..range.end));
+                };
+                self.next()
+            }
             (Some(Source { start, end }), Some((_, range))) if start < range.start => {
                 let intersect = range.start.min(end);
 
@@ -91,7 +186,7 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
 
                 Some(event)
             }
-            (Some(event), None) => {
+            (Some(event @ (HighlightStart(_) | HighlightEnd)), _) | (Some(event), None) => {
                 self.next_event = self.iter.next();
                 Some(event)
             }
@@ -25,7 +25,7 @@ pub struct InlineAnnotation {
 /// `aX͎̊͢͜͝͡bc`
 ///
 /// ```
-/// use helix_core::text_annotations::Overlay;
+/// use toy_helix::core::text_annotations::Overlay;
 ///
 /// // replaces a
 /// Overlay {
@@ -49,7 +49,7 @@ pub struct InlineAnnotation {
 /// The following examples are invalid uses
 ///
 /// ```
-/// use helix_core::text_annotations::Overlay;
+/// use toy_helix::core::text_annotations::Overlay;
 ///
 /// // overlay is not aligned at grapheme boundary
 /// Overlay{
@@ -110,6 +110,18 @@ impl<A, M> Layer<A, M> {
 
         self.current_index.set(new_index);
     }
+
+    /// Returns the next annotation if it is anchored at `char_idx` and moves past it.
+    pub fn consume(&self, char_idx: usize, get_char_idx: impl Fn(&A) -> usize) -> Option<&A> {
+        let annotation = self.annotations.get(self.current_index.get())?;
+        debug_assert!(get_char_idx(annotation) >= char_idx);
+        if get_char_idx(annotation) == char_idx {
+            self.current_index.set(self.current_index.get() + 1);
+            Some(annotation)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -126,6 +138,28 @@ impl TextAnnotations {
         reset_pos(&self.overlays, char_idx, |annot| annot.char_idx);
         reset_pos(&self.line_annotations, char_idx, |annot| annot.anchor_char_idx);
     }
+
+    /// Adds a layer of inline annotations, drawn with `highlight`.
+    ///
+    /// The annotations **must be sorted** by their `char_idx`. Annotations at the same position
+    /// are shown in the order of their layers, then in their order within the layer.
+    pub fn add_inline_annotations(&mut self, layer: Rc<[InlineAnnotation]>, highlight: Option<Highlight>) -> &mut Self {
+        self.inline_annotations.push(Layer {
+            annotations: layer,
+            current_index: Cell::new(0),
+            metadata: highlight,
+        });
+        self
+    }
+
+    /// Returns the next inline annotation at `char_idx` with its highlight, the formatter calls
+    /// this until it returns `None` before yielding the grapheme at `char_idx`.
+    pub(crate) fn next_inline_annotation_at(&self, char_idx: usize) -> Option<(&InlineAnnotation, Option<Highlight>)> {
+        self.inline_annotations.iter().find_map(|layer| {
+            let annotation = layer.consume(char_idx, |annot| annot.char_idx)?;
+            Some((annotation, layer.metadata))
+        })
+    }
 }
 
 fn reset_pos<A, M>(layers: &[Layer<A, M>], pos: usize, get_pos: impl Fn(&A) -> usize) {
@@ -2,7 +2,14 @@
 
 pub mod application;
 pub mod config;
+pub mod loader;
 pub mod core;
+pub mod lsp;
 pub mod term;
 pub mod tui;
 pub mod view;
+
+/// Path of the file the application log is written to.
+pub fn log_file() -> std::path::PathBuf {
+    std::env::temp_dir().join("toy-helix.log")
+}
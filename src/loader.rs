@@ -0,0 +1,167 @@
+//! Locations of the user configuration and the runtime files, like themes, and helpers to load
+//! them.
+
+use std::path::PathBuf;
+
+use etcetera::base_strategy::{choose_base_strategy, BaseStrategy};
+use toml::Value;
+
+/// Name of the directories the files of the editor are stored in.
+const APP_NAME: &str = "toy-helix";
+/// Environment variable overriding the runtime directory.
+const RUNTIME_ENV: &str = "TOY_HELIX_RUNTIME";
+/// Name of the runtime directory in the source tree and next to the executable.
+const RUNTIME_DIR_NAME: &str = "runtime";
+
+/// The directory of the user configuration, e.g. `~/.config/toy-helix`.
+pub fn config_dir() -> PathBuf {
+    let strategy = choose_base_strategy().expect("Unable to find the config directory!");
+    strategy.config_dir().join(APP_NAME)
+}
+
+/// The `config.toml` of the user configuration.
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// The directories runtime files are searched in, in order of priority: the `runtime`
+/// directory of the user configuration, `$TOY_HELIX_RUNTIME`, the one of the source tree in debug
+/// builds and the one next to the executable.
+pub fn runtime_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![config_dir().join(RUNTIME_DIR_NAME)];
+
+    if let Ok(dir) = std::env::var(RUNTIME_ENV) {
+        dirs.push(dir.into());
+    }
+
+    // used when running from the source tree with `cargo run`, release builds don't know about it
+    #[cfg(debug_assertions)]
+    dirs.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(RUNTIME_DIR_NAME));
+
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from)) {
+        dirs.push(exe_dir.join(RUNTIME_DIR_NAME));
+    }
+
+    dirs.dedup();
+    dirs
+}
+
+/// The embedded `languages.toml`.
+pub fn default_lang_config() -> Value {
+    let bytes = include_bytes!("../languages.toml");
+    toml::from_str(std::str::from_utf8(bytes).unwrap()).expect("Could not parse built-in languages.toml to valid toml")
+}
+
+/// The embedded `languages.toml` merged with the one of the user configuration, if any.
+pub fn user_lang_config() -> Result<Value, toml::de::Error> {
+    let path = config_dir().join("languages.toml");
+    let config = match std::fs::read_to_string(path) {
+        Ok(config) => merge_toml_values(default_lang_config(), toml::from_str(&config)?, 3),
+        Err(_) => default_lang_config(),
+    };
+    Ok(config)
+}
+
+/// Merges the toml `right` into `left`, the values of `right` taking precedence.
+///
+/// Tables are merged recursively up to `merge_depth` levels, below that the tables of `right`
+/// replace the ones of `left`. The tables of arrays, like the `[[language]]` entries of
+/// `languages.toml`, are merged with the ones of the same `name`, other arrays are replaced.
+pub fn merge_toml_values(left: Value, right: Value, merge_depth: usize) -> Value {
+    fn get_name(value: &Value) -> Option<&str> {
+        value.get("name").and_then(Value::as_str)
+    }
+
+    match (left, right) {
+        (Value::Array(mut left_items), Value::Array(right_items)) if merge_depth > 0 => {
+            left_items.reserve(right_items.len());
+            for right_value in right_items {
+                let left_value = get_name(&right_value)
+                    .and_then(|name| left_items.iter().position(|value| get_name(value) == Some(name)))
+                    .map(|position| left_items.remove(position));
+                let value = match left_value {
+                    Some(left_value) => merge_toml_values(left_value, right_value, merge_depth - 1),
+                    None => right_value,
+                };
+                left_items.push(value);
+            }
+            Value::Array(left_items)
+        }
+        (Value::Table(mut left_map), Value::Table(right_map)) if merge_depth > 0 => {
+            for (key, right_value) in right_map {
+                let value = match left_map.remove(&key) {
+                    Some(left_value) => merge_toml_values(left_value, right_value, merge_depth - 1),
+                    None => right_value,
+                };
+                left_map.insert(key, value);
+            }
+            Value::Table(left_map)
+        }
+        (_, value) => value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_toml() {
+        let left: Value = toml::from_str(
+            r#"
+            a = 1
+            [table]
+            b = 2
+            nested = { c = 3, d = 4 }
+            "#,
+        )
+        .unwrap();
+        let right: Value = toml::from_str(
+            r#"
+            e = 5
+            [table]
+            b = 6
+            nested = { c = 7 }
+            "#,
+        )
+        .unwrap();
+
+        let merged = merge_toml_values(left.clone(), right.clone(), usize::MAX);
+        assert_eq!(merged["a"].as_integer(), Some(1));
+        assert_eq!(merged["e"].as_integer(), Some(5));
+        assert_eq!(merged["table"]["b"].as_integer(), Some(6));
+        assert_eq!(merged["table"]["nested"]["c"].as_integer(), Some(7));
+        assert_eq!(merged["table"]["nested"]["d"].as_integer(), Some(4));
+
+        // below the merge depth the tables are replaced
+        let merged = merge_toml_values(left, right.clone(), 1);
+        assert_eq!(merged["a"].as_integer(), Some(1));
+        assert_eq!(merged["table"], right["table"]);
+
+        let user: Value = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            rulers = [80]
+            language-server = { command = "ra-multiplex" }
+
+            [[language]]
+            name = "new"
+            file-types = ["new"]
+            "#,
+        )
+        .unwrap();
+        let merged = merge_toml_values(default_lang_config(), user, 3);
+        let languages = merged["language"].as_array().unwrap();
+        let rust = languages
+            .iter()
+            .find(|language| language["name"].as_str() == Some("rust"))
+            .unwrap();
+        // languages are merged by name, nested arrays are replaced
+        assert_eq!(rust["rulers"].as_array().unwrap().len(), 1);
+        assert_eq!(rust["roots"].as_array().unwrap().len(), 2);
+        assert_eq!(rust["language-server"]["command"].as_str(), Some("ra-multiplex"));
+        assert!(languages.iter().any(|language| language["name"].as_str() == Some("new")));
+        assert!(languages.iter().any(|language| language["name"].as_str() == Some("toml")));
+    }
+}
@@ -0,0 +1,548 @@
+//! A client for the [Language Server Protocol](https://microsoft.github.io/language-server-protocol/).
+
+pub mod client;
+pub mod jsonrpc;
+pub mod snippet;
+pub mod transport;
+
+pub use client::Client;
+pub use jsonrpc::Call;
+pub use lsp_types as lsp;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures_util::stream::SelectAll;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::core::syntax::LanguageConfiguration;
+
+pub type Result<T> = core::result::Result<T, Error>;
+type LanguageId = String;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server replied with an error.
+    Rpc(jsonrpc::Error),
+    Parse(serde_json::Error),
+    IO(std::io::Error),
+    /// The server didn't reply in time.
+    Timeout,
+    /// The server exited.
+    StreamClosed,
+    /// The server doesn't support the request.
+    Unhandled,
+    Other(anyhow::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Rpc(err) => write!(f, "protocol error: {err}"),
+            Error::Parse(err) => write!(f, "failed to parse: {err}"),
+            Error::IO(err) => write!(f, "IO Error: {err}"),
+            Error::Timeout => f.write_str("request timed out"),
+            Error::StreamClosed => f.write_str("server closed the stream"),
+            Error::Unhandled => f.write_str("Unhandled"),
+            Error::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<jsonrpc::Error> for Error {
+    fn from(err: jsonrpc::Error) -> Self {
+        Error::Rpc(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Other(err)
+    }
+}
+
+/// The unit the columns of positions are counted in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OffsetEncoding {
+    /// UTF-8 code units aka bytes
+    Utf8,
+    /// UTF-16 code units, the default of the protocol
+    #[default]
+    Utf16,
+}
+
+pub mod util {
+    use super::*;
+    use crate::core::{diagnostic, line_ending::line_end_char_index, Range, Rope, RopeSlice, Selection, Tendril, Transaction};
+
+    /// Converts an LSP position to a char index in `doc`, `None` if the line is out of bounds.
+    ///
+    /// As the protocol demands, a column past the end of the line is clamped to the end of
+    /// the line, before its line ending.
+    pub fn lsp_pos_to_pos(doc: &Rope, pos: lsp::Position, offset_encoding: OffsetEncoding) -> Option<usize> {
+        let pos_line = pos.line as usize;
+        if pos_line > doc.len_lines() - 1 {
+            return None;
+        }
+
+        let line_start = doc.line_to_char(pos_line);
+        let line_end = line_end_char_index(&doc.slice(..), pos_line);
+        let line = match offset_encoding {
+            OffsetEncoding::Utf8 => doc.char_to_byte(line_start)..doc.char_to_byte(line_end),
+            OffsetEncoding::Utf16 => doc.char_to_utf16_cu(line_start)..doc.char_to_utf16_cu(line_end),
+        };
+        let pos = line.start.saturating_add(pos.character as usize).min(line.end);
+
+        match offset_encoding {
+            OffsetEncoding::Utf8 => doc.try_byte_to_char(pos).ok(),
+            OffsetEncoding::Utf16 => doc.try_utf16_cu_to_char(pos).ok(),
+        }
+    }
+
+    /// Converts a char index of `doc` to an LSP position.
+    pub fn pos_to_lsp_pos(doc: &Rope, pos: usize, offset_encoding: OffsetEncoding) -> lsp::Position {
+        let line = doc.char_to_line(pos);
+        let col = match offset_encoding {
+            OffsetEncoding::Utf8 => doc.char_to_byte(pos) - doc.line_to_byte(line),
+            OffsetEncoding::Utf16 => doc.char_to_utf16_cu(pos) - doc.char_to_utf16_cu(doc.line_to_char(line)),
+        };
+        lsp::Position::new(line as u32, col as u32)
+    }
+
+    /// Converts a range of chars of `doc` to an LSP range.
+    pub fn range_to_lsp_range(doc: &Rope, range: std::ops::Range<usize>, offset_encoding: OffsetEncoding) -> lsp::Range {
+        let start = pos_to_lsp_pos(doc, range.start, offset_encoding);
+        let end = pos_to_lsp_pos(doc, range.end, offset_encoding);
+        lsp::Range::new(start, end)
+    }
+
+    /// Converts an LSP range to a range of chars of `doc`, `None` if it is out of bounds.
+    pub fn lsp_range_to_range(doc: &Rope, range: lsp::Range, offset_encoding: OffsetEncoding) -> Option<std::ops::Range<usize>> {
+        let start = lsp_pos_to_pos(doc, range.start, offset_encoding)?;
+        let end = lsp_pos_to_pos(doc, range.end, offset_encoding)?;
+        Some(start..end)
+    }
+
+    /// Converts a diagnostic of the server to one of `doc`, `None` if it is out of bounds.
+    pub fn lsp_diagnostic_to_diagnostic(
+        doc: &Rope,
+        diagnostic: &lsp::Diagnostic,
+        offset_encoding: OffsetEncoding,
+    ) -> Option<diagnostic::Diagnostic> {
+        use diagnostic::Severity::*;
+
+        let Some(range) = lsp_range_to_range(doc, diagnostic.range, offset_encoding) else {
+            tracing::warn!("lsp position out of bounds - {diagnostic:?}");
+            return None;
+        };
+
+        let severity = diagnostic.severity.and_then(|severity| match severity {
+            lsp::DiagnosticSeverity::ERROR => Some(Error),
+            lsp::DiagnosticSeverity::WARNING => Some(Warning),
+            lsp::DiagnosticSeverity::INFORMATION => Some(Info),
+            lsp::DiagnosticSeverity::HINT => Some(Hint),
+            severity => {
+                tracing::warn!("unrecognized diagnostic severity: {severity:?}");
+                None
+            }
+        });
+
+        Some(diagnostic::Diagnostic {
+            range: diagnostic::Range {
+                start: range.start,
+                end: range.end,
+            },
+            line: doc.char_to_line(range.start),
+            message: diagnostic.message.clone(),
+            severity,
+        })
+    }
+
+    /// Converts text edits of the server to a transaction. Edits that are out of bounds or
+    /// overlap a previous one are dropped.
+    pub fn generate_transaction_from_edits(
+        doc: &Rope,
+        mut edits: Vec<lsp::TextEdit>,
+        offset_encoding: OffsetEncoding,
+    ) -> Transaction {
+        // the edits may come in any order, the changes of a transaction are sorted
+        edits.sort_by_key(|edit| edit.range.start);
+
+        let mut last = 0;
+        let changes = edits.into_iter().filter_map(|edit| {
+            let Some(range) = lsp_range_to_range(doc, edit.range, offset_encoding) else {
+                tracing::warn!("lsp position out of bounds - {edit:?}");
+                return None;
+            };
+            if range.start < last {
+                tracing::warn!("ignoring an overlapping edit - {edit:?}");
+                return None;
+            }
+            last = range.end;
+            let text = (!edit.new_text.is_empty()).then(|| edit.new_text.into());
+            Some((range.start, range.end, text))
+        });
+        Transaction::change(doc, changes)
+    }
+
+    /// The ranges from `start_offset` to `end_offset` chars around the cursor of each range of
+    /// `selection`, clamped so that they stay in `doc` and don't overlap.
+    fn ranges_around_cursors(
+        doc: RopeSlice,
+        selection: &Selection,
+        start_offset: i128,
+        end_offset: i128,
+    ) -> Vec<std::ops::Range<usize>> {
+        let len = doc.len_chars() as i128;
+        let mut last = 0;
+        selection
+            .iter()
+            .map(|range| {
+                let cursor = range.cursor(doc) as i128;
+                let start = ((cursor + start_offset).clamp(0, len) as usize).max(last);
+                let end = ((cursor + end_offset).clamp(0, len) as usize).max(start);
+                last = end;
+                start..end
+            })
+            .collect()
+    }
+
+    /// Replaces the text from `start_offset` to `end_offset` chars around each cursor with
+    /// `new_text`, so that a completion of the primary cursor applies to every cursor. The
+    /// cursors are placed after the inserted text.
+    pub fn generate_transaction_from_completion_edit(
+        doc: &Rope,
+        selection: &Selection,
+        start_offset: i128,
+        end_offset: i128,
+        new_text: String,
+    ) -> Transaction {
+        let ranges = ranges_around_cursors(doc.slice(..), selection, start_offset, end_offset);
+        let new_text = Tendril::from(new_text);
+        let new_len = new_text.chars().count();
+
+        // the position of each range once the previous ones are replaced
+        let mut shift = 0;
+        let cursors = ranges
+            .iter()
+            .map(|range| {
+                let cursor = (range.start as isize + shift) as usize + new_len;
+                shift += new_len as isize - range.len() as isize;
+                Range::point(cursor)
+            })
+            .collect();
+
+        let changes = ranges
+            .into_iter()
+            .map(|range| (range.start, range.end, Some(new_text.clone())));
+        Transaction::change(doc, changes).with_selection(Selection::new(cursors, selection.primary_index()))
+    }
+
+    /// Replaces the text from `start_offset` to `end_offset` chars around each cursor with the
+    /// rendered snippet. The lines of the snippet keep the indentation of the line it is inserted
+    /// on. The placeholders of the first tabstop are selected, or the cursors placed after the
+    /// snippet if it has none. The later tabstops are dropped, there is no jumping to them.
+    pub fn generate_transaction_from_snippet(
+        doc: &Rope,
+        selection: &Selection,
+        start_offset: i128,
+        end_offset: i128,
+        snippet: &snippet::Snippet,
+        line_ending: &str,
+    ) -> Transaction {
+        let text = doc.slice(..);
+        let ranges = ranges_around_cursors(text, selection, start_offset, end_offset);
+
+        let mut shift = 0;
+        let mut selection_ranges = smallvec::SmallVec::new();
+        let mut primary_index = 0;
+        let mut changes = Vec::with_capacity(ranges.len());
+        for (i, range) in ranges.into_iter().enumerate() {
+            let line = text.line(text.char_to_line(range.start));
+            let indent: String = line.chars().take_while(|ch| *ch == ' ' || *ch == '\t').collect();
+            let rendered = snippet.render(&format!("{line_ending}{indent}"));
+
+            let start = (range.start as isize + shift) as usize;
+            let len = rendered.text.chars().count();
+            if i == selection.primary_index() {
+                primary_index = selection_ranges.len();
+            }
+            match rendered.tabstops.first() {
+                // placeholders are selected backwards, so that typing inserts before them
+                Some(tabstop) => selection_ranges.extend(
+                    tabstop
+                        .iter()
+                        .map(|placeholder| Range::new(start + placeholder.end, start + placeholder.start)),
+                ),
+                None => selection_ranges.push(Range::point(start + len)),
+            }
+
+            shift += len as isize - range.len() as isize;
+            changes.push((range.start, range.end, Some(rendered.text)));
+        }
+
+        Transaction::change(doc, changes.into_iter()).with_selection(Selection::new(selection_ranges, primary_index))
+    }
+
+    /// The position `pos` is moved to by inserting `text` at it. Only `\n`, `\r` and `\r\n` are
+    /// line endings for the protocol.
+    pub(crate) fn traverse(pos: lsp::Position, text: RopeSlice, offset_encoding: OffsetEncoding) -> lsp::Position {
+        let lsp::Position { mut line, mut character } = pos;
+
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\n' || ch == '\r' {
+                // consume a \r\n
+                if ch == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                line += 1;
+                character = 0;
+            } else {
+                character += match offset_encoding {
+                    OffsetEncoding::Utf8 => ch.len_utf8(),
+                    OffsetEncoding::Utf16 => ch.len_utf16(),
+                } as u32;
+            }
+        }
+        lsp::Position { line, character }
+    }
+}
+
+/// A request of the server handled by the editor.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MethodCall {
+    WorkDoneProgressCreate(lsp::WorkDoneProgressCreateParams),
+    WorkspaceFolders,
+    WorkspaceConfiguration(lsp::ConfigurationParams),
+}
+
+impl MethodCall {
+    /// Parses a request of the server, `Unhandled` if the editor doesn't support it.
+    pub fn parse(method: &str, params: jsonrpc::Params) -> Result<MethodCall> {
+        use lsp::request::Request;
+        let request = match method {
+            lsp::request::WorkDoneProgressCreate::METHOD => Self::WorkDoneProgressCreate(params.parse()?),
+            lsp::request::WorkspaceFoldersRequest::METHOD => Self::WorkspaceFolders,
+            lsp::request::WorkspaceConfiguration::METHOD => Self::WorkspaceConfiguration(params.parse()?),
+            _ => return Err(Error::Unhandled),
+        };
+        Ok(request)
+    }
+}
+
+/// A notification of the server handled by the editor.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Notification {
+    /// Not sent by the server, but by the transport once the server is initialized.
+    Initialized,
+    /// Sent by the transport when the server exited.
+    Exit,
+    PublishDiagnostics(lsp::PublishDiagnosticsParams),
+    ShowMessage(lsp::ShowMessageParams),
+    LogMessage(lsp::LogMessageParams),
+    ProgressMessage(lsp::ProgressParams),
+}
+
+impl Notification {
+    /// Parses a notification of the server, `Unhandled` if the editor doesn't support it.
+    pub fn parse(method: &str, params: jsonrpc::Params) -> Result<Notification> {
+        use lsp::notification::Notification as _;
+
+        let notification = match method {
+            lsp::notification::Initialized::METHOD => Self::Initialized,
+            lsp::notification::Exit::METHOD => Self::Exit,
+            lsp::notification::PublishDiagnostics::METHOD => Self::PublishDiagnostics(params.parse()?),
+            lsp::notification::ShowMessage::METHOD => Self::ShowMessage(params.parse()?),
+            lsp::notification::LogMessage::METHOD => Self::LogMessage(params.parse()?),
+            lsp::notification::Progress::METHOD => Self::ProgressMessage(params.parse()?),
+            _ => return Err(Error::Unhandled),
+        };
+
+        Ok(notification)
+    }
+}
+
+/// The running language servers, one per language.
+#[derive(Debug)]
+pub struct Registry {
+    inner: HashMap<LanguageId, (usize, Arc<Client>)>,
+
+    counter: usize,
+    /// The requests and notifications of every server, tagged with the id of the server.
+    pub incoming: SelectAll<UnboundedReceiverStream<(usize, Call)>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self {
+            inner: HashMap::new(),
+            counter: 0,
+            incoming: SelectAll::new(),
+        }
+    }
+
+    pub fn get_by_id(&self, id: usize) -> Option<&Client> {
+        self.inner
+            .values()
+            .find(|(client_id, _)| client_id == &id)
+            .map(|(_, client)| client.as_ref())
+    }
+
+    /// Stops tracking the server `id`, e.g. after it exited.
+    pub fn remove_by_id(&mut self, id: usize) {
+        self.inner.retain(|_, (client_id, _)| client_id != &id)
+    }
+
+    /// The language server of `language_config`, started if it isn't running yet. `None` if the
+    /// language has no language server configured.
+    pub fn get(&mut self, language_config: &LanguageConfiguration, doc_path: Option<&Path>) -> Result<Option<Arc<Client>>> {
+        let Some(config) = &language_config.language_server else {
+            return Ok(None);
+        };
+
+        if let Some((_, client)) = self.inner.get(&language_config.language_id) {
+            return Ok(Some(client.clone()));
+        }
+
+        self.counter += 1;
+        let id = self.counter;
+        let (client, incoming, initialize_notify) =
+            Client::start(config, language_config.config.clone(), &language_config.roots, id, doc_path)?;
+        self.incoming.push(UnboundedReceiverStream::new(incoming));
+        let client = Arc::new(client);
+
+        // initialize the server in the background, the messages sent to it meanwhile are held
+        // back by the transport
+        let initializing = client.clone();
+        tokio::spawn(async move {
+            let value = initializing
+                .capabilities
+                .get_or_try_init(|| async { initializing.initialize().await.map(|response| response.capabilities) })
+                .await;
+
+            if let Err(err) = value {
+                tracing::error!("failed to initialize language server: {err}");
+                return;
+            }
+
+            if let Err(err) = initializing.notify::<lsp::notification::Initialized>(lsp::InitializedParams {}) {
+                tracing::error!("failed to send the initialized notification: {err}");
+                return;
+            }
+            initialize_notify.notify_one();
+        });
+
+        self.inner.insert(language_config.language_id.clone(), (id, client.clone()));
+
+        Ok(Some(client))
+    }
+
+    pub fn iter_clients(&self) -> impl Iterator<Item = &Arc<Client>> {
+        self.inner.values().map(|(_, client)| client)
+    }
+}
+
+/// Finds the root of the workspace of the document at `doc_path`: the topmost of its ancestors
+/// containing one of the `root_markers`, without leaving the enclosing git repository. Falls back
+/// to the root of the repository, then to the current directory.
+pub fn find_root(doc_path: Option<&Path>, root_markers: &[String]) -> PathBuf {
+    let current_dir = std::env::current_dir().expect("unable to determine current directory");
+    let start = doc_path
+        .and_then(Path::parent)
+        .map(|dir| current_dir.join(dir))
+        .unwrap_or_else(|| current_dir.clone());
+
+    let mut top_marker = None;
+    for ancestor in start.ancestors() {
+        if root_markers.iter().any(|marker| ancestor.join(marker).exists()) {
+            top_marker = Some(ancestor);
+        }
+
+        if ancestor.join(".git").exists() {
+            // don't go above the repository, which is the root if there is no marker
+            top_marker = top_marker.or(Some(ancestor));
+            break;
+        }
+    }
+
+    top_marker.map_or(current_dir, Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{util::*, *};
+    use crate::core::Rope;
+
+    #[test]
+    fn convert_positions() {
+        let doc = Rope::from("a😀b\nçd\n");
+
+        // the emoji takes two UTF-16 code units and four bytes
+        let pos = pos_to_lsp_pos(&doc, 2, OffsetEncoding::Utf16);
+        assert_eq!(pos, lsp::Position::new(0, 3));
+        assert_eq!(lsp_pos_to_pos(&doc, pos, OffsetEncoding::Utf16), Some(2));
+        let pos = pos_to_lsp_pos(&doc, 2, OffsetEncoding::Utf8);
+        assert_eq!(pos, lsp::Position::new(0, 5));
+        assert_eq!(lsp_pos_to_pos(&doc, pos, OffsetEncoding::Utf8), Some(2));
+
+        let range = range_to_lsp_range(&doc, 4..6, OffsetEncoding::Utf8);
+        assert_eq!(range, lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(1, 3)));
+        assert_eq!(lsp_range_to_range(&doc, range, OffsetEncoding::Utf8), Some(4..6));
+
+        assert_eq!(lsp_pos_to_pos(&doc, lsp::Position::new(5, 0), OffsetEncoding::Utf16), None);
+    }
+
+    #[test]
+    fn clamp_columns_to_the_line_end() {
+        let doc = Rope::from("a😀b\r\nçd\n");
+
+        // a column past the end of the line stays before the line ending
+        for encoding in [OffsetEncoding::Utf8, OffsetEncoding::Utf16] {
+            assert_eq!(lsp_pos_to_pos(&doc, lsp::Position::new(0, 10), encoding), Some(3));
+            assert_eq!(lsp_pos_to_pos(&doc, lsp::Position::new(1, 3), encoding), Some(7));
+            assert_eq!(lsp_pos_to_pos(&doc, lsp::Position::new(1, u32::MAX), encoding), Some(7));
+        }
+    }
+
+    #[test]
+    fn find_root_markers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let src = dir.join("project/crate/src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join("project/Cargo.toml"), "").unwrap();
+        std::fs::write(dir.join("project/crate/Cargo.toml"), "").unwrap();
+
+        let doc = src.join("main.rs");
+        let markers = ["Cargo.toml".to_string()];
+        // the topmost marker wins, e.g. the root of a cargo workspace
+        assert_eq!(find_root(Some(&doc), &markers), dir.join("project"));
+        // without a marker the repository is the root
+        assert_eq!(find_root(Some(&doc), &[]), dir);
+    }
+}
@@ -0,0 +1,688 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    io::{BufReader, BufWriter},
+    process::{Child, Command},
+    sync::{
+        mpsc::{channel, UnboundedReceiver, UnboundedSender},
+        Notify, OnceCell,
+    },
+};
+
+use super::{
+    find_root, jsonrpc, lsp,
+    transport::{Payload, Transport},
+    util::{pos_to_lsp_pos, traverse},
+    Call, Error, OffsetEncoding, Result,
+};
+use crate::core::{syntax::LanguageServerConfiguration, ChangeSet, Operation, Rope};
+
+#[derive(Debug)]
+pub struct Client {
+    id: usize,
+    name: String,
+    _process: Child,
+    server_tx: UnboundedSender<Payload>,
+    request_counter: AtomicU64,
+    /// Set once the server replied to the initialize request.
+    pub(crate) capabilities: OnceCell<lsp::ServerCapabilities>,
+    config: Option<Value>,
+    root_path: PathBuf,
+    root_uri: Option<lsp::Url>,
+    /// Seconds to wait for the response to a request.
+    req_timeout: u64,
+}
+
+impl Client {
+    /// Spawns the server of `config` for the workspace of the document at `doc_path`, see
+    /// [`find_root`]. The server still needs to be initialized.
+    #[allow(clippy::type_complexity)]
+    pub fn start(
+        config: &LanguageServerConfiguration,
+        settings: Option<Value>,
+        root_markers: &[String],
+        id: usize,
+        doc_path: Option<&Path>,
+    ) -> Result<(Self, UnboundedReceiver<(usize, Call)>, Arc<Notify>)> {
+        // resolve the command first, to report a missing server clearly
+        let cmd = which(&config.command).with_context(|| format!("'{}' not found in $PATH", config.command))?;
+
+        let process = Command::new(cmd)
+            .envs(&config.environment)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // make sure the process is reaped on drop
+            .kill_on_drop(true)
+            .spawn();
+
+        let mut process = process?;
+
+        let writer = BufWriter::new(process.stdin.take().expect("Failed to open stdin"));
+        let reader = BufReader::new(process.stdout.take().expect("Failed to open stdout"));
+        let stderr = BufReader::new(process.stderr.take().expect("Failed to open stderr"));
+
+        let name = config.command.clone();
+        let (server_rx, server_tx, initialize_notify) = Transport::start(reader, writer, stderr, id, name.clone());
+
+        let root_path = find_root(doc_path, root_markers);
+        let root_uri = lsp::Url::from_file_path(&root_path).ok();
+
+        let client = Self {
+            id,
+            name,
+            _process: process,
+            server_tx,
+            request_counter: AtomicU64::new(0),
+            capabilities: OnceCell::new(),
+            config: settings,
+            root_path,
+            root_uri,
+            req_timeout: config.timeout,
+        };
+
+        Ok((client, server_rx, initialize_notify))
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    pub fn root_uri(&self) -> Option<&lsp::Url> {
+        self.root_uri.as_ref()
+    }
+
+    pub fn config(&self) -> Option<&Value> {
+        self.config.as_ref()
+    }
+
+    fn next_request_id(&self) -> jsonrpc::Id {
+        let id = self.request_counter.fetch_add(1, Ordering::Relaxed);
+        jsonrpc::Id::Num(id)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.capabilities.get().is_some()
+    }
+
+    /// The capabilities of the server.
+    ///
+    /// # Panics
+    ///
+    /// If the server isn't initialized yet.
+    pub fn capabilities(&self) -> &lsp::ServerCapabilities {
+        self.capabilities.get().expect("language server not yet initialized!")
+    }
+
+    pub fn offset_encoding(&self) -> OffsetEncoding {
+        match self
+            .capabilities
+            .get()
+            .and_then(|capabilities| capabilities.position_encoding.as_ref())
+        {
+            Some(encoding) if *encoding == lsp::PositionEncodingKind::UTF8 => OffsetEncoding::Utf8,
+            _ => OffsetEncoding::Utf16,
+        }
+    }
+
+    /// Sends a request to the server, the returned future resolves to its response.
+    pub fn call<R: lsp::request::Request>(&self, params: R::Params) -> impl Future<Output = Result<Value>>
+    where
+        R::Params: Serialize,
+    {
+        let (tx, mut rx) = channel::<Result<Value>>(1);
+        // the request is sent right away, so that requests and notifications keep their order
+        let sent = serde_json::to_value(params).map_err(Error::from).and_then(|params| {
+            let request = jsonrpc::MethodCall {
+                jsonrpc: Some(jsonrpc::Version),
+                id: self.next_request_id(),
+                method: R::METHOD.to_string(),
+                params: value_into_params(params),
+            };
+            self.server_tx
+                .send(Payload::Request {
+                    chan: tx,
+                    value: request,
+                })
+                .map_err(|err| Error::Other(err.into()))
+        });
+        let timeout_secs = self.req_timeout;
+
+        async move {
+            sent?;
+            tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), rx.recv())
+                .await
+                .map_err(|_| Error::Timeout)?
+                .ok_or(Error::StreamClosed)?
+        }
+    }
+
+    /// Sends a notification to the server.
+    pub fn notify<R: lsp::notification::Notification>(&self, params: R::Params) -> Result<()>
+    where
+        R::Params: Serialize,
+    {
+        let params = serde_json::to_value(params)?;
+
+        let notification = jsonrpc::Notification {
+            jsonrpc: Some(jsonrpc::Version),
+            method: R::METHOD.to_string(),
+            params: value_into_params(params),
+        };
+
+        self.server_tx
+            .send(Payload::Notification(notification))
+            .map_err(|err| Error::Other(err.into()))
+    }
+
+    /// Replies to the request `id` of the server.
+    pub fn reply(&self, id: jsonrpc::Id, result: core::result::Result<Value, jsonrpc::Error>) -> Result<()> {
+        let output = jsonrpc::Output::new(result, id);
+        self.server_tx
+            .send(Payload::Response(output))
+            .map_err(|err| Error::Other(err.into()))
+    }
+
+    // -------------------------------------------------------------------------------------------
+    // General messages
+    // -------------------------------------------------------------------------------------------
+
+    pub(crate) async fn initialize(&self) -> Result<lsp::InitializeResult> {
+        #[allow(deprecated)]
+        let params = lsp::InitializeParams {
+            process_id: Some(std::process::id()),
+            workspace_folders: Some(self.workspace_folders()),
+            // root_path is obsolete, but some servers still rely on it
+            root_path: self.root_path.to_str().map(String::from),
+            root_uri: self.root_uri.clone(),
+            initialization_options: self.config.clone(),
+            capabilities: lsp::ClientCapabilities {
+                workspace: Some(lsp::WorkspaceClientCapabilities {
+                    configuration: Some(true),
+                    workspace_folders: Some(true),
+                    ..Default::default()
+                }),
+                text_document: Some(lsp::TextDocumentClientCapabilities {
+                    synchronization: Some(lsp::TextDocumentSyncClientCapabilities {
+                        did_save: Some(true),
+                        ..Default::default()
+                    }),
+                    completion: Some(lsp::CompletionClientCapabilities {
+                        completion_item: Some(lsp::CompletionItemCapability {
+                            snippet_support: Some(true),
+                            insert_replace_support: Some(true),
+                            // the documentation is shown as is, markdown is still readable
+                            documentation_format: Some(vec![lsp::MarkupKind::PlainText, lsp::MarkupKind::Markdown]),
+                            resolve_support: Some(lsp::CompletionItemCapabilityResolveSupport {
+                                properties: vec![
+                                    String::from("documentation"),
+                                    String::from("detail"),
+                                    String::from("additionalTextEdits"),
+                                ],
+                            }),
+                            ..Default::default()
+                        }),
+                        context_support: Some(true),
+                        ..Default::default()
+                    }),
+                    document_symbol: Some(lsp::DocumentSymbolClientCapabilities {
+                        hierarchical_document_symbol_support: Some(true),
+                        ..Default::default()
+                    }),
+                    publish_diagnostics: Some(lsp::PublishDiagnosticsClientCapabilities {
+                        version_support: Some(true),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                window: Some(lsp::WindowClientCapabilities {
+                    work_done_progress: Some(true),
+                    ..Default::default()
+                }),
+                general: Some(lsp::GeneralClientCapabilities {
+                    position_encodings: Some(vec![lsp::PositionEncodingKind::UTF8, lsp::PositionEncodingKind::UTF16]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            trace: None,
+            client_info: Some(lsp::ClientInfo {
+                name: String::from(env!("CARGO_PKG_NAME")),
+                version: Some(String::from(env!("CARGO_PKG_VERSION"))),
+            }),
+            locale: None,
+        };
+
+        let response = self.call::<lsp::request::Initialize>(params).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    pub async fn shutdown(&self) -> Result<()> {
+        self.call::<lsp::request::Shutdown>(()).await?;
+        Ok(())
+    }
+
+    pub fn exit(&self) -> Result<()> {
+        self.notify::<lsp::notification::Exit>(())
+    }
+
+    /// Tries to shut down the language server but returns early if the server doesn't respond.
+    pub async fn shutdown_and_exit(&self) -> Result<()> {
+        self.shutdown().await?;
+        self.exit()
+    }
+
+    /// Forcefully shuts down the language server ignoring any errors.
+    pub async fn force_shutdown(&self) -> Result<()> {
+        if let Err(err) = self.shutdown().await {
+            tracing::warn!("language server failed to terminate gracefully - {err}");
+        }
+        self.exit()
+    }
+
+    // -------------------------------------------------------------------------------------------
+    // Text document
+    // -------------------------------------------------------------------------------------------
+
+    pub fn text_document_did_open(&self, uri: lsp::Url, version: i32, doc: &Rope, language_id: String) -> Result<()> {
+        self.notify::<lsp::notification::DidOpenTextDocument>(lsp::DidOpenTextDocumentParams {
+            text_document: lsp::TextDocumentItem {
+                uri,
+                language_id,
+                version,
+                text: String::from(doc),
+            },
+        })
+    }
+
+    /// Converts the changes of `changeset`, turning `old_text` into `new_text`, to the events of
+    /// the protocol.
+    ///
+    /// The changes of a changeset are relative to the original text while the events are applied
+    /// one after the other, so the positions of an event account for the previous ones.
+    pub fn changeset_to_changes(
+        old_text: &Rope,
+        new_text: &Rope,
+        changeset: &ChangeSet,
+        offset_encoding: OffsetEncoding,
+    ) -> Vec<lsp::TextDocumentContentChangeEvent> {
+        let mut iter = changeset.changes().iter().peekable();
+        let mut old_pos = 0;
+        let mut new_pos = 0;
+
+        let mut changes = Vec::new();
+
+        let old_text = old_text.slice(..);
+
+        while let Some(change) = iter.next() {
+            let len = match change {
+                Operation::Delete(i) | Operation::Retain(i) => *i,
+                Operation::Insert(_) => 0,
+            };
+            let mut old_end = old_pos + len;
+
+            match change {
+                Operation::Retain(i) => {
+                    new_pos += i;
+                }
+                Operation::Delete(_) => {
+                    let start = pos_to_lsp_pos(new_text, new_pos, offset_encoding);
+                    let end = traverse(start, old_text.slice(old_pos..old_end), offset_encoding);
+
+                    changes.push(lsp::TextDocumentContentChangeEvent {
+                        range: Some(lsp::Range::new(start, end)),
+                        text: String::new(),
+                        range_length: None,
+                    });
+                }
+                Operation::Insert(s) => {
+                    let start = pos_to_lsp_pos(new_text, new_pos, offset_encoding);
+
+                    new_pos += s.chars().count();
+
+                    // a subsequent delete means a replacement, consume it
+                    let end = if let Some(Operation::Delete(len)) = iter.peek() {
+                        old_end = old_pos + len;
+                        let end = traverse(start, old_text.slice(old_pos..old_end), offset_encoding);
+
+                        iter.next();
+
+                        end
+                    } else {
+                        start
+                    };
+
+                    changes.push(lsp::TextDocumentContentChangeEvent {
+                        range: Some(lsp::Range::new(start, end)),
+                        text: s.to_string(),
+                        range_length: None,
+                    });
+                }
+            }
+            old_pos = old_end;
+        }
+
+        changes
+    }
+
+    /// Notifies the server of the changes of `changeset`, according to the kind of synchronization
+    /// it supports. `None` if it doesn't want to be notified.
+    pub fn text_document_did_change(
+        &self,
+        text_document: lsp::VersionedTextDocumentIdentifier,
+        old_text: &Rope,
+        new_text: &Rope,
+        changeset: &ChangeSet,
+    ) -> Option<Result<()>> {
+        let sync_kind = match self.capabilities().text_document_sync.as_ref()? {
+            lsp::TextDocumentSyncCapability::Kind(kind) => *kind,
+            lsp::TextDocumentSyncCapability::Options(options) => options.change?,
+        };
+
+        let changes = match sync_kind {
+            lsp::TextDocumentSyncKind::FULL => {
+                vec![lsp::TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: new_text.to_string(),
+                }]
+            }
+            lsp::TextDocumentSyncKind::INCREMENTAL => {
+                Self::changeset_to_changes(old_text, new_text, changeset, self.offset_encoding())
+            }
+            // NONE and unknown kinds
+            _ => return None,
+        };
+
+        Some(
+            self.notify::<lsp::notification::DidChangeTextDocument>(lsp::DidChangeTextDocumentParams {
+                text_document,
+                content_changes: changes,
+            }),
+        )
+    }
+
+    pub fn text_document_did_close(&self, text_document: lsp::TextDocumentIdentifier) -> Result<()> {
+        self.notify::<lsp::notification::DidCloseTextDocument>(lsp::DidCloseTextDocumentParams { text_document })
+    }
+
+    /// Notifies the server that the document was written to disk. `None` if it doesn't want to be
+    /// notified.
+    pub fn text_document_did_save(&self, text_document: lsp::TextDocumentIdentifier, text: &Rope) -> Option<Result<()>> {
+        let include_text = match self.capabilities().text_document_sync.as_ref()? {
+            lsp::TextDocumentSyncCapability::Kind(_) => false,
+            lsp::TextDocumentSyncCapability::Options(options) => match options.save.as_ref()? {
+                lsp::TextDocumentSyncSaveOptions::Supported(true) => false,
+                lsp::TextDocumentSyncSaveOptions::SaveOptions(lsp::SaveOptions { include_text }) => include_text.unwrap_or(false),
+                lsp::TextDocumentSyncSaveOptions::Supported(false) => return None,
+            },
+        };
+
+        Some(
+            self.notify::<lsp::notification::DidSaveTextDocument>(lsp::DidSaveTextDocumentParams {
+                text_document,
+                text: include_text.then(|| text.into()),
+            }),
+        )
+    }
+
+    // -------------------------------------------------------------------------------------------
+    // Language features
+    // -------------------------------------------------------------------------------------------
+
+    /// Requests the completions at `position`. `None` if the server doesn't complete.
+    pub fn completion(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+        context: Option<lsp::CompletionContext>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        self.capabilities().completion_provider.as_ref()?;
+
+        let params = lsp::CompletionParams {
+            text_document_position: lsp::TextDocumentPositionParams { text_document, position },
+            context,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+        Some(self.call::<lsp::request::Completion>(params))
+    }
+
+    /// Requests the properties left out of a completion item, e.g. its documentation. `None` if
+    /// the server can't resolve items.
+    pub fn resolve_completion_item(&self, completion_item: lsp::CompletionItem) -> Option<impl Future<Output = Result<Value>>> {
+        let completion_provider = self.capabilities().completion_provider.as_ref()?;
+        if completion_provider.resolve_provider != Some(true) {
+            return None;
+        }
+
+        Some(self.call::<lsp::request::ResolveCompletionItem>(completion_item))
+    }
+
+    fn goto_request<
+        T: lsp::request::Request<Params = lsp::GotoDefinitionParams, Result = Option<lsp::GotoDefinitionResponse>>,
+    >(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> impl Future<Output = Result<Value>> {
+        let params = lsp::GotoDefinitionParams {
+            text_document_position_params: lsp::TextDocumentPositionParams { text_document, position },
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+        self.call::<T>(params)
+    }
+
+    /// Requests the definition of the symbol at `position`. `None` if the server doesn't support
+    /// it, and likewise for the other goto requests.
+    pub fn goto_definition(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().definition_provider {
+            Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_)) => {}
+            _ => return None,
+        }
+        Some(self.goto_request::<lsp::request::GotoDefinition>(text_document, position))
+    }
+
+    pub fn goto_declaration(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().declaration_provider {
+            Some(
+                lsp::DeclarationCapability::Simple(true)
+                | lsp::DeclarationCapability::RegistrationOptions(_)
+                | lsp::DeclarationCapability::Options(_),
+            ) => {}
+            _ => return None,
+        }
+        Some(self.goto_request::<lsp::request::GotoDeclaration>(text_document, position))
+    }
+
+    pub fn goto_type_definition(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().type_definition_provider {
+            Some(lsp::TypeDefinitionProviderCapability::Simple(true) | lsp::TypeDefinitionProviderCapability::Options(_)) => {}
+            _ => return None,
+        }
+        Some(self.goto_request::<lsp::request::GotoTypeDefinition>(text_document, position))
+    }
+
+    pub fn goto_implementation(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().implementation_provider {
+            Some(lsp::ImplementationProviderCapability::Simple(true) | lsp::ImplementationProviderCapability::Options(_)) => {}
+            _ => return None,
+        }
+        Some(self.goto_request::<lsp::request::GotoImplementation>(text_document, position))
+    }
+
+    /// Requests the references to the symbol at `position`, including its declaration.
+    pub fn goto_reference(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().references_provider {
+            Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_)) => {}
+            _ => return None,
+        }
+
+        let params = lsp::ReferenceParams {
+            text_document_position: lsp::TextDocumentPositionParams { text_document, position },
+            context: lsp::ReferenceContext {
+                include_declaration: true,
+            },
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+        Some(self.call::<lsp::request::References>(params))
+    }
+
+    /// Requests the symbols of a document, e.g. its functions and types.
+    pub fn document_symbols(&self, text_document: lsp::TextDocumentIdentifier) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().document_symbol_provider {
+            Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_)) => {}
+            _ => return None,
+        }
+
+        let params = lsp::DocumentSymbolParams {
+            text_document,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+        Some(self.call::<lsp::request::DocumentSymbolRequest>(params))
+    }
+
+    /// Requests the symbols of the workspace matching `query`.
+    pub fn workspace_symbols(&self, query: String) -> Option<impl Future<Output = Result<Value>>> {
+        match self.capabilities().workspace_symbol_provider {
+            Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_)) => {}
+            _ => return None,
+        }
+
+        let params = lsp::WorkspaceSymbolParams {
+            query,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+        Some(self.call::<lsp::request::WorkspaceSymbolRequest>(params))
+    }
+
+    // -------------------------------------------------------------------------------------------
+    // Workspace
+    // -------------------------------------------------------------------------------------------
+
+    pub fn did_change_configuration(&self, settings: Value) -> Result<()> {
+        self.notify::<lsp::notification::DidChangeConfiguration>(lsp::DidChangeConfigurationParams { settings })
+    }
+
+    /// The workspace folders of the server, only its root.
+    pub fn workspace_folders(&self) -> Vec<lsp::WorkspaceFolder> {
+        self.root_uri
+            .iter()
+            .map(|uri| lsp::WorkspaceFolder {
+                name: self
+                    .root_path
+                    .file_name()
+                    .map_or_else(|| "/".into(), |name| name.to_string_lossy().into_owned()),
+                uri: uri.clone(),
+            })
+            .collect()
+    }
+}
+
+fn value_into_params(value: Value) -> jsonrpc::Params {
+    use jsonrpc::Params;
+
+    match value {
+        Value::Null => Params::None,
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => Params::Array(vec![value]),
+        Value::Array(vec) => Params::Array(vec),
+        Value::Object(map) => Params::Map(map),
+    }
+}
+
+/// Finds `command` in the directories of `$PATH`, unless it is a path.
+fn which(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{Selection, Transaction};
+
+    #[test]
+    fn changeset_to_changes() {
+        let old = Rope::from("hello\nworld\n");
+        let mut new = old.clone();
+        // replace the first char, insert before "world" and delete the last newline
+        let transaction = Transaction::change(
+            &old,
+            [(0, 1, Some("J".into())), (6, 6, Some("big ".into())), (11, 12, None)].into_iter(),
+        )
+        .with_selection(Selection::point(0));
+        transaction.apply(&mut new);
+        assert_eq!(new, "Jello\nbig world");
+
+        let changes = Client::changeset_to_changes(&old, &new, transaction.changes(), OffsetEncoding::Utf16);
+        let changes: Vec<_> = changes
+            .into_iter()
+            .map(|change| {
+                let range = change.range.unwrap();
+                (
+                    range.start.line,
+                    range.start.character,
+                    range.end.line,
+                    range.end.character,
+                    change.text,
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (0, 0, 0, 1, "J".to_string()),
+                (1, 0, 1, 0, "big ".to_string()),
+                (1, 9, 2, 0, String::new()),
+            ]
+        );
+    }
+}
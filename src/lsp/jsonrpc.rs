@@ -0,0 +1,334 @@
+//! The JSON-RPC 2.0 messages exchanged with language servers, see
+//! <https://www.jsonrpc.org/specification>.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    ParseError,
+    InvalidRequest,
+    MethodNotFound,
+    InvalidParams,
+    InternalError,
+    ServerError(i64),
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> i64 {
+        match *self {
+            ErrorCode::ParseError => -32700,
+            ErrorCode::InvalidRequest => -32600,
+            ErrorCode::MethodNotFound => -32601,
+            ErrorCode::InvalidParams => -32602,
+            ErrorCode::InternalError => -32603,
+            ErrorCode::ServerError(code) => code,
+        }
+    }
+}
+
+impl From<i64> for ErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            -32700 => ErrorCode::ParseError,
+            -32600 => ErrorCode::InvalidRequest,
+            -32601 => ErrorCode::MethodNotFound,
+            -32602 => ErrorCode::InvalidParams,
+            -32603 => ErrorCode::InternalError,
+            code => ErrorCode::ServerError(code),
+        }
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_i64(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let code: i64 = Deserialize::deserialize(deserializer)?;
+        Ok(ErrorCode::from(code))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode) -> Self {
+        let message = match code {
+            ErrorCode::ParseError => "Parse error",
+            ErrorCode::InvalidRequest => "Invalid request",
+            ErrorCode::MethodNotFound => "Method not found",
+            ErrorCode::InvalidParams => "Invalid params",
+            ErrorCode::InternalError => "Internal error",
+            ErrorCode::ServerError(_) => "Server error",
+        };
+
+        Error {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    pub fn invalid_params<M: Into<String>>(message: M) -> Self {
+        Error {
+            code: ErrorCode::InvalidParams,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The id of a request, used to match it with its response.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Id {
+    Null,
+    Num(u64),
+    Str(String),
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Id::Null => f.write_str("null"),
+            Id::Num(num) => write!(f, "{num}"),
+            Id::Str(string) => f.write_str(string),
+        }
+    }
+}
+
+/// Only version 2.0 of the protocol is supported.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Version;
+
+impl Serialize for Version {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str("2.0")
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match String::deserialize(deserializer)?.as_str() {
+            "2.0" => Ok(Version),
+            version => Err(serde::de::Error::custom(format!("unsupported jsonrpc version: {version}"))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Params {
+    #[default]
+    None,
+    Array(Vec<Value>),
+    Map(Map<String, Value>),
+}
+
+impl Params {
+    /// Deserializes the params into the params type of a request or notification.
+    pub fn parse<D: DeserializeOwned>(self) -> Result<D, Error> {
+        let value: Value = self.into();
+        serde_json::from_value(value).map_err(|err| Error::invalid_params(format!("Invalid params: {err}")))
+    }
+
+    pub fn is_none(&self) -> bool {
+        self == &Params::None
+    }
+}
+
+impl From<Params> for Value {
+    fn from(params: Params) -> Value {
+        match params {
+            Params::Array(vec) => Value::Array(vec),
+            Params::Map(map) => Value::Object(map),
+            Params::None => Value::Null,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodCall {
+    pub jsonrpc: Option<Version>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Params::is_none")]
+    pub params: Params,
+    pub id: Id,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notification {
+    pub jsonrpc: Option<Version>,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Params::is_none")]
+    pub params: Params,
+}
+
+/// A request or a notification.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Call {
+    MethodCall(MethodCall),
+    Notification(Notification),
+    /// A call that couldn't be parsed, the id is kept to reply with an error.
+    Invalid {
+        #[serde(default = "default_id")]
+        id: Id,
+    },
+}
+
+fn default_id() -> Id {
+    Id::Null
+}
+
+impl From<MethodCall> for Call {
+    fn from(method_call: MethodCall) -> Self {
+        Call::MethodCall(method_call)
+    }
+}
+
+impl From<Notification> for Call {
+    fn from(notification: Notification) -> Self {
+        Call::Notification(notification)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Success {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<Version>,
+    // some servers omit the result of requests returning nothing
+    #[serde(default)]
+    pub result: Value,
+    pub id: Id,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Failure {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<Version>,
+    pub error: Error,
+    pub id: Id,
+}
+
+/// The response to a request.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Output {
+    // Failure comes first, a response without a result would otherwise be parsed as a success
+    // with a `null` result.
+    Failure(Failure),
+    Success(Success),
+}
+
+impl Output {
+    pub fn id(&self) -> &Id {
+        match self {
+            Output::Success(success) => &success.id,
+            Output::Failure(failure) => &failure.id,
+        }
+    }
+
+    pub fn new(result: Result<Value, Error>, id: Id) -> Self {
+        match result {
+            Ok(result) => Output::Success(Success {
+                jsonrpc: Some(Version),
+                result,
+                id,
+            }),
+            Err(error) => Output::Failure(Failure {
+                jsonrpc: Some(Version),
+                error,
+                id,
+            }),
+        }
+    }
+}
+
+impl From<Output> for Result<Value, Error> {
+    fn from(output: Output) -> Self {
+        match output {
+            Output::Success(success) => Ok(success.result),
+            Output::Failure(failure) => Err(failure.error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_messages() {
+        let call: Call = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"update","params":[1,2],"id":1}"#).unwrap();
+        assert_eq!(
+            call,
+            Call::MethodCall(MethodCall {
+                jsonrpc: Some(Version),
+                method: "update".into(),
+                params: Params::Array(vec![Value::from(1), Value::from(2)]),
+                id: Id::Num(1),
+            })
+        );
+
+        let call: Call = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"exit"}"#).unwrap();
+        assert!(matches!(
+            call,
+            Call::Notification(Notification {
+                params: Params::None,
+                ..
+            })
+        ));
+
+        let output: Output = serde_json::from_str(r#"{"jsonrpc":"2.0","result":null,"id":"a"}"#).unwrap();
+        assert_eq!(output, Output::new(Ok(Value::Null), Id::Str("a".into())));
+
+        let output: Output =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"nope"},"id":2}"#).unwrap();
+        let result: Result<Value, Error> = output.into();
+        assert_eq!(result.unwrap_err().code, ErrorCode::MethodNotFound);
+
+        let request = serde_json::to_string(&MethodCall {
+            jsonrpc: Some(Version),
+            method: "shutdown".into(),
+            params: Params::None,
+            id: Id::Num(3),
+        })
+        .unwrap();
+        assert_eq!(request, r#"{"jsonrpc":"2.0","method":"shutdown","id":3}"#);
+    }
+}
@@ -0,0 +1,364 @@
+//! Parses the snippets of completion items, see the [snippet syntax].
+//!
+//! Snippets that don't follow the grammar are still inserted: a `$` that doesn't start a valid
+//! tabstop, placeholder, choice or variable is kept as text.
+//!
+//! [snippet syntax]: https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#snippet_syntax
+
+use std::ops::Range;
+
+use crate::core::Tendril;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetElement {
+    /// `$1` or `${1}`.
+    Tabstop {
+        tabstop: usize,
+    },
+    /// `${1:value}`, the value may contain other elements.
+    Placeholder {
+        tabstop: usize,
+        value: Vec<SnippetElement>,
+    },
+    /// `${1|one,two|}`, the first choice is inserted.
+    Choice {
+        tabstop: usize,
+        choices: Vec<String>,
+    },
+    /// `$NAME`, `${NAME}` or `${NAME:default}`. Variables aren't resolved, their default is
+    /// inserted. A transform, e.g. `${NAME/regex/format/}`, is dropped.
+    Variable {
+        name: String,
+        default: Option<Vec<SnippetElement>>,
+    },
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    elements: Vec<SnippetElement>,
+}
+
+/// A snippet rendered to text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedSnippet {
+    pub text: Tendril,
+    /// The char ranges of each tabstop in `text`, in the order they are visited: by increasing
+    /// number, with the final tabstop `$0` last.
+    pub tabstops: Vec<Vec<Range<usize>>>,
+}
+
+pub fn parse(snippet: &str) -> Snippet {
+    let mut parser = Parser { input: snippet, pos: 0 };
+    Snippet {
+        elements: parser.elements(false),
+    }
+}
+
+impl Snippet {
+    pub fn elements(&self) -> &[SnippetElement] {
+        &self.elements
+    }
+
+    /// Renders the snippet, replacing its line breaks with `newline`, e.g. to keep the
+    /// indentation of the line it is inserted on.
+    pub fn render(&self, newline: &str) -> RenderedSnippet {
+        let mut text = Tendril::new();
+        let mut tabstops: Vec<(usize, Vec<Range<usize>>)> = Vec::new();
+        render_elements(&self.elements, newline, &mut text, &mut tabstops);
+
+        // `$0` is visited last
+        tabstops.sort_by_key(|&(tabstop, _)| if tabstop == 0 { usize::MAX } else { tabstop });
+        RenderedSnippet {
+            text,
+            tabstops: tabstops.into_iter().map(|(_, ranges)| ranges).collect(),
+        }
+    }
+}
+
+fn render_elements(
+    elements: &[SnippetElement],
+    newline: &str,
+    text: &mut Tendril,
+    tabstops: &mut Vec<(usize, Vec<Range<usize>>)>,
+) {
+    fn add_tabstop(tabstops: &mut Vec<(usize, Vec<Range<usize>>)>, tabstop: usize, range: Range<usize>) {
+        match tabstops.iter_mut().find(|(i, _)| *i == tabstop) {
+            Some((_, ranges)) => ranges.push(range),
+            None => tabstops.push((tabstop, vec![range])),
+        }
+    }
+    let len = |text: &Tendril| text.chars().count();
+
+    for element in elements {
+        match element {
+            SnippetElement::Tabstop { tabstop } => {
+                let pos = len(text);
+                add_tabstop(tabstops, *tabstop, pos..pos);
+            }
+            SnippetElement::Placeholder { tabstop, value } => {
+                let start = len(text);
+                render_elements(value, newline, text, tabstops);
+                add_tabstop(tabstops, *tabstop, start..len(text));
+            }
+            SnippetElement::Choice { tabstop, choices } => {
+                let start = len(text);
+                text.push_str(choices.first().map_or("", String::as_str));
+                add_tabstop(tabstops, *tabstop, start..len(text));
+            }
+            SnippetElement::Variable { default, .. } => {
+                if let Some(default) = default {
+                    render_elements(default, newline, text, tabstops);
+                }
+            }
+            SnippetElement::Text(value) => {
+                for (i, line) in value.split('\n').enumerate() {
+                    if i > 0 {
+                        text.push_str(newline);
+                    }
+                    text.push_str(line);
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Byte offset of the next char.
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    /// Consumes `ch` if it is the next char.
+    fn eat(&mut self, ch: char) -> bool {
+        let matches = self.peek() == Some(ch);
+        if matches {
+            self.pos += ch.len_utf8();
+        }
+        matches
+    }
+
+    fn int(&mut self) -> Option<usize> {
+        let digits = self.input[self.pos..].bytes().take_while(u8::is_ascii_digit).count();
+        let int = self.input[self.pos..self.pos + digits].parse().ok()?;
+        self.pos += digits;
+        Some(int)
+    }
+
+    fn var_name(&mut self) -> Option<String> {
+        let rest = &self.input[self.pos..];
+        if !rest.starts_with(|ch: char| ch == '_' || ch.is_ascii_alphabetic()) {
+            return None;
+        }
+        let len = rest.bytes().take_while(|&b| b == b'_' || b.is_ascii_alphanumeric()).count();
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    /// Parses elements until the end of the input, or the `}` closing a placeholder or variable
+    /// if `nested`. The `}` isn't consumed.
+    fn elements(&mut self, nested: bool) -> Vec<SnippetElement> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+
+        while let Some(ch) = self.peek() {
+            match ch {
+                '}' if nested => break,
+                '\\' => {
+                    self.bump();
+                    match self.peek() {
+                        Some(ch @ ('$' | '}' | '\\')) => {
+                            self.bump();
+                            text.push(ch);
+                        }
+                        _ => text.push('\\'),
+                    }
+                }
+                '$' => {
+                    let start = self.pos;
+                    self.bump();
+                    match self.dollar() {
+                        Some(element) => {
+                            if !text.is_empty() {
+                                elements.push(SnippetElement::Text(std::mem::take(&mut text)));
+                            }
+                            elements.push(element);
+                        }
+                        None => {
+                            // not a valid element, the `$` is text
+                            self.pos = start + 1;
+                            text.push('$');
+                        }
+                    }
+                }
+                ch => {
+                    self.bump();
+                    text.push(ch);
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            elements.push(SnippetElement::Text(text));
+        }
+        elements
+    }
+
+    /// Parses the element started by a `$`, `None` if it isn't valid.
+    fn dollar(&mut self) -> Option<SnippetElement> {
+        if let Some(tabstop) = self.int() {
+            return Some(SnippetElement::Tabstop { tabstop });
+        }
+        if let Some(name) = self.var_name() {
+            return Some(SnippetElement::Variable { name, default: None });
+        }
+        if !self.eat('{') {
+            return None;
+        }
+
+        if let Some(tabstop) = self.int() {
+            return if self.eat('}') {
+                Some(SnippetElement::Tabstop { tabstop })
+            } else if self.eat(':') {
+                let value = self.elements(true);
+                self.eat('}').then_some(SnippetElement::Placeholder { tabstop, value })
+            } else if self.eat('|') {
+                let choices = self.choices()?;
+                Some(SnippetElement::Choice { tabstop, choices })
+            } else {
+                None
+            };
+        }
+
+        let name = self.var_name()?;
+        if self.eat('}') {
+            Some(SnippetElement::Variable { name, default: None })
+        } else if self.eat(':') {
+            let default = self.elements(true);
+            self.eat('}').then_some(SnippetElement::Variable {
+                name,
+                default: Some(default),
+            })
+        } else if self.eat('/') {
+            // skip the transform up to the closing brace
+            loop {
+                match self.bump()? {
+                    '\\' => {
+                        self.bump()?;
+                    }
+                    '}' => return Some(SnippetElement::Variable { name, default: None }),
+                    _ => {}
+                }
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Parses the choices of a choice element, after the opening `|` and up to the closing `|}`.
+    fn choices(&mut self) -> Option<Vec<String>> {
+        let mut choices = Vec::new();
+        let mut choice = String::new();
+        loop {
+            match self.bump()? {
+                '\\' => match self.peek() {
+                    Some(ch @ ('$' | '}' | '\\' | ',' | '|')) => {
+                        self.bump();
+                        choice.push(ch);
+                    }
+                    _ => choice.push('\\'),
+                },
+                ',' => choices.push(std::mem::take(&mut choice)),
+                '|' => {
+                    choices.push(choice);
+                    return self.eat('}').then_some(choices);
+                }
+                ch => choice.push(ch),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use SnippetElement::*;
+
+    #[test]
+    fn parse_snippets() {
+        assert_eq!(
+            parse("match(${1:value}) {\n\t$0\n}").elements(),
+            [
+                Text("match(".into()),
+                Placeholder {
+                    tabstop: 1,
+                    value: vec![Text("value".into())]
+                },
+                Text(") {\n\t".into()),
+                Tabstop { tabstop: 0 },
+                Text("\n}".into()),
+            ]
+        );
+        assert_eq!(
+            parse("${1:a ${2:b}} ${3|x,y\\,z|} ${TM_FILENAME/(.*)/$1/} $NAME").elements(),
+            [
+                Placeholder {
+                    tabstop: 1,
+                    value: vec![
+                        Text("a ".into()),
+                        Placeholder {
+                            tabstop: 2,
+                            value: vec![Text("b".into())]
+                        }
+                    ]
+                },
+                Text(" ".into()),
+                Choice {
+                    tabstop: 3,
+                    choices: vec!["x".into(), "y,z".into()]
+                },
+                Text(" ".into()),
+                Variable {
+                    name: "TM_FILENAME".into(),
+                    default: None
+                },
+                Text(" ".into()),
+                Variable {
+                    name: "NAME".into(),
+                    default: None
+                },
+            ]
+        );
+        // invalid elements and escaped chars are text
+        assert_eq!(
+            parse("cost: $ 5 ${1:unclosed \\$1 \\}").elements(),
+            [Text("cost: $ 5 ${1:unclosed $1 }".into())]
+        );
+    }
+
+    #[test]
+    fn render_snippets() {
+        let snippet = parse("fn ${1:name}(${2:arg}: ${3:u8}) {\n    $0\n}$1");
+        let rendered = snippet.render("\n  ");
+        assert_eq!(rendered.text, "fn name(arg: u8) {\n      \n  }");
+        assert_eq!(
+            rendered.tabstops,
+            vec![vec![3..7, 29..29], vec![8..11], vec![13..15], vec![25..25]]
+        );
+
+        let rendered = parse("${1:a ${2:b}}c").render("\n");
+        assert_eq!(rendered.text, "a bc");
+        assert_eq!(rendered.tabstops, vec![vec![0..3], vec![2..3]]);
+    }
+}
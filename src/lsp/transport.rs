@@ -0,0 +1,291 @@
+//! Sends and receives the messages of a language server over its stdio, framed by the
+//! `Content-Length` headers of the base protocol.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+    process::{ChildStderr, ChildStdin, ChildStdout},
+    sync::{
+        mpsc::{unbounded_channel, Sender, UnboundedReceiver, UnboundedSender},
+        Mutex, Notify,
+    },
+};
+
+use super::{jsonrpc, Error, Result};
+
+/// A message sent to the language server.
+#[derive(Debug)]
+pub enum Payload {
+    /// A request, its response is sent on `chan`.
+    Request {
+        chan: Sender<Result<Value>>,
+        value: jsonrpc::MethodCall,
+    },
+    Notification(jsonrpc::Notification),
+    /// The response to a request of the server.
+    Response(jsonrpc::Output),
+}
+
+/// A message received from the language server.
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum ServerMessage {
+    Output(jsonrpc::Output),
+    Call(jsonrpc::Call),
+}
+
+#[derive(Debug)]
+pub struct Transport {
+    id: usize,
+    name: String,
+    /// The requests waiting for a response, by id.
+    pending_requests: Mutex<HashMap<jsonrpc::Id, Sender<Result<Value>>>>,
+}
+
+impl Transport {
+    /// Spawns the tasks reading from and writing to the server. The requests and notifications
+    /// of the server are sent on the returned receiver, tagged with `id`. The messages sent on
+    /// the returned sender are held back, or dropped for notifications, until
+    /// `initialize_notify` is notified, except for the `initialize` request.
+    pub fn start(
+        server_stdout: BufReader<ChildStdout>,
+        server_stdin: BufWriter<ChildStdin>,
+        server_stderr: BufReader<ChildStderr>,
+        id: usize,
+        name: String,
+    ) -> (
+        UnboundedReceiver<(usize, jsonrpc::Call)>,
+        UnboundedSender<Payload>,
+        Arc<Notify>,
+    ) {
+        let (client_tx, rx) = unbounded_channel();
+        let (tx, client_rx) = unbounded_channel();
+        let notify = Arc::new(Notify::new());
+
+        let transport = Arc::new(Self {
+            id,
+            name,
+            pending_requests: Mutex::new(HashMap::default()),
+        });
+
+        tokio::spawn(Self::recv(transport.clone(), server_stdout, client_tx.clone()));
+        tokio::spawn(Self::err(transport.clone(), server_stderr));
+        tokio::spawn(Self::send(transport, server_stdin, client_tx, client_rx, notify.clone()));
+
+        (rx, tx, notify)
+    }
+
+    /// Reads the next message, skipping the headers other than `Content-Length`.
+    async fn recv_server_message(
+        reader: &mut (impl AsyncBufRead + Unpin + Send),
+        buffer: &mut String,
+        language_server_name: &str,
+    ) -> Result<ServerMessage> {
+        let mut content_length = None;
+        loop {
+            buffer.clear();
+            if reader.read_line(buffer).await? == 0 {
+                return Err(Error::StreamClosed);
+            };
+
+            match buffer.trim_end() {
+                // an empty line ends the headers
+                "" => break,
+                header => {
+                    if let Some(("Content-Length", value)) = header.split_once(": ") {
+                        content_length = Some(value.parse().context("invalid content length")?);
+                    }
+                    // other headers, like Content-Type, are ignored
+                }
+            }
+        }
+
+        let content_length = content_length.context("missing content length")?;
+        let mut content = vec![0; content_length];
+        reader.read_exact(&mut content).await?;
+        let msg = std::str::from_utf8(&content).context("invalid utf8 from server")?;
+
+        tracing::info!("{language_server_name} <- {msg}");
+
+        let output = serde_json::from_str(msg).map_err(Into::into);
+        if let Err(err) = &output {
+            tracing::error!("{language_server_name} <- failed to parse message: {err}");
+        }
+        output
+    }
+
+    async fn send_payload_to_server(&self, server_stdin: &mut BufWriter<ChildStdin>, payload: Payload) -> Result<()> {
+        let json = match payload {
+            Payload::Request { chan, value } => {
+                self.pending_requests.lock().await.insert(value.id.clone(), chan);
+                serde_json::to_string(&value)?
+            }
+            Payload::Notification(value) => serde_json::to_string(&value)?,
+            Payload::Response(error) => serde_json::to_string(&error)?,
+        };
+        self.send_string_to_server(server_stdin, json).await
+    }
+
+    async fn send_string_to_server(&self, server_stdin: &mut (impl AsyncWrite + Unpin), request: String) -> Result<()> {
+        tracing::info!("{} -> {request}", self.name);
+
+        server_stdin
+            .write_all(format!("Content-Length: {}\r\n\r\n", request.len()).as_bytes())
+            .await?;
+        server_stdin.write_all(request.as_bytes()).await?;
+        server_stdin.flush().await?;
+
+        Ok(())
+    }
+
+    /// Resolves the pending request of a response, or forwards a call of the server to the client.
+    async fn process_server_message(
+        &self,
+        client_tx: &UnboundedSender<(usize, jsonrpc::Call)>,
+        msg: ServerMessage,
+    ) -> Result<()> {
+        match msg {
+            ServerMessage::Output(output) => {
+                let id = output.id().clone();
+                let Some(tx) = self.pending_requests.lock().await.remove(&id) else {
+                    tracing::warn!("{}: no pending request for the response with id {id}", self.name);
+                    return Ok(());
+                };
+                let result = core::result::Result::<Value, jsonrpc::Error>::from(output).map_err(Error::Rpc);
+                // the request may have timed out, its receiver is dropped then
+                let _ = tx.send(result).await;
+            }
+            ServerMessage::Call(call) => {
+                client_tx
+                    .send((self.id, call))
+                    .context("failed to send a message to the client")?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn recv(
+        transport: Arc<Self>,
+        server_stdout: BufReader<ChildStdout>,
+        client_tx: UnboundedSender<(usize, jsonrpc::Call)>,
+    ) {
+        let mut reader = server_stdout;
+        let mut buffer = String::new();
+        loop {
+            match Self::recv_server_message(&mut reader, &mut buffer, &transport.name).await {
+                Ok(msg) => {
+                    if let Err(err) = transport.process_server_message(&client_tx, msg).await {
+                        tracing::error!("{} err: <- {err:?}", transport.name);
+                        break;
+                    }
+                }
+                Err(Error::StreamClosed) => {
+                    // the requests still pending will never be answered
+                    for (id, tx) in transport.pending_requests.lock().await.drain() {
+                        if tx.send(Err(Error::StreamClosed)).await.is_err() {
+                            tracing::error!("Could not close request on a closed channel (id={id})");
+                        }
+                    }
+
+                    // tell the client the server is gone, as if it had sent the exit notification
+                    use lsp_types::notification::{Exit, Notification};
+                    let notification = jsonrpc::Call::Notification(jsonrpc::Notification {
+                        jsonrpc: None,
+                        method: Exit::METHOD.to_string(),
+                        params: jsonrpc::Params::None,
+                    });
+                    let _ = client_tx.send((transport.id, notification));
+                    break;
+                }
+                // a malformed message is skipped
+                Err(err @ (Error::Parse(_) | Error::Other(_))) => {
+                    tracing::error!("{} err: <- {err}", transport.name);
+                }
+                Err(err) => {
+                    tracing::error!("{} err: <- {err}", transport.name);
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn err(transport: Arc<Self>, server_stderr: BufReader<ChildStderr>) {
+        let mut lines = server_stderr.lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            tracing::info!("{} err <- {line}", transport.name);
+        }
+    }
+
+    async fn send(
+        transport: Arc<Self>,
+        mut server_stdin: BufWriter<ChildStdin>,
+        client_tx: UnboundedSender<(usize, jsonrpc::Call)>,
+        mut client_rx: UnboundedReceiver<Payload>,
+        initialize_notify: Arc<Notify>,
+    ) {
+        let mut pending_messages: Vec<Payload> = Vec::new();
+        let mut is_pending = true;
+
+        // Only the initialize request may be sent before the server is initialized.
+        fn is_initialize(payload: &Payload) -> bool {
+            use lsp_types::{
+                notification::{Initialized, Notification},
+                request::{Initialize, Request},
+            };
+            match payload {
+                Payload::Request { value, .. } => value.method == Initialize::METHOD,
+                Payload::Notification(notification) => notification.method == Initialized::METHOD,
+                Payload::Response(_) => false,
+            }
+        }
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = initialize_notify.notified(), if is_pending => {
+                    is_pending = false;
+
+                    // let the client know, so that it can open the documents using the server
+                    use lsp_types::notification::{Initialized, Notification};
+                    let notification = jsonrpc::Call::Notification(jsonrpc::Notification {
+                        jsonrpc: None,
+                        method: Initialized::METHOD.to_string(),
+                        params: jsonrpc::Params::None,
+                    });
+                    if client_tx.send((transport.id, notification)).is_err() {
+                        tracing::error!("{}: client dropped before the server was initialized", transport.name);
+                        return;
+                    }
+
+                    for msg in pending_messages.drain(..) {
+                        tracing::info!("Draining pending message {msg:?}");
+                        if let Err(err) = transport.send_payload_to_server(&mut server_stdin, msg).await {
+                            tracing::error!("{} err: <- {err:?}", transport.name);
+                        }
+                    }
+                }
+                msg = client_rx.recv() => {
+                    let Some(msg) = msg else {
+                        // the client was dropped
+                        break;
+                    };
+                    if is_pending && !is_initialize(&msg) {
+                        // the documents are opened once the server is initialized, changes made
+                        // before that are part of their text
+                        if let Payload::Notification(_) = msg {
+                            continue;
+                        }
+                        tracing::info!("Language server not initialized, delaying request");
+                        pending_messages.push(msg);
+                    } else if let Err(err) = transport.send_payload_to_server(&mut server_stdin, msg).await {
+                        tracing::error!("{} err: <- {err:?}", transport.name);
+                    }
+                }
+            }
+        }
+    }
+}
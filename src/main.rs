@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use crossterm::event::EventStream;
-use toy_helix::{application::Application, config::Config, term::args::Args};
+use toy_helix::{application::Application, config::Config, loader, term::args::Args};
 
 fn main() -> Result<()> {
     let exit_code = run()?;
@@ -15,8 +15,19 @@ async fn run() -> Result<i32> {
 
     tracing::info!("Starting...");
 
-    let config = Config::default();
-    let mut app = Application::new(args, config).context("unable to create new application")?;
+    let config = Config::load(&loader::config_file()).unwrap_or_else(|err| {
+        tracing::warn!("failed to load config.toml, using the default one - {err}");
+        Config::default()
+    });
+    let syn_loader_conf = loader::user_lang_config()
+        .and_then(|config| config.try_into())
+        .unwrap_or_else(|err| {
+            tracing::warn!("failed to load languages.toml, using the default one - {err}");
+            loader::default_lang_config()
+                .try_into()
+                .expect("Could not serialize built-in languages.toml")
+        });
+    let mut app = Application::new(args, config, syn_loader_conf).context("unable to create new application")?;
 
     let exit_code = app.run(&mut EventStream::new()).await?;
 
@@ -24,7 +35,11 @@ async fn run() -> Result<i32> {
 }
 
 fn init_tracing() -> tracing_appender::non_blocking::WorkerGuard {
-    let appender = tracing_appender::rolling::never("/tmp", "toy-helix.log");
+    let log_file = toy_helix::log_file();
+    let appender = tracing_appender::rolling::never(
+        log_file.parent().expect("log file has a parent directory"),
+        log_file.file_name().expect("log file has a name"),
+    );
     let (non_blocking, guard) = tracing_appender::non_blocking(appender);
 
     tracing_subscriber::fmt()
@@ -1,5 +1,6 @@
 pub mod args;
 pub mod commands;
 pub mod compositor;
+pub mod job;
 pub mod keymap;
 pub mod ui;
@@ -1,11 +1,13 @@
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 
-use crate::core::position::Position;
+use crate::core::{encoding, position::Position};
 
 #[derive(Default)]
 pub struct Args {
     pub files: Vec<(PathBuf, Position)>,
+    /// Encoding used to decode the files instead of auto-detecting it.
+    pub encoding: Option<&'static encoding::Encoding>,
 }
 
 impl Args {
@@ -17,6 +19,15 @@ impl Args {
 
         while let Some(arg) = argv.next() {
             match arg.as_str() {
+                "--encoding" => match argv.next().as_deref() {
+                    Some(label) => {
+                        args.encoding = Some(
+                            encoding::Encoding::for_label(label.as_bytes())
+                                .ok_or_else(|| anyhow::anyhow!("unknown encoding: {}", label))?,
+                        )
+                    }
+                    None => anyhow::bail!("--encoding must specify an encoding label"),
+                },
                 arg if arg.starts_with("--") => {
                     anyhow::bail!("unexpected double dash argment: {}", arg)
                 }
@@ -1,32 +1,75 @@
+pub mod lsp;
 pub mod typed;
 
+pub use lsp::*;
 pub use typed::*;
 
-use std::{fmt, num::NonZeroUsize};
+use std::{fmt, future::Future, num::NonZeroUsize, path::PathBuf};
 
 use ropey::RopeSlice;
+use serde::de::DeserializeOwned;
 
 use crate::{
     core::{
         doc_formatter::TextFormat,
         movement::{move_horizontally, move_vertically_visual, Direction, Movement},
+        position::char_idx_at_visual_offset,
         text_annotations::TextAnnotations,
-        Range,
+        Range, Selection,
     },
     current,
-    view::editor::Editor,
+    term::{
+        compositor::{self, Component},
+        job::{self, Jobs},
+        ui::{self, overlaid, Picker},
+    },
+    view,
+    view::{
+        document::{Mode, SCRATCH_BUFFER_NAME},
+        editor::{Action, Editor},
+        tree, DocumentId,
+    },
 };
 
+use insert::*;
+
 pub struct Context<'a> {
     // pub register: Option<char>,
     pub count: Option<NonZeroUsize>,
     pub editor: &'a mut Editor,
-    // pub callback: Option<crate::compositor::Callback>,
+    pub callback: Option<compositor::Callback>,
     // pub on_next_key_callback: Option<Box<dyn FnOnce(&mut Context, KeyEvent)>>,
-    // pub jobs: &'a mut Jobs,
+    pub jobs: &'a mut Jobs,
 }
 
 impl<'a> Context<'a> {
+    /// Push a new component onto the compositor.
+    pub fn push_layer(&mut self, component: Box<dyn Component>) {
+        self.callback = Some(Box::new(|compositor: &mut compositor::Compositor, _| {
+            compositor.push(component)
+        }));
+    }
+
+    /// Waits for the response of a language server request in the background and calls
+    /// `callback` with it on the main loop.
+    pub fn callback<T, F>(
+        &mut self,
+        call: impl Future<Output = crate::lsp::Result<serde_json::Value>> + Send + 'static,
+        callback: F,
+    ) where
+        T: DeserializeOwned + Send + 'static,
+        F: FnOnce(&mut Editor, &mut compositor::Compositor, T) + Send + 'static,
+    {
+        self.jobs.callback(async move {
+            let json = call.await?;
+            let response = serde_json::from_value(json)?;
+            let call: job::Callback = Box::new(move |editor: &mut Editor, compositor: &mut compositor::Compositor| {
+                callback(editor, compositor, response)
+            });
+            Ok(call)
+        });
+    }
+
     /// Returns 1 if no explicit count was provided
     pub fn count(&self) -> usize {
         self.count.map_or(1, |v| v.get())
@@ -87,6 +130,44 @@ impl MappableCommand {
         move_char_left, "Move left",
         move_visual_line_down, "Move down",
         move_visual_line_up, "Move up",
+        insert_mode, "Insert before selection",
+        normal_mode, "Enter normal mode",
+        insert_newline, "Insert newline char",
+        delete_char_backward, "Delete previous char",
+        command_mode, "Enter command mode",
+        goto_next_buffer, "Goto next buffer",
+        goto_previous_buffer, "Goto previous buffer",
+        file_picker, "Open file picker",
+        buffer_picker, "Open buffer picker",
+        diagnostics_picker, "Open diagnostic picker",
+        workspace_diagnostics_picker, "Open workspace diagnostic picker",
+        completion, "Invoke completion popup",
+        goto_definition, "Goto definition",
+        goto_declaration, "Goto declaration",
+        goto_type_definition, "Goto type definition",
+        goto_implementation, "Goto implementation",
+        goto_reference, "Goto references",
+        symbol_picker, "Open symbol picker",
+        workspace_symbol_picker, "Open workspace symbol picker",
+        goto_first_diag, "Goto first diagnostic",
+        goto_last_diag, "Goto last diagnostic",
+        goto_next_diag, "Goto next diagnostic",
+        goto_prev_diag, "Goto previous diagnostic",
+        jump_view_right, "Jump to right split",
+        jump_view_left, "Jump to left split",
+        jump_view_up, "Jump to split above",
+        jump_view_down, "Jump to split below",
+        swap_view_right, "Swap with right split",
+        swap_view_left, "Swap with left split",
+        swap_view_up, "Swap with split above",
+        swap_view_down, "Swap with split below",
+        transpose_view, "Transpose splits",
+        grow_view, "Grow split",
+        shrink_view, "Shrink split",
+        hsplit, "Horizontal bottom split",
+        vsplit, "Vertical right split",
+        wclose, "Close window",
+        wonly, "Close windows except current",
         _quit, "Quit",
     );
 }
@@ -153,6 +234,412 @@ fn move_visual_line_down(cx: &mut Context) {
     move_impl(cx, move_vertically_visual, Direction::Forward, Movement::Move)
 }
 
+/// Scrolls the focused view by `offset` visual lines, moving the primary cursor along so that it
+/// stays within the scrolloff area.
+pub fn scroll(cx: &mut Context, offset: usize, direction: Direction) {
+    let config = cx.editor.config();
+    let mode = cx.editor.mode;
+    let (view, doc) = current!(cx.editor);
+
+    let range = doc.selection(view.id).primary();
+    let text = doc.text().slice(..);
+    let cursor = range.cursor(text);
+
+    let viewport = view.inner_area(doc);
+    let height = viewport.height as usize;
+    let scrolloff = config.scrolloff.min(height.saturating_sub(1) / 2);
+    let offset = match direction {
+        Direction::Forward => offset as isize,
+        Direction::Backward => -(offset as isize),
+    };
+
+    let text_fmt = doc.text_format(viewport.width, None);
+    let annotations = view.text_annotations(doc, None);
+    (view.offset.anchor, view.offset.vertical_offset) = char_idx_at_visual_offset(
+        text,
+        view.offset.anchor,
+        view.offset.vertical_offset as isize + offset,
+        0,
+        &text_fmt,
+        &annotations,
+    );
+
+    let head = match direction {
+        Direction::Forward => {
+            let (head, off) = char_idx_at_visual_offset(
+                text,
+                view.offset.anchor,
+                (view.offset.vertical_offset + scrolloff) as isize,
+                0,
+                &text_fmt,
+                &annotations,
+            );
+            let head = head + (off != 0) as usize;
+            if head <= cursor {
+                return;
+            }
+            head
+        }
+        Direction::Backward => {
+            let (head, _) = char_idx_at_visual_offset(
+                text,
+                view.offset.anchor,
+                (view.offset.vertical_offset + height.saturating_sub(scrolloff + 1)) as isize,
+                0,
+                &text_fmt,
+                &annotations,
+            );
+            if head >= cursor {
+                return;
+            }
+            head
+        }
+    };
+
+    let mut selection = doc.selection(view.id).clone();
+    let primary = selection.primary_mut();
+    *primary = if mode == Mode::Select {
+        primary.put_cursor(text, head, true)
+    } else {
+        Range::point(head)
+    };
+    doc.set_selection(view.id, selection);
+}
+
+fn insert_mode(cx: &mut Context) {
+    cx.editor.mode = Mode::Insert;
+}
+
+fn normal_mode(cx: &mut Context) {
+    cx.editor.mode = Mode::Normal;
+}
+
+fn jump_view_right(cx: &mut Context) {
+    cx.editor.focus_direction(tree::Direction::Right)
+}
+
+fn jump_view_left(cx: &mut Context) {
+    cx.editor.focus_direction(tree::Direction::Left)
+}
+
+fn jump_view_up(cx: &mut Context) {
+    cx.editor.focus_direction(tree::Direction::Up)
+}
+
+fn jump_view_down(cx: &mut Context) {
+    cx.editor.focus_direction(tree::Direction::Down)
+}
+
+fn swap_view_right(cx: &mut Context) {
+    cx.editor.swap_split_in_direction(tree::Direction::Right)
+}
+
+fn swap_view_left(cx: &mut Context) {
+    cx.editor.swap_split_in_direction(tree::Direction::Left)
+}
+
+fn swap_view_up(cx: &mut Context) {
+    cx.editor.swap_split_in_direction(tree::Direction::Up)
+}
+
+fn swap_view_down(cx: &mut Context) {
+    cx.editor.swap_split_in_direction(tree::Direction::Down)
+}
+
+fn transpose_view(cx: &mut Context) {
+    cx.editor.transpose_view()
+}
+
+/// The count as a resize amount, saturated so that a large count doesn't wrap around.
+fn resize_amount(cx: &Context) -> i16 {
+    i16::try_from(cx.count()).unwrap_or(i16::MAX)
+}
+
+fn grow_view(cx: &mut Context) {
+    cx.editor.tree.resize(resize_amount(cx))
+}
+
+fn shrink_view(cx: &mut Context) {
+    cx.editor.tree.resize(-resize_amount(cx))
+}
+
+fn split(editor: &mut Editor, action: Action) {
+    let (view, doc) = current!(editor);
+    let id = doc.id;
+    let selection = doc.selection(view.id).clone();
+    let offset = view.offset;
+
+    editor.switch(id, action);
+
+    // match the selection and scroll offset of the previous view
+    let (view, doc) = current!(editor);
+    doc.set_selection(view.id, selection);
+    view.offset = offset;
+}
+
+fn hsplit(cx: &mut Context) {
+    split(cx.editor, Action::HorizontalSplit);
+}
+
+fn vsplit(cx: &mut Context) {
+    split(cx.editor, Action::VerticalSplit);
+}
+
+fn wclose(cx: &mut Context) {
+    if cx.editor.tree.views().count() == 1 {
+        if let Err(err) = typed::buffers_remaining_impl(cx.editor) {
+            cx.editor.set_error(err.to_string());
+            return;
+        }
+    }
+
+    let view_id = view!(cx.editor).id;
+    // close current split
+    cx.editor.close(view_id);
+}
+
+fn wonly(cx: &mut Context) {
+    let views = cx
+        .editor
+        .tree
+        .views()
+        .filter(|(_, focus)| !focus)
+        .map(|(view, _)| view.id)
+        .collect::<Vec<_>>();
+    for view_id in views {
+        cx.editor.close(view_id);
+    }
+}
+
+fn goto_next_buffer(cx: &mut Context) {
+    goto_buffer(cx.editor, Direction::Forward);
+}
+
+fn goto_previous_buffer(cx: &mut Context) {
+    goto_buffer(cx.editor, Direction::Backward);
+}
+
+fn goto_buffer(editor: &mut Editor, direction: Direction) {
+    let current = view!(editor).doc;
+
+    let id = match direction {
+        Direction::Forward => {
+            let mut iter = editor.documents.keys().skip_while(|id| *id != &current);
+            iter.next(); // skip current item
+            iter.next().or_else(|| editor.documents.keys().next())
+        }
+        Direction::Backward => {
+            let mut iter = editor.documents.keys().rev().skip_while(|id| *id != &current);
+            iter.next(); // skip current item
+            iter.next().or_else(|| editor.documents.keys().next_back())
+        }
+    }
+    .unwrap();
+
+    let id = *id;
+    editor.switch(id, Action::Replace);
+}
+
+fn goto_first_diag(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let selection = match doc.diagnostics().first() {
+        Some(diag) => Selection::single(diag.range.start, diag.range.end),
+        None => return,
+    };
+    doc.set_selection(view.id, selection);
+}
+
+fn goto_last_diag(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let selection = match doc.diagnostics().last() {
+        Some(diag) => Selection::single(diag.range.start, diag.range.end),
+        None => return,
+    };
+    doc.set_selection(view.id, selection);
+}
+
+/// Selects the first diagnostic after the primary cursor, wrapping around to the first one.
+fn goto_next_diag(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+
+    let cursor_pos = doc.selection(view.id).primary().cursor(doc.text().slice(..));
+    let diag = doc
+        .diagnostics()
+        .iter()
+        .find(|diag| diag.range.start > cursor_pos)
+        .or_else(|| doc.diagnostics().first());
+
+    let selection = match diag {
+        Some(diag) => Selection::single(diag.range.start, diag.range.end),
+        None => return,
+    };
+    doc.set_selection(view.id, selection);
+}
+
+/// Selects the last diagnostic starting before the primary selection, wrapping around to the last
+/// one.
+fn goto_prev_diag(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+
+    // the cursor is at the end of a diagnostic selected by `goto_next_diag`
+    let start = doc.selection(view.id).primary().from();
+    let diag = doc
+        .diagnostics()
+        .iter()
+        .rev()
+        .find(|diag| diag.range.start < start)
+        .or_else(|| doc.diagnostics().last());
+
+    let selection = match diag {
+        // the selection is reversed as we're moving backwards
+        Some(diag) => Selection::single(diag.range.end, diag.range.start),
+        None => return,
+    };
+    doc.set_selection(view.id, selection);
+}
+
+fn file_picker(cx: &mut Context) {
+    let root = match std::env::current_dir() {
+        Ok(root) => root,
+        Err(err) => {
+            cx.editor.set_error(format!("failed to read the current directory: {err}"));
+            return;
+        }
+    };
+    let picker = ui::file_picker(root, cx.editor);
+    cx.push_layer(Box::new(overlaid(picker)));
+}
+
+fn buffer_picker(cx: &mut Context) {
+    struct BufferMeta {
+        id: DocumentId,
+        path: Option<PathBuf>,
+        is_modified: bool,
+        is_current: bool,
+    }
+
+    let current = view!(cx.editor).doc;
+    let items = cx
+        .editor
+        .documents()
+        .map(|doc| BufferMeta {
+            id: doc.id,
+            path: doc.path().cloned(),
+            is_modified: doc.is_modified(),
+            is_current: doc.id == current,
+        })
+        .collect();
+
+    let picker = Picker::new(
+        items,
+        |meta: &BufferMeta| {
+            let path = meta
+                .path
+                .as_deref()
+                .map(crate::core::path::get_relative_path)
+                .map_or_else(|| SCRATCH_BUFFER_NAME.into(), |path| path.to_string_lossy().into_owned());
+
+            let mut flags = String::new();
+            if meta.is_modified {
+                flags.push('+');
+            }
+            if meta.is_current {
+                flags.push('*');
+            }
+
+            format!("{:<4} {:<2} {}", meta.id, flags, path).into()
+        },
+        |cx, meta, action| {
+            cx.editor.switch(meta.id, action);
+        },
+    )
+    .with_preview(|_editor, meta| meta.path.clone().map(|path| (path, None)));
+    cx.push_layer(Box::new(overlaid(picker)));
+}
+
+// insert mode commands. text is inserted in front of the block cursor of each range.
+pub mod insert {
+    use super::*;
+    use crate::{
+        core::{chars::char_is_word, graphemes::prev_grapheme_boundary, Tendril, Transaction},
+        doc,
+    };
+
+    /// Inserts `c` at every cursor, and requests completions if it triggers them.
+    pub fn insert_char(cx: &mut Context, c: char) {
+        let (view, doc) = current!(cx.editor);
+        let text = doc.text();
+        let cursors = doc.selection(view.id).clone().cursors(text.slice(..));
+
+        let mut t = Tendril::new();
+        t.push(c);
+        let transaction = Transaction::insert(text, &cursors, t);
+
+        doc.apply(&transaction, view.id);
+
+        language_server_completion(cx, c);
+    }
+
+    /// Requests completions right away if `ch` is a trigger character of the language server,
+    /// e.g. the `.` before a method.
+    fn language_server_completion(cx: &mut Context, ch: char) {
+        let Some(language_server) = doc!(cx.editor).language_server() else {
+            return;
+        };
+        let trigger_characters = language_server
+            .capabilities()
+            .completion_provider
+            .as_ref()
+            .and_then(|provider| provider.trigger_characters.as_ref());
+        let is_trigger = trigger_characters.is_some_and(|triggers| triggers.iter().any(|trigger| trigger.starts_with(ch)));
+
+        if is_trigger {
+            cx.editor.clear_idle_timer();
+            request_completion(cx, Some(ch));
+        }
+    }
+
+    /// Requests completions once typing paused, if enough of a word precedes the cursor.
+    pub fn idle_completion(cx: &mut Context) {
+        let trigger_len = cx.editor.config().completion_trigger_len as usize;
+        let (view, doc) = current!(cx.editor);
+        let text = doc.text().slice(..);
+        let cursor = doc.selection(view.id).primary().cursor(text);
+
+        let word_len = text
+            .chars_at(cursor)
+            .reversed()
+            .take(trigger_len)
+            .take_while(|&ch| char_is_word(ch))
+            .count();
+        if word_len == trigger_len {
+            request_completion(cx, None);
+        }
+    }
+
+    /// Inserts the line ending of the document.
+    pub fn insert_newline(cx: &mut Context) {
+        let (view, doc) = current!(cx.editor);
+        let text = doc.text();
+        let cursors = doc.selection(view.id).clone().cursors(text.slice(..));
+
+        let transaction = Transaction::insert(text, &cursors, doc.line_ending.as_str().into());
+
+        doc.apply(&transaction, view.id);
+    }
+
+    pub fn delete_char_backward(cx: &mut Context) {
+        let (view, doc) = current!(cx.editor);
+        let text = doc.text().slice(..);
+        let transaction = Transaction::change_by_selection(doc.text(), doc.selection(view.id), |range| {
+            let pos = range.cursor(text);
+            (prev_grapheme_boundary(text, pos), pos, None)
+        });
+
+        doc.apply(&transaction, view.id);
+    }
+}
+
 // for debug use.
 fn _quit(cx: &mut Context) {
     panic!("Bye")
@@ -0,0 +1,500 @@
+use std::{collections::BTreeMap, future::Future, path::Path};
+
+use futures_util::FutureExt;
+
+use super::Context;
+use crate::{
+    core::{chars::char_is_word, coords_at_pos, path::get_relative_path, Position, Selection},
+    current, doc,
+    lsp::{
+        lsp::{self, Diagnostic, DiagnosticSeverity, Url},
+        util::{lsp_pos_to_pos, lsp_range_to_range, pos_to_lsp_pos},
+        Client, OffsetEncoding,
+    },
+    term::{
+        compositor::Compositor,
+        ui::{editor::EditorView, overlaid, DynamicPicker, Picker},
+    },
+    view,
+    view::{
+        document::Mode,
+        editor::{Action, Editor},
+    },
+};
+
+/// The path of `url` relative to the working directory, or the URL itself if it isn't a file.
+fn display_url(url: &Url) -> String {
+    url.to_file_path().map_or_else(
+        |_| url.to_string(),
+        |path| get_relative_path(&path).to_string_lossy().into_owned(),
+    )
+}
+
+/// Opens the file of `url` and selects `range`, converting its positions with the text of the
+/// file as it is now.
+fn jump_to_range(editor: &mut Editor, url: &Url, range: lsp::Range, offset_encoding: OffsetEncoding, action: Action) {
+    let Ok(path) = url.to_file_path() else {
+        editor.set_error(format!("unsupported file URI: {url}"));
+        return;
+    };
+    if let Err(err) = editor.open(&path, action) {
+        editor.set_error(format!("failed to open '{}': {err}", path.display()));
+        return;
+    }
+
+    let (view, doc) = current!(editor);
+    // the file may have changed since the server sent the range
+    let Some(range) = lsp_range_to_range(doc.text(), range, offset_encoding) else {
+        return;
+    };
+    doc.set_selection(view.id, Selection::single(range.start, range.end));
+    let view_id = view.id;
+    editor.ensure_cursor_in_view(view_id);
+}
+
+/// The line and column of an LSP position in the file at `path`, to center a preview on it. The
+/// column is only known while the file is open, its code units can't be counted otherwise.
+fn preview_position(editor: &Editor, path: &Path, position: lsp::Position, offset_encoding: OffsetEncoding) -> Position {
+    editor
+        .document_by_path(path)
+        .and_then(|doc| {
+            let text = doc.text();
+            let pos = lsp_pos_to_pos(text, position, offset_encoding)?;
+            Some(coords_at_pos(text.slice(..), pos))
+        })
+        .unwrap_or(Position::new(position.line as usize, 0))
+}
+
+/// A diagnostic listed by a diagnostics picker. Its positions are converted once it is picked,
+/// its file may not be open before.
+struct PickerDiagnostic {
+    url: Url,
+    diag: Diagnostic,
+    offset_encoding: OffsetEncoding,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum DiagnosticsFormat {
+    ShowSourcePath,
+    HideSourcePath,
+}
+
+/// The line of a diagnostic in the picker, e.g. `error   src/main.rs:3: mismatched types`.
+fn format_diagnostic(PickerDiagnostic { url, diag, .. }: &PickerDiagnostic, format: DiagnosticsFormat) -> String {
+    // like the editor, a missing severity is shown as a warning
+    let severity = match diag.severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "warning",
+    };
+    let line = diag.range.start.line + 1;
+    let message = diag.message.lines().next().unwrap_or_default();
+
+    match format {
+        DiagnosticsFormat::ShowSourcePath => format!("{severity:<7} {}:{line}: {message}", display_url(url)),
+        DiagnosticsFormat::HideSourcePath => format!("{severity:<7} {line}: {message}"),
+    }
+}
+
+fn diag_picker(
+    cx: &Context,
+    diagnostics: BTreeMap<Url, Vec<(Diagnostic, usize)>>,
+    format: DiagnosticsFormat,
+) -> Picker<PickerDiagnostic> {
+    // the diagnostics of servers that exited since are left out
+    let mut flat_diag = Vec::new();
+    for (url, diags) in diagnostics {
+        flat_diag.reserve(diags.len());
+        for (diag, server_id) in diags {
+            if let Some(language_server) = cx.editor.language_servers.get_by_id(server_id) {
+                flat_diag.push(PickerDiagnostic {
+                    url: url.clone(),
+                    diag,
+                    offset_encoding: language_server.offset_encoding(),
+                });
+            }
+        }
+    }
+
+    Picker::new(
+        flat_diag,
+        move |diag: &PickerDiagnostic| format_diagnostic(diag, format).into(),
+        |cx,
+         PickerDiagnostic {
+             url,
+             diag,
+             offset_encoding,
+         },
+         action| jump_to_range(cx.editor, url, diag.range, *offset_encoding, action),
+    )
+    .with_preview(|editor, diagnostic| {
+        let path = diagnostic.url.to_file_path().ok()?;
+        let position = preview_position(editor, &path, diagnostic.diag.range.start, diagnostic.offset_encoding);
+        Some((path, Some(position)))
+    })
+}
+
+/// Lists the diagnostics of the current document.
+pub fn diagnostics_picker(cx: &mut Context) {
+    let Some(url) = doc!(cx.editor).url() else {
+        cx.editor.set_error("the buffer has no diagnostics, it isn't a file");
+        return;
+    };
+    let diagnostics = cx.editor.diagnostics.get(&url).cloned().unwrap_or_default();
+    let picker = diag_picker(cx, BTreeMap::from([(url, diagnostics)]), DiagnosticsFormat::HideSourcePath);
+    cx.push_layer(Box::new(overlaid(picker)));
+}
+
+/// Lists the diagnostics of every file, including the ones that aren't open.
+pub fn workspace_diagnostics_picker(cx: &mut Context) {
+    let diagnostics = cx.editor.diagnostics.clone();
+    let picker = diag_picker(cx, diagnostics, DiagnosticsFormat::ShowSourcePath);
+    cx.push_layer(Box::new(overlaid(picker)));
+}
+
+/// Requests the completions at the primary cursor and shows them in a menu.
+pub fn completion(cx: &mut Context) {
+    request_completion(cx, None);
+}
+
+/// Requests the completions at the primary cursor, after `trigger_character` was typed if set.
+/// They are dropped if the editor left insert mode or switched documents in the meantime.
+pub(super) fn request_completion(cx: &mut Context, trigger_character: Option<char>) {
+    let (view, doc) = current!(cx.editor);
+    let Some(language_server) = doc.language_server() else {
+        return;
+    };
+    let offset_encoding = language_server.offset_encoding();
+
+    let text = doc.text().slice(..);
+    let cursor = doc.selection(view.id).primary().cursor(text);
+    let position = pos_to_lsp_pos(doc.text(), cursor, offset_encoding);
+    let context = lsp::CompletionContext {
+        trigger_kind: match trigger_character {
+            Some(_) => lsp::CompletionTriggerKind::TRIGGER_CHARACTER,
+            None => lsp::CompletionTriggerKind::INVOKED,
+        },
+        trigger_character: trigger_character.map(String::from),
+    };
+    let Some(future) = language_server.completion(doc.identifier(), position, Some(context)) else {
+        return;
+    };
+
+    // the items are filtered with the word before the cursor
+    let word_len = text.chars_at(cursor).reversed().take_while(|&ch| char_is_word(ch)).count();
+    let start_offset = cursor - word_len;
+    let (view_id, doc_id) = (view.id, doc.id);
+
+    cx.callback(
+        future,
+        move |editor, compositor, response: Option<lsp::CompletionResponse>| {
+            let view = view!(editor);
+            if editor.mode() != Mode::Insert || view.id != view_id || view.doc != doc_id {
+                return;
+            }
+
+            let items = match response {
+                Some(lsp::CompletionResponse::Array(items)) => items,
+                Some(lsp::CompletionResponse::List(list)) => list.items,
+                None => Vec::new(),
+            };
+            if items.is_empty() {
+                return;
+            }
+            if let Some(editor_view) = compositor.find::<EditorView>() {
+                editor_view.set_completion(editor, items, offset_encoding, start_offset, cursor);
+            }
+        },
+    );
+}
+
+/// Jumps to the location if there is only one, or lists them in a picker.
+fn goto_locations(
+    editor: &mut Editor,
+    compositor: &mut Compositor,
+    locations: Vec<lsp::Location>,
+    offset_encoding: OffsetEncoding,
+) {
+    match locations.as_slice() {
+        [] => editor.set_error("no location found"),
+        [location] => jump_to_range(editor, &location.uri, location.range, offset_encoding, Action::Replace),
+        _ => {
+            let picker = Picker::new(
+                locations,
+                |location: &lsp::Location| format!("{}:{}", display_url(&location.uri), location.range.start.line + 1).into(),
+                move |cx, location, action| jump_to_range(cx.editor, &location.uri, location.range, offset_encoding, action),
+            )
+            .with_preview(move |editor, location| {
+                let path = location.uri.to_file_path().ok()?;
+                let position = preview_position(editor, &path, location.range.start, offset_encoding);
+                Some((path, Some(position)))
+            });
+            compositor.push(Box::new(overlaid(picker)));
+        }
+    }
+}
+
+/// Sends the request built by `request` for the primary cursor, then jumps to the locations it
+/// responds with. `feature` names the request in the error shown if the server doesn't support
+/// it.
+fn goto_request<F, T>(
+    cx: &mut Context,
+    feature: &str,
+    request: impl FnOnce(&Client, lsp::TextDocumentIdentifier, lsp::Position) -> Option<F>,
+    into_locations: fn(T) -> Vec<lsp::Location>,
+) where
+    F: Future<Output = crate::lsp::Result<serde_json::Value>> + Send + 'static,
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    let (view, doc) = current!(cx.editor);
+    let Some(language_server) = doc.language_server() else {
+        cx.editor.set_error("the buffer has no language server");
+        return;
+    };
+    let offset_encoding = language_server.offset_encoding();
+    let cursor = doc.selection(view.id).primary().cursor(doc.text().slice(..));
+    let position = pos_to_lsp_pos(doc.text(), cursor, offset_encoding);
+    let Some(future) = request(language_server, doc.identifier(), position) else {
+        cx.editor.set_error(format!("the language server doesn't support {feature}"));
+        return;
+    };
+
+    cx.callback(future, move |editor, compositor, response: T| {
+        goto_locations(editor, compositor, into_locations(response), offset_encoding)
+    });
+}
+
+/// The locations of a goto response, the target of a link is the range of its symbol.
+fn goto_response_locations(response: Option<lsp::GotoDefinitionResponse>) -> Vec<lsp::Location> {
+    match response {
+        Some(lsp::GotoDefinitionResponse::Scalar(location)) => vec![location],
+        Some(lsp::GotoDefinitionResponse::Array(locations)) => locations,
+        Some(lsp::GotoDefinitionResponse::Link(links)) => links
+            .into_iter()
+            .map(|link| lsp::Location::new(link.target_uri, link.target_selection_range))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn goto_definition(cx: &mut Context) {
+    goto_request(cx, "goto definition", Client::goto_definition, goto_response_locations);
+}
+
+pub fn goto_declaration(cx: &mut Context) {
+    goto_request(cx, "goto declaration", Client::goto_declaration, goto_response_locations);
+}
+
+pub fn goto_type_definition(cx: &mut Context) {
+    goto_request(
+        cx,
+        "goto type definition",
+        Client::goto_type_definition,
+        goto_response_locations,
+    );
+}
+
+pub fn goto_implementation(cx: &mut Context) {
+    goto_request(
+        cx,
+        "goto implementation",
+        Client::goto_implementation,
+        goto_response_locations,
+    );
+}
+
+pub fn goto_reference(cx: &mut Context) {
+    goto_request(cx, "goto reference", Client::goto_reference, Option::unwrap_or_default);
+}
+
+fn symbol_kind_name(kind: lsp::SymbolKind) -> &'static str {
+    match kind {
+        lsp::SymbolKind::FILE => "file",
+        lsp::SymbolKind::MODULE => "module",
+        lsp::SymbolKind::NAMESPACE => "namespace",
+        lsp::SymbolKind::PACKAGE => "package",
+        lsp::SymbolKind::CLASS => "class",
+        lsp::SymbolKind::METHOD => "method",
+        lsp::SymbolKind::PROPERTY => "property",
+        lsp::SymbolKind::FIELD => "field",
+        lsp::SymbolKind::CONSTRUCTOR => "constructor",
+        lsp::SymbolKind::ENUM => "enum",
+        lsp::SymbolKind::INTERFACE => "interface",
+        lsp::SymbolKind::FUNCTION => "function",
+        lsp::SymbolKind::VARIABLE => "variable",
+        lsp::SymbolKind::CONSTANT => "constant",
+        lsp::SymbolKind::STRING => "string",
+        lsp::SymbolKind::NUMBER => "number",
+        lsp::SymbolKind::BOOLEAN => "boolean",
+        lsp::SymbolKind::ARRAY => "array",
+        lsp::SymbolKind::OBJECT => "object",
+        lsp::SymbolKind::KEY => "key",
+        lsp::SymbolKind::NULL => "null",
+        lsp::SymbolKind::ENUM_MEMBER => "enum_member",
+        lsp::SymbolKind::STRUCT => "struct",
+        lsp::SymbolKind::EVENT => "event",
+        lsp::SymbolKind::OPERATOR => "operator",
+        lsp::SymbolKind::TYPE_PARAMETER => "type_param",
+        _ => "",
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SymbolsFormat {
+    ShowPath,
+    HidePath,
+}
+
+/// The line of a symbol in the picker, e.g. `function    main  src/main.rs`.
+fn format_symbol(symbol: &lsp::SymbolInformation, format: SymbolsFormat) -> String {
+    let kind = symbol_kind_name(symbol.kind);
+    match format {
+        SymbolsFormat::ShowPath => format!("{kind:<11} {}  {}", symbol.name, display_url(&symbol.location.uri)),
+        SymbolsFormat::HidePath => format!("{kind:<11} {}", symbol.name),
+    }
+}
+
+fn sym_picker(
+    symbols: Vec<lsp::SymbolInformation>,
+    offset_encoding: OffsetEncoding,
+    format: SymbolsFormat,
+) -> Picker<lsp::SymbolInformation> {
+    Picker::new(
+        symbols,
+        move |symbol: &lsp::SymbolInformation| format_symbol(symbol, format).into(),
+        move |cx, symbol, action| {
+            let location = &symbol.location;
+            jump_to_range(cx.editor, &location.uri, location.range, offset_encoding, action)
+        },
+    )
+    .with_preview(move |editor, symbol| {
+        let path = symbol.location.uri.to_file_path().ok()?;
+        let position = preview_position(editor, &path, symbol.location.range.start, offset_encoding);
+        Some((path, Some(position)))
+    })
+}
+
+/// Flattens the symbols of the document at `url` and their children, which are listed after
+/// them with their name as container.
+fn flatten_document_symbols(
+    url: &Url,
+    symbols: Vec<lsp::DocumentSymbol>,
+    container_name: Option<&str>,
+    flat: &mut Vec<lsp::SymbolInformation>,
+) {
+    for symbol in symbols {
+        #[allow(deprecated)]
+        flat.push(lsp::SymbolInformation {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            tags: symbol.tags,
+            deprecated: symbol.deprecated,
+            location: lsp::Location::new(url.clone(), symbol.selection_range),
+            container_name: container_name.map(String::from),
+        });
+        if let Some(children) = symbol.children {
+            flatten_document_symbols(url, children, Some(&symbol.name), flat);
+        }
+    }
+}
+
+/// Lists the symbols of the current document.
+pub fn symbol_picker(cx: &mut Context) {
+    let doc = doc!(cx.editor);
+    let Some(language_server) = doc.language_server() else {
+        cx.editor.set_error("the buffer has no language server");
+        return;
+    };
+    let Some(url) = doc.url() else {
+        cx.editor.set_error("the buffer has no symbols, it isn't a file");
+        return;
+    };
+    let offset_encoding = language_server.offset_encoding();
+    let Some(future) = language_server.document_symbols(doc.identifier()) else {
+        cx.editor.set_error("the language server doesn't support document symbols");
+        return;
+    };
+
+    cx.callback(
+        future,
+        move |_editor, compositor, response: Option<lsp::DocumentSymbolResponse>| {
+            let symbols = match response {
+                Some(lsp::DocumentSymbolResponse::Flat(symbols)) => symbols,
+                Some(lsp::DocumentSymbolResponse::Nested(symbols)) => {
+                    let mut flat = Vec::new();
+                    flatten_document_symbols(&url, symbols, None, &mut flat);
+                    flat
+                }
+                None => Vec::new(),
+            };
+            let picker = sym_picker(symbols, offset_encoding, SymbolsFormat::HidePath);
+            compositor.push(Box::new(overlaid(picker)));
+        },
+    );
+}
+
+/// The symbols of a workspace symbols response. A symbol whose range is left out is located at
+/// the start of its file.
+fn workspace_symbols(response: Option<lsp::WorkspaceSymbolResponse>) -> Vec<lsp::SymbolInformation> {
+    match response {
+        Some(lsp::WorkspaceSymbolResponse::Flat(symbols)) => symbols,
+        Some(lsp::WorkspaceSymbolResponse::Nested(symbols)) => symbols
+            .into_iter()
+            .map(|symbol| {
+                let location = match symbol.location {
+                    lsp::OneOf::Left(location) => location,
+                    lsp::OneOf::Right(location) => lsp::Location::new(location.uri, lsp::Range::default()),
+                };
+                #[allow(deprecated)]
+                lsp::SymbolInformation {
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    tags: symbol.tags,
+                    deprecated: None,
+                    location,
+                    container_name: symbol.container_name,
+                }
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Lists the symbols of the workspace, which are requested again from the language server
+/// whenever the query changes.
+pub fn workspace_symbol_picker(cx: &mut Context) {
+    let doc = doc!(cx.editor);
+    let Some(language_server) = doc.language_server() else {
+        cx.editor.set_error("the buffer has no language server");
+        return;
+    };
+    let (server_id, offset_encoding) = (language_server.id(), language_server.offset_encoding());
+    let Some(future) = language_server.workspace_symbols(String::new()) else {
+        cx.editor.set_error("the language server doesn't support workspace symbols");
+        return;
+    };
+
+    cx.callback(
+        future,
+        move |_editor, compositor, response: Option<lsp::WorkspaceSymbolResponse>| {
+            let picker = sym_picker(workspace_symbols(response), offset_encoding, SymbolsFormat::ShowPath);
+            let query_callback = move |query: String, editor: &mut Editor| {
+                let future = editor
+                    .language_servers
+                    .get_by_id(server_id)
+                    .and_then(|language_server| language_server.workspace_symbols(query));
+                async move {
+                    let Some(future) = future else {
+                        anyhow::bail!("the language server exited");
+                    };
+                    let response = serde_json::from_value(future.await?)?;
+                    Ok(workspace_symbols(response))
+                }
+                .boxed()
+            };
+            let picker = DynamicPicker::new(picker, Box::new(query_callback));
+            compositor.push(Box::new(overlaid(picker)));
+        },
+    );
+}
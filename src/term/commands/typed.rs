@@ -1,5 +1,552 @@
-use super::Context;
+use std::{borrow::Cow, cmp::Reverse, collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail, ensure};
+use once_cell::sync::Lazy;
+
+use super::{goto_buffer, Context};
+use crate::{
+    core::{
+        diagnostic::Severity, encoding, line_ending::get_line_ending, movement::Direction, path::get_relative_path, LineEnding,
+        Rope, Transaction,
+    },
+    current, doc, doc_mut,
+    term::{
+        compositor,
+        ui::{
+            completers::{self, Completer},
+            fuzzy_match::FuzzyQuery,
+            Prompt, PromptEvent,
+        },
+    },
+    view,
+    view::{
+        document::Document,
+        editor::{Action, CloseError, Editor},
+        DocumentId,
+    },
+};
+
+pub struct TypableCommand {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub doc: &'static str,
+    // params, flags, helper
+    pub fun: fn(&mut compositor::Context, &[Cow<str>], PromptEvent) -> anyhow::Result<()>,
+    /// Completes the arguments of the command in the command prompt.
+    pub completer: Option<Completer>,
+}
+
+fn write_impl(cx: &mut compositor::Context, path: Option<&Cow<str>>, force: bool) -> anyhow::Result<()> {
+    let syn_loader = cx.editor.syn_loader.clone();
+    let doc = doc_mut!(cx.editor);
+
+    if let Some(path) = path {
+        doc.set_path(Some(Path::new(path.as_ref())))?;
+        doc.detect_language(&syn_loader);
+    }
+    doc.save(force)?;
+
+    // the language may have changed with the path
+    if path.is_some() {
+        let doc_id = doc.id;
+        cx.editor.refresh_language_server(doc_id);
+    }
+    Ok(())
+}
+
+fn write(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    write_impl(cx, args.first(), false)
+}
+
+fn force_write(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    write_impl(cx, args.first(), true)
+}
+
+fn new_file(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    cx.editor.new_file(Action::Replace);
+
+    Ok(())
+}
+
+/// Fails if any buffer has unsaved changes, listing them.
+pub(super) fn buffers_remaining_impl(editor: &Editor) -> anyhow::Result<()> {
+    let modified: Vec<_> = editor
+        .documents()
+        .filter(|doc| doc.is_modified())
+        .map(|doc| doc.display_name())
+        .collect();
+    if !modified.is_empty() {
+        bail!("{} unsaved buffer(s) remaining: {:?}", modified.len(), modified);
+    }
+    Ok(())
+}
+
+fn buffer_close_by_ids_impl(cx: &mut compositor::Context, doc_ids: &[DocumentId], force: bool) -> anyhow::Result<()> {
+    let (modified_ids, modified_names): (Vec<_>, Vec<_>) = doc_ids
+        .iter()
+        .filter_map(|&doc_id| {
+            if let Err(CloseError::BufferModified(name)) = cx.editor.close_document(doc_id, force) {
+                Some((doc_id, name))
+            } else {
+                None
+            }
+        })
+        .unzip();
+
+    if let Some(first) = modified_ids.first() {
+        // If the current document is unmodified, and there are modified
+        // documents, switch focus to the first modified doc.
+        if !modified_ids.contains(&view!(cx.editor).doc) {
+            cx.editor.switch(*first, Action::Replace);
+        }
+        bail!(
+            "{} unsaved buffer(s) remaining (add ! to discard the changes): {:?}",
+            modified_names.len(),
+            modified_names
+        );
+    }
+
+    Ok(())
+}
+
+/// The documents named by `args` (by path, either absolute or relative to the working directory),
+/// or the current document if there are none.
+fn buffer_gather_paths_impl(editor: &mut Editor, args: &[Cow<str>]) -> Vec<DocumentId> {
+    // No arguments implies current document
+    if args.is_empty() {
+        return vec![view!(editor).doc];
+    }
+
+    let mut nonexistent_buffers = vec![];
+    let mut document_ids = vec![];
+    for arg in args {
+        let arg_path = Path::new(arg.as_ref());
+        let doc_id = editor.documents().find_map(|doc| {
+            let path = doc.path()?;
+            (path == arg_path || get_relative_path(path) == arg_path).then_some(doc.id)
+        });
+
+        match doc_id {
+            Some(doc_id) => document_ids.push(doc_id),
+            None => nonexistent_buffers.push(format!("'{}'", arg)),
+        }
+    }
+
+    if !nonexistent_buffers.is_empty() {
+        editor.set_error(format!(
+            "cannot close non-existent buffers: {}",
+            nonexistent_buffers.join(", ")
+        ));
+    }
+
+    document_ids.sort_unstable();
+    document_ids.dedup();
+    document_ids
+}
+
+fn buffer_close(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids = buffer_gather_paths_impl(cx.editor, args);
+    buffer_close_by_ids_impl(cx, &document_ids, false)
+}
+
+fn force_buffer_close(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids = buffer_gather_paths_impl(cx.editor, args);
+    buffer_close_by_ids_impl(cx, &document_ids, true)
+}
+
+fn buffer_gather_others_impl(editor: &Editor) -> Vec<DocumentId> {
+    let current_document = view!(editor).doc;
+    editor
+        .documents()
+        .map(|doc| doc.id)
+        .filter(|&doc_id| doc_id != current_document)
+        .collect()
+}
+
+fn buffer_close_others(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids = buffer_gather_others_impl(cx.editor);
+    buffer_close_by_ids_impl(cx, &document_ids, false)
+}
+
+fn force_buffer_close_others(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids = buffer_gather_others_impl(cx.editor);
+    buffer_close_by_ids_impl(cx, &document_ids, true)
+}
+
+fn buffer_close_all(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids: Vec<_> = cx.editor.documents().map(|doc| doc.id).collect();
+    buffer_close_by_ids_impl(cx, &document_ids, false)
+}
+
+fn force_buffer_close_all(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let document_ids: Vec<_> = cx.editor.documents().map(|doc| doc.id).collect();
+    buffer_close_by_ids_impl(cx, &document_ids, true)
+}
+
+fn buffer_next(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    goto_buffer(cx.editor, Direction::Forward);
+    Ok(())
+}
+
+fn buffer_previous(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    goto_buffer(cx.editor, Direction::Backward);
+    Ok(())
+}
+
+fn set_line_ending(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+    ensure!(args.len() <= 1, "Usage: :line-ending [crlf|lf]");
+
+    // Without an argument, report the current line ending.
+    if args.is_empty() {
+        let line_ending = doc!(cx.editor).line_ending;
+        cx.editor.set_status(line_ending.name());
+        return Ok(());
+    }
+
+    let line_ending = LineEnding::from_name(&args[0]).ok_or_else(|| anyhow!("invalid line ending '{}'", args[0]))?;
+
+    let (view, doc) = current!(cx.editor);
+
+    // Convert every line ending of the document in a single transaction.
+    let mut pos = 0;
+    let transaction = Transaction::change(
+        doc.text(),
+        doc.text().lines().filter_map(|line| {
+            pos += line.len_chars();
+            match get_line_ending(&line) {
+                Some(ending) if ending != line_ending => {
+                    let start = pos - ending.len_chars();
+                    let end = pos;
+                    Some((start, end, Some(line_ending.as_str().into())))
+                }
+                _ => None,
+            }
+        }),
+    );
+    if !doc.apply(&transaction, view.id) {
+        bail!("failed to convert line endings");
+    }
+    // only once the text was converted, the document is left as is otherwise
+    doc.line_ending = line_ending;
+
+    Ok(())
+}
+
+fn set_encoding(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+    ensure!(args.len() <= 1, "Usage: :encoding [label]");
+
+    // Without an argument, report the current encoding.
+    if args.is_empty() {
+        let encoding = doc!(cx.editor).encoding().name();
+        cx.editor.set_status(encoding);
+        return Ok(());
+    }
+
+    let encoding = encoding::Encoding::for_label(args[0].as_bytes()).ok_or_else(|| anyhow!("unknown encoding '{}'", args[0]))?;
+
+    let (view, doc) = current!(cx.editor);
+    // An unmodified file is decoded again, otherwise only the encoding used on save changes.
+    if !doc.is_modified() && doc.path().is_some_and(|path| path.exists()) {
+        doc.reload(view.id, encoding)
+    } else {
+        doc.set_encoding(encoding);
+        Ok(())
+    }
+}
+
+fn log_open(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    cx.editor.open(&crate::log_file(), Action::Replace)?;
+    Ok(())
+}
+
+/// Previews the theme while its name is typed, and switches to it once validated.
+fn theme(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    let theme_name = args.first();
+    match event {
+        PromptEvent::Abort => cx.editor.unset_theme_preview(),
+        PromptEvent::Update => match theme_name {
+            // ignore the names that don't load, they may not be complete yet
+            Some(theme_name) => {
+                if let Ok((theme, _warnings)) = cx.editor.theme_loader.load_with_warnings(theme_name) {
+                    cx.editor.set_theme_preview(theme);
+                }
+            }
+            None => cx.editor.unset_theme_preview(),
+        },
+        PromptEvent::Validate => match theme_name {
+            Some(theme_name) => {
+                let (theme, warnings) = cx
+                    .editor
+                    .theme_loader
+                    .load_with_warnings(theme_name)
+                    .map_err(|err| anyhow!("could not load theme: {err}"))?;
+                cx.editor.set_theme(theme);
+                if !warnings.is_empty() {
+                    for warning in &warnings {
+                        tracing::warn!("Theme '{theme_name}': {warning}");
+                    }
+                    cx.editor.set_error(format!(
+                        "theme '{theme_name}' has {} invalid entries, see the log",
+                        warnings.len()
+                    ));
+                }
+            }
+            None => {
+                let name = cx.editor.theme.name().to_owned();
+                cx.editor.set_status(name);
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Opens a scratch buffer listing every status and error message of this session.
+fn messages(cx: &mut compositor::Context, _args: &[Cow<str>], event: PromptEvent) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let mut text = String::new();
+    for (message, severity) in &cx.editor.messages {
+        let severity = match severity {
+            Severity::Hint => "HINT",
+            Severity::Info => "INFO",
+            Severity::Warning => "WARN",
+            Severity::Error => "ERROR",
+        };
+        text.push_str(&format!("[{severity}] {message}\n"));
+    }
+
+    let doc = Document::from(Rope::from(text), None, cx.editor.config.clone());
+    cx.editor.new_file_from_document(Action::Replace, doc);
+    Ok(())
+}
+
+pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
+    TypableCommand {
+        name: "write",
+        aliases: &["w"],
+        doc: "Write changes to disk. Accepts an optional path (:write some/path.txt)",
+        fun: write,
+        completer: None,
+    },
+    TypableCommand {
+        name: "write!",
+        aliases: &["w!"],
+        doc: "Force write changes to disk creating necessary subdirectories. Accepts an optional path (:write! some/path.txt)",
+        fun: force_write,
+        completer: None,
+    },
+    TypableCommand {
+        name: "new",
+        aliases: &["n"],
+        doc: "Create a new scratch buffer.",
+        fun: new_file,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close",
+        aliases: &["bc", "bclose"],
+        doc: "Close the current buffer, or the buffers with the given paths.",
+        fun: buffer_close,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close!",
+        aliases: &["bc!", "bclose!"],
+        doc: "Close the current buffer, or the buffers with the given paths, discarding unsaved changes.",
+        fun: force_buffer_close,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close-others",
+        aliases: &["bco", "bcloseother"],
+        doc: "Close all buffers but the currently focused one.",
+        fun: buffer_close_others,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close-others!",
+        aliases: &["bco!", "bcloseother!"],
+        doc: "Force close all buffers but the currently focused one.",
+        fun: force_buffer_close_others,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close-all",
+        aliases: &["bca", "bcloseall"],
+        doc: "Close all buffers without quitting.",
+        fun: buffer_close_all,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-close-all!",
+        aliases: &["bca!", "bcloseall!"],
+        doc: "Force close all buffers ignoring unsaved changes without quitting.",
+        fun: force_buffer_close_all,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-next",
+        aliases: &["bn", "bnext"],
+        doc: "Goto next buffer.",
+        fun: buffer_next,
+        completer: None,
+    },
+    TypableCommand {
+        name: "buffer-previous",
+        aliases: &["bp", "bprev"],
+        doc: "Goto previous buffer.",
+        fun: buffer_previous,
+        completer: None,
+    },
+    TypableCommand {
+        name: "line-ending",
+        aliases: &[],
+        doc: "Set the document's default line ending. Options: crlf, lf.",
+        fun: set_line_ending,
+        completer: None,
+    },
+    TypableCommand {
+        name: "encoding",
+        aliases: &[],
+        doc: "Re-open the file with the given encoding, or set the encoding used on save if it has unsaved changes.",
+        fun: set_encoding,
+        completer: None,
+    },
+    TypableCommand {
+        name: "log-open",
+        aliases: &[],
+        doc: "Open the log file.",
+        fun: log_open,
+        completer: None,
+    },
+    TypableCommand {
+        name: "messages",
+        aliases: &[],
+        doc: "Open a scratch buffer listing the status and error messages of this session.",
+        fun: messages,
+        completer: None,
+    },
+    TypableCommand {
+        name: "theme",
+        aliases: &[],
+        doc: "Change the editor theme (show current theme if no name specified).",
+        fun: theme,
+        completer: Some(completers::theme),
+    },
+];
+
+pub static TYPABLE_COMMAND_MAP: Lazy<HashMap<&'static str, &'static TypableCommand>> = Lazy::new(|| {
+    TYPABLE_COMMAND_LIST
+        .iter()
+        .flat_map(|cmd| {
+            std::iter::once((cmd.name, cmd))
+                .chain(cmd.aliases.iter().map(move |&alias| (alias, cmd)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+});
 
 pub(super) fn command_mode(cx: &mut Context) {
-    todo!()
+    let mut prompt = Prompt::new(":".into(), |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+        let parts = input.split_whitespace().collect::<Vec<&str>>();
+        if parts.is_empty() {
+            return;
+        }
+
+        // Handle typable commands
+        if let Some(cmd) = TYPABLE_COMMAND_MAP.get(parts[0]) {
+            let args: Vec<Cow<str>> = parts[1..].iter().map(|&arg| arg.into()).collect();
+
+            if let Err(e) = (cmd.fun)(cx, &args, event) {
+                cx.editor.set_error(format!("'{}': {e}", cmd.name));
+            }
+        } else if event == PromptEvent::Validate {
+            cx.editor.set_error(format!("no such command: '{}'", parts[0]));
+        }
+    })
+    .with_completion(|editor: &Editor, input: &str| {
+        let parts = input.split_whitespace().collect::<Vec<&str>>();
+        let ends_with_space = input.ends_with(char::is_whitespace);
+
+        // complete the command name while it is typed
+        if parts.len() <= 1 && !ends_with_space {
+            let query = FuzzyQuery::new(input);
+            let mut matches: Vec<_> = TYPABLE_COMMAND_LIST
+                .iter()
+                .filter_map(|cmd| query.fuzzy_match(cmd.name).map(|score| (cmd.name, score)))
+                .collect();
+            matches.sort_by_key(|&(_, score)| Reverse(score));
+            return matches.into_iter().map(|(name, _)| (0.., name.into())).collect();
+        }
+
+        // complete the last argument with the completer of the command
+        let Some(completer) = TYPABLE_COMMAND_MAP.get(parts[0]).and_then(|cmd| cmd.completer) else {
+            return Vec::new();
+        };
+        let arg = if ends_with_space { "" } else { parts[parts.len() - 1] };
+        let offset = input.len() - arg.len();
+        completer(editor, arg)
+            .into_iter()
+            .map(|(range, text)| ((range.start + offset).., text))
+            .collect()
+    });
+    prompt.recalculate_completion(cx.editor);
+
+    cx.push_layer(Box::new(prompt));
 }
@@ -1,6 +1,9 @@
 use std::any::Any;
 
+use crate::core::Position;
+use crate::term::job::Jobs;
 use crate::view::editor::Editor;
+use crate::view::graphics::CursorKind;
 
 use crate::tui::buffer::Buffer as Surface;
 use crate::view::graphics::Rect;
@@ -16,7 +19,11 @@ pub enum EventResult {
 
 pub struct Context<'a> {
     pub editor: &'a mut Editor,
+    pub jobs: &'a mut Jobs,
+    /// Scroll offset for the component being rendered, set by containers like `Popup`.
+    pub scroll: Option<usize>,
 }
+
 pub trait Component: Any {
     fn handle_event(&mut self, _event: &Event, _ctx: &mut Context) -> EventResult {
         EventResult::Ignored(None)
@@ -32,6 +39,16 @@ pub trait Component: Any {
     fn required_size(&mut self, _viewport: (u16, u16)) -> Option<(u16, u16)> {
         None
     }
+
+    /// Get cursor position and cursor kind.
+    fn cursor(&self, _area: Rect, _ctx: &Editor) -> (Option<Position>, CursorKind) {
+        (None, CursorKind::Hidden)
+    }
+
+    /// An identifier used to look up the layer in the compositor, see [`Compositor::find_id`].
+    fn id(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 pub struct Compositor {
@@ -84,6 +101,20 @@ impl Compositor {
         }
     }
 
+    /// The cursor of the topmost layer that has one.
+    pub fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        for layer in self.layers.iter().rev() {
+            if let (Some(pos), kind) = layer.cursor(area, editor) {
+                return (Some(pos), kind);
+            }
+        }
+        (None, CursorKind::Hidden)
+    }
+
+    pub fn resize(&mut self, area: Rect) {
+        self.area = area;
+    }
+
     pub fn size(&self) -> Rect {
         self.area
     }
@@ -94,4 +125,87 @@ impl Compositor {
         layer.required_size((size.width, size.height));
         self.layers.push(layer);
     }
+
+    /// Replace a layer with the same id, or push it on top if there is none.
+    pub fn replace_or_push<T: Component>(&mut self, id: &'static str, layer: T) {
+        if let Some(component) = self.find_id(id) {
+            *component = layer;
+        } else {
+            self.push(Box::new(layer))
+        }
+    }
+
+    /// Remove the top most layer.
+    pub fn pop(&mut self) -> Option<Box<dyn Component>> {
+        self.layers.pop()
+    }
+
+    /// Remove the layer with the given id.
+    pub fn remove(&mut self, id: &'static str) -> Option<Box<dyn Component>> {
+        let idx = self.layers.iter().position(|layer| layer.id() == Some(id))?;
+        Some(self.layers.remove(idx))
+    }
+
+    /// The topmost layer of type `T`.
+    pub fn find<T: Component>(&mut self) -> Option<&mut T> {
+        self.layers.iter_mut().rev().find_map(|layer| {
+            let layer: &mut dyn Any = layer.as_mut();
+            layer.downcast_mut()
+        })
+    }
+
+    /// The layer with the given id, if it is of type `T`.
+    pub fn find_id<T: Component>(&mut self, id: &'static str) -> Option<&mut T> {
+        self.layers.iter_mut().find_map(|layer| {
+            if layer.id() != Some(id) {
+                return None;
+            }
+            let layer: &mut dyn Any = layer.as_mut();
+            layer.downcast_mut()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Layer(&'static str, usize);
+
+    impl Component for Layer {
+        fn render(&mut self, _area: Rect, _frame: &mut Surface, _ctx: &mut Context) {}
+
+        fn id(&self) -> Option<&'static str> {
+            Some(self.0)
+        }
+    }
+
+    struct Other;
+
+    impl Component for Other {
+        fn render(&mut self, _area: Rect, _frame: &mut Surface, _ctx: &mut Context) {}
+    }
+
+    #[test]
+    fn find_and_remove_layers() {
+        let mut compositor = Compositor::new(Rect::new(0, 0, 80, 24));
+        compositor.push(Box::new(Layer("a", 1)));
+        compositor.push(Box::new(Other));
+        compositor.push(Box::new(Layer("b", 2)));
+
+        assert_eq!(compositor.find::<Layer>().map(|layer| layer.1), Some(2));
+        assert_eq!(compositor.find_id::<Layer>("a").map(|layer| layer.1), Some(1));
+        assert!(compositor.find_id::<Other>("a").is_none());
+
+        compositor.replace_or_push("a", Layer("a", 3));
+        compositor.replace_or_push("c", Layer("c", 4));
+        assert_eq!(compositor.find_id::<Layer>("a").map(|layer| layer.1), Some(3));
+        assert_eq!(compositor.layers.len(), 4);
+
+        assert_eq!(compositor.remove("b").and_then(|layer| layer.id()), Some("b"));
+        assert!(compositor.remove("b").is_none());
+        assert_eq!(compositor.pop().and_then(|layer| layer.id()), Some("c"));
+        assert!(compositor.find::<Other>().is_some());
+        assert_eq!(compositor.layers.len(), 2);
+    }
 }
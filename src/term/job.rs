@@ -0,0 +1,45 @@
+//! Background work whose result is handled on the main loop, e.g. the responses of language
+//! server requests.
+
+use futures_util::{
+    future::{BoxFuture, Future, FutureExt},
+    stream::FuturesUnordered,
+};
+
+use crate::{term::compositor::Compositor, view::editor::Editor};
+
+/// Run on the main loop with the result of a job.
+pub type Callback = Box<dyn FnOnce(&mut Editor, &mut Compositor) + Send>;
+
+pub type JobFuture = BoxFuture<'static, anyhow::Result<Option<Callback>>>;
+
+#[derive(Default)]
+pub struct Jobs {
+    /// The jobs still running, polled by the event loop.
+    pub futures: FuturesUnordered<JobFuture>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` in the background, ignoring its result.
+    pub fn spawn<F: Future<Output = anyhow::Result<()>> + Send + 'static>(&mut self, f: F) {
+        self.futures.push(f.map(|result| result.map(|()| None)).boxed());
+    }
+
+    /// Runs `f` in the background and then the callback it resolves to on the main loop.
+    pub fn callback<F: Future<Output = anyhow::Result<Callback>> + Send + 'static>(&mut self, f: F) {
+        self.futures.push(f.map(|result| result.map(Some)).boxed());
+    }
+
+    /// Runs the callback of a finished job, or reports its error.
+    pub fn handle_callback(&self, editor: &mut Editor, compositor: &mut Compositor, call: anyhow::Result<Option<Callback>>) {
+        match call {
+            Ok(None) => {}
+            Ok(Some(call)) => call(editor, compositor),
+            Err(err) => editor.set_error(format!("Async job failed: {err}")),
+        }
+    }
+}
@@ -11,7 +11,11 @@ use arc_swap::access::{DynAccess, DynGuard};
 
 use crate::{
     term::commands::MappableCommand,
-    view::{document::Mode, input::KeyEvent},
+    view::{
+        document::Mode,
+        input::KeyEvent,
+        keyboard::{KeyCode, KeyModifiers},
+    },
 };
 
 pub use default::default;
@@ -129,18 +133,43 @@ impl Keymaps {
         let keymaps = &*self.map();
         let keymap = &keymaps[&mode];
 
-        // TODO: care state
-        let first = &key;
-        // TODO: handle sticky
-        let trie_node = Cow::Borrowed(&keymap.root);
+        if key.code == KeyCode::Esc && key.modifiers == KeyModifiers::NONE {
+            if !self.state.is_empty() {
+                // Note that Esc is not included here
+                return KeymapResult::Cancelled(self.state.drain(..).collect());
+            }
+            self.sticky = None;
+        }
+
+        let first = self.state.first().unwrap_or(&key);
+        let trie_node = match self.sticky {
+            Some(ref trie) => Cow::Owned(KeyTrie::Node(trie.clone())),
+            None => Cow::Borrowed(&keymap.root),
+        };
 
         let trie = match trie_node.search(&[*first]) {
             Some(KeyTrie::Leaf(ref cmd)) => {
                 return KeymapResult::Matched(cmd.clone());
             }
             None => return KeymapResult::NotFound,
-            Some(t) => todo!(),
+            Some(t) => t,
         };
+
+        self.state.push(key);
+        match trie.search(&self.state[1..]) {
+            Some(KeyTrie::Node(map)) => {
+                if map.is_sticky {
+                    self.state.clear();
+                    self.sticky = Some(map.clone());
+                }
+                KeymapResult::Pending(map.clone())
+            }
+            Some(KeyTrie::Leaf(cmd)) => {
+                self.state.clear();
+                KeymapResult::Matched(cmd.clone())
+            }
+            None => KeymapResult::Cancelled(self.state.drain(..).collect()),
+        }
     }
 
     pub fn map(&self) -> DynGuard<HashMap<Mode, Keymap>> {
@@ -12,10 +12,62 @@ pub fn default() -> HashMap<Mode, Keymap> {
     "h" => move_char_left,
     "j" => move_visual_line_down,
     "k" => move_visual_line_up,
+    "i" => insert_mode,
+    ":" => command_mode,
+    "g" => { "Goto"
+        "d" => goto_definition,
+        "D" => goto_declaration,
+        "y" => goto_type_definition,
+        "i" => goto_implementation,
+        "r" => goto_reference,
+        "n" => goto_next_buffer,
+        "p" => goto_previous_buffer,
+    },
+    "[" => { "Left bracket"
+        "d" => goto_prev_diag,
+        "D" => goto_first_diag,
+    },
+    "]" => { "Right bracket"
+        "d" => goto_next_diag,
+        "D" => goto_last_diag,
+    },
+    "space" => { "Space"
+        "f" => file_picker,
+        "b" => buffer_picker,
+        "d" => diagnostics_picker,
+        "D" => workspace_diagnostics_picker,
+        "s" => symbol_picker,
+        "S" => workspace_symbol_picker,
+    },
+    "C-w" => { "Window"
+        "h" | "left" => jump_view_left,
+        "j" | "down" => jump_view_down,
+        "k" | "up" => jump_view_up,
+        "l" | "right" => jump_view_right,
+        "H" => swap_view_left,
+        "J" => swap_view_down,
+        "K" => swap_view_up,
+        "L" => swap_view_right,
+        "q" => wclose,
+        "o" => wonly,
+        "s" => hsplit,
+        "v" => vsplit,
+        "t" => transpose_view,
+        "+" => grow_view,
+        "minus" => shrink_view,
+    },
     "q" => _quit,
     });
 
+    let insert = keymap!({ "Insert mode"
+    "esc" => normal_mode,
+    "ret" => insert_newline,
+    "backspace" => delete_char_backward,
+    "C-x" => completion,
+    });
+
     hashmap!(
         Mode::Normal => Keymap::new(normal),
+        Mode::Insert => Keymap::new(insert),
     )
 }
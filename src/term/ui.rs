@@ -1,2 +1,196 @@
+pub mod completion;
 pub mod document;
 pub mod editor;
+pub mod fuzzy_match;
+pub mod menu;
+pub mod overlay;
+pub mod picker;
+pub mod popup;
+pub mod prompt;
+pub mod statusline;
+pub mod text_decorations;
+
+pub use menu::Menu;
+pub use overlay::{overlaid, Overlay};
+pub use picker::{DynamicPicker, Picker};
+pub use popup::Popup;
+pub use prompt::{Completion, Prompt, PromptEvent};
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use ignore::{DirEntry, WalkBuilder};
+
+use crate::{
+    core::path::get_canonicalized_path,
+    view::editor::{Editor, FilePickerConfig},
+};
+
+/// Maximum number of files listed by the file picker.
+const MAX_FILES: usize = 100_000;
+/// How long the file picker waits for the walker before it is shown, so that small directories
+/// are listed at once instead of flickering in.
+const FILE_PICKER_INITIAL_TIMEOUT: Duration = Duration::from_millis(30);
+/// Minimum interval between the redraws requested while files are being listed.
+const FILE_PICKER_REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Skips the version control directories, which would otherwise be listed along with the hidden
+/// files.
+fn filter_picker_entry(entry: &DirEntry) -> bool {
+    !matches!(entry.file_name().to_str(), Some(".git" | ".pijul" | ".jj" | ".hg" | ".svn"))
+}
+
+/// The files below `root` honouring `config`, in depth-first order with the entries of each
+/// directory sorted by name.
+fn walk_files(root: &Path, config: &FilePickerConfig) -> impl Iterator<Item = PathBuf> + Send {
+    let mut walk_builder = WalkBuilder::new(root);
+    walk_builder
+        .hidden(config.hidden)
+        .follow_links(config.follow_symlinks)
+        .parents(config.parents)
+        .ignore(config.ignore)
+        .git_ignore(config.git_ignore)
+        .git_exclude(config.git_exclude)
+        .max_depth(config.max_depth)
+        .sort_by_file_name(|name1, name2| name1.cmp(name2))
+        .filter_entry(filter_picker_entry);
+
+    walk_builder.build().filter_map(|entry| {
+        let entry = entry.ok()?;
+        // symlinks only have the type of their target when they are followed
+        entry.file_type()?.is_file().then(|| entry.into_path())
+    })
+}
+
+/// Lists the files below `root` honouring the `file-picker` config. The directory is walked in a
+/// background thread and the files are streamed into the picker as they are found.
+pub fn file_picker(root: PathBuf, editor: &Editor) -> Picker<PathBuf> {
+    let config = editor.config();
+    let root = get_canonicalized_path(&root).unwrap_or(root);
+
+    let mut files = walk_files(&root, &config.file_picker).take(MAX_FILES);
+
+    let (picker, injector) = Picker::stream(
+        {
+            let root = root.clone();
+            move |path: &PathBuf| path.strip_prefix(&root).unwrap_or(path).to_string_lossy()
+        },
+        |cx, path: &PathBuf, action| {
+            if let Err(e) = cx.editor.open(path, action) {
+                cx.editor.set_error(format!("failed to open '{}': {e}", path.display()));
+            }
+        },
+        editor.redraw_handle.clone(),
+    );
+
+    let start = Instant::now();
+    for path in files.by_ref() {
+        injector.push(path);
+        if start.elapsed() >= FILE_PICKER_INITIAL_TIMEOUT {
+            break;
+        }
+    }
+
+    std::thread::spawn(move || {
+        let mut last_redraw = Instant::now();
+        for path in files {
+            if !injector.push(path) {
+                // the picker was closed
+                return;
+            }
+            if last_redraw.elapsed() >= FILE_PICKER_REDRAW_INTERVAL {
+                injector.redraw();
+                last_redraw = Instant::now();
+            }
+        }
+        injector.redraw();
+    });
+
+    picker.with_preview(|_editor, path| Some((path.clone(), None)))
+}
+
+pub mod completers {
+    use std::cmp::Reverse;
+
+    use super::{fuzzy_match::FuzzyQuery, Completion};
+    use crate::view::editor::Editor;
+
+    /// Completes an argument of a typable command.
+    pub type Completer = fn(&Editor, &str) -> Vec<Completion>;
+
+    /// The names of the available themes matching `input`, best matches first.
+    pub fn theme(editor: &Editor, input: &str) -> Vec<Completion> {
+        let query = FuzzyQuery::new(input);
+        let mut names: Vec<_> = editor
+            .theme_loader
+            .names()
+            .into_iter()
+            .filter_map(|name| query.fuzzy_match(&name).map(|score| (name, score)))
+            .collect();
+        // the names are sorted, the sort is stable
+        names.sort_by_key(|&(_, score)| Reverse(score));
+        names.into_iter().map(|(name, _)| (0.., name.into())).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn walk_honours_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for dir in [".git", "src", "target", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for (file, contents) in [
+            (".git/HEAD", ""),
+            (".gitignore", "target/\n*.tmp\n"),
+            ("src/.ignore", "skip.rs\n"),
+            ("src/main.rs", ""),
+            ("src/skip.rs", ""),
+            ("src/notes.tmp", ""),
+            ("target/out", ""),
+            (".hidden/file", ""),
+            ("README", ""),
+        ] {
+            fs::write(root.join(file), contents).unwrap();
+        }
+
+        let walk = |config: FilePickerConfig| -> Vec<String> {
+            walk_files(root, &config)
+                .map(|path| path.strip_prefix(root).unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(walk(FilePickerConfig::default()), ["README", "src/main.rs"]);
+        // the version control directory is never listed
+        assert_eq!(
+            walk(FilePickerConfig {
+                hidden: false,
+                ignore: false,
+                ..FilePickerConfig::default()
+            }),
+            [
+                ".gitignore",
+                ".hidden/file",
+                "README",
+                "src/.ignore",
+                "src/main.rs",
+                "src/skip.rs"
+            ]
+        );
+        assert_eq!(
+            walk(FilePickerConfig {
+                max_depth: Some(1),
+                ..FilePickerConfig::default()
+            }),
+            ["README"]
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use std::borrow::Cow;
+
+use crate::{
+    core::{
+        unicode::width::{UnicodeWidthChar, UnicodeWidthStr},
+        Transaction,
+    },
+    current, doc,
+    lsp::{
+        lsp::{self, CompletionItem, CompletionItemKind, InsertTextFormat},
+        snippet, util, OffsetEncoding,
+    },
+    term::{
+        commands,
+        compositor::{Component, Context, EventResult},
+        ui::{editor::EditorView, menu, Menu, Popup, PromptEvent},
+    },
+    tui::buffer::Buffer as Surface,
+    view::{document::Document, editor::Editor, graphics::Rect, input::Event, ViewId},
+};
+
+/// Documentation is only shown beside the menu if it can be at least this wide.
+const MIN_DOC_WIDTH: u16 = 20;
+const MAX_DOC_WIDTH: u16 = 60;
+const MAX_DOC_HEIGHT: u16 = 15;
+
+impl menu::Item for CompletionItem {
+    /// The widths of the label and kind columns.
+    type Data = (usize, usize);
+
+    fn format(&self, &(label_width, kind_width): &Self::Data) -> Cow<'_, str> {
+        let padding = label_width.saturating_sub(self.label.width());
+        let kind = kind_name(self.kind);
+        // only the first line of the detail fits in the menu, the rest is in the documentation
+        match self.detail.as_deref().and_then(|detail| detail.lines().next()) {
+            Some(detail) => format!("{}{:padding$}  {kind:kind_width$}  {detail}", self.label, "").into(),
+            None => format!("{}{:padding$}  {kind}", self.label, "").into(),
+        }
+    }
+
+    fn filter_text(&self, _data: &Self::Data) -> Cow<'_, str> {
+        self.filter_text.as_deref().unwrap_or(&self.label).into()
+    }
+}
+
+fn kind_name(kind: Option<CompletionItemKind>) -> &'static str {
+    match kind {
+        Some(CompletionItemKind::TEXT) => "text",
+        Some(CompletionItemKind::METHOD) => "method",
+        Some(CompletionItemKind::FUNCTION) => "function",
+        Some(CompletionItemKind::CONSTRUCTOR) => "constructor",
+        Some(CompletionItemKind::FIELD) => "field",
+        Some(CompletionItemKind::VARIABLE) => "variable",
+        Some(CompletionItemKind::CLASS) => "class",
+        Some(CompletionItemKind::INTERFACE) => "interface",
+        Some(CompletionItemKind::MODULE) => "module",
+        Some(CompletionItemKind::PROPERTY) => "property",
+        Some(CompletionItemKind::UNIT) => "unit",
+        Some(CompletionItemKind::VALUE) => "value",
+        Some(CompletionItemKind::ENUM) => "enum",
+        Some(CompletionItemKind::KEYWORD) => "keyword",
+        Some(CompletionItemKind::SNIPPET) => "snippet",
+        Some(CompletionItemKind::COLOR) => "color",
+        Some(CompletionItemKind::FILE) => "file",
+        Some(CompletionItemKind::REFERENCE) => "reference",
+        Some(CompletionItemKind::FOLDER) => "folder",
+        Some(CompletionItemKind::ENUM_MEMBER) => "enum_member",
+        Some(CompletionItemKind::CONSTANT) => "constant",
+        Some(CompletionItemKind::STRUCT) => "struct",
+        Some(CompletionItemKind::EVENT) => "event",
+        Some(CompletionItemKind::OPERATOR) => "operator",
+        Some(CompletionItemKind::TYPE_PARAMETER) => "type_param",
+        _ => "",
+    }
+}
+
+/// The menu of the completions of a language server at the cursor in insert mode, with the
+/// documentation of the selected item beside it. The items are filtered with the text typed
+/// since the start of the completed word, and the picked item is applied to every cursor.
+pub struct Completion {
+    popup: Popup<Menu<CompletionItem>>,
+    /// Where the completed word starts.
+    start_offset: usize,
+}
+
+impl Completion {
+    pub const ID: &'static str = "completion";
+
+    /// `trigger_offset` is the cursor when the completions were requested, the positions of
+    /// their edits are relative to the text at that time.
+    pub fn new(
+        editor: &Editor,
+        mut items: Vec<CompletionItem>,
+        offset_encoding: OffsetEncoding,
+        start_offset: usize,
+        trigger_offset: usize,
+    ) -> Self {
+        // the sort is stable, items without a sort text keep the order of the server
+        items.sort_by(|a, b| {
+            let a = a.sort_text.as_ref().unwrap_or(&a.label);
+            let b = b.sort_text.as_ref().unwrap_or(&b.label);
+            a.cmp(b)
+        });
+        let label_width = items.iter().map(|item| item.label.width()).max().unwrap_or(0);
+        let kind_width = items.iter().map(|item| kind_name(item.kind).len()).max().unwrap_or(0);
+
+        let menu = Menu::new(items, (label_width, kind_width), move |editor: &mut Editor, item, event| {
+            let (PromptEvent::Validate, Some(item)) = (event, item) else {
+                return;
+            };
+            let (view, doc) = current!(editor);
+            apply_completion(doc, view.id, item, offset_encoding, start_offset, trigger_offset);
+        });
+        // escape leaves insert mode, which closes the menu
+        let popup = Popup::new(Self::ID, menu).ignore_escape_key(true);
+
+        let mut completion = Self { popup, start_offset };
+        completion.recompute_filter(editor);
+        completion
+    }
+
+    /// Filters the items with the text from the start of the word to the cursor, or clears them
+    /// if the cursor moved before it.
+    pub fn recompute_filter(&mut self, editor: &Editor) {
+        let view = crate::view!(editor);
+        let doc = doc!(editor, &view.doc);
+        let text = doc.text().slice(..);
+        let cursor = doc.selection(view.id).primary().cursor(text);
+
+        let menu = self.popup.contents_mut();
+        if self.start_offset <= cursor {
+            menu.score(&Cow::from(text.slice(self.start_offset..cursor)));
+        } else {
+            menu.clear();
+        }
+    }
+
+    /// Updates the menu after a key was handled by the editor.
+    pub fn update(&mut self, cx: &mut commands::Context) {
+        self.recompute_filter(cx.editor)
+    }
+
+    /// Whether no item matches the typed text, the menu is closed then.
+    pub fn is_empty(&self) -> bool {
+        self.popup.contents().is_empty()
+    }
+
+    pub fn replace_item(&mut self, old_item: &CompletionItem, new_item: CompletionItem) {
+        self.popup.contents_mut().replace_option(old_item, new_item);
+    }
+
+    /// Asks the language server for the documentation and additional edits of the selected
+    /// item if it left them out. Returns whether a request was sent.
+    pub fn ensure_item_resolved(&mut self, cx: &mut commands::Context) -> bool {
+        let Some(item) = self.popup.contents().selection() else {
+            return false;
+        };
+        if item.documentation.is_some() {
+            return false;
+        }
+        let Some(language_server) = doc!(cx.editor).language_server() else {
+            return false;
+        };
+        let Some(future) = language_server.resolve_completion_item(item.clone()) else {
+            return false;
+        };
+
+        let item = item.clone();
+        cx.callback(future, move |_editor, compositor, resolved_item: CompletionItem| {
+            let completion = compositor
+                .find::<EditorView>()
+                .and_then(|editor_view| editor_view.completion.as_mut());
+            if let Some(completion) = completion {
+                completion.replace_item(&item, resolved_item);
+            }
+        });
+        true
+    }
+
+    /// Draws the detail and documentation of the selected item beside the menu, on the right
+    /// if there is room.
+    fn render_documentation(&mut self, viewport: Rect, surface: &mut Surface, cx: &mut Context) {
+        let Some(item) = self.popup.contents().selection() else {
+            return;
+        };
+        let documentation = documentation(item);
+        if documentation.is_empty() {
+            return;
+        }
+
+        let menu_area = self.popup.area(viewport, cx.editor);
+        let right = viewport.right().saturating_sub(menu_area.right());
+        let left = menu_area.left().saturating_sub(viewport.left());
+        let (x, width) = if right >= MIN_DOC_WIDTH {
+            (menu_area.right(), right.min(MAX_DOC_WIDTH))
+        } else if left >= MIN_DOC_WIDTH {
+            let width = left.min(MAX_DOC_WIDTH);
+            (menu_area.left() - width, width)
+        } else {
+            return;
+        };
+
+        // one column of padding on each side
+        let lines = wrap(&documentation, width as usize - 2);
+        let height = (lines.len() as u16)
+            .min(MAX_DOC_HEIGHT)
+            .min(viewport.bottom().saturating_sub(menu_area.y));
+        let area = Rect::new(x, menu_area.y, width, height);
+
+        let theme = &cx.editor.theme;
+        surface.clear_with(area, theme.get("ui.popup"));
+        let style = theme.get("ui.text");
+        for (i, line) in lines.iter().take(height as usize).enumerate() {
+            surface.set_stringn(area.x + 1, area.y + i as u16, line, width as usize - 2, style);
+        }
+    }
+}
+
+impl Component for Completion {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        self.popup.handle_event(event, cx)
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        self.popup.required_size(viewport)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.popup.render(area, surface, cx);
+        self.render_documentation(area, surface, cx);
+    }
+
+    fn id(&self) -> Option<&'static str> {
+        Some(Self::ID)
+    }
+}
+
+/// The range replaced by `item` relative to the primary cursor, its new text and whether it is a
+/// snippet. `None` if the range of its edit is out of bounds.
+fn completion_edit<'i>(
+    doc: &Document,
+    view_id: ViewId,
+    item: &'i CompletionItem,
+    offset_encoding: OffsetEncoding,
+    start_offset: usize,
+    trigger_offset: usize,
+) -> Option<(i128, i128, &'i str, bool)> {
+    let text = doc.text();
+    let cursor = doc.selection(view_id).primary().cursor(text.slice(..)) as i128;
+    let is_snippet = item.insert_text_format == Some(InsertTextFormat::SNIPPET);
+
+    let Some(edit) = &item.text_edit else {
+        // replace the completed word, including what was typed since the request
+        let new_text = item.insert_text.as_deref().unwrap_or(&item.label);
+        return Some((start_offset as i128 - cursor, 0, new_text, is_snippet));
+    };
+
+    let (range, new_text) = match edit {
+        lsp::CompletionTextEdit::Edit(edit) => (edit.range, &edit.new_text),
+        lsp::CompletionTextEdit::InsertAndReplace(edit) => (edit.replace, &edit.new_text),
+    };
+    // the range is relative to the text at the time of the request, what was typed since moved
+    // the positions after the trigger. An end at the trigger also replaces the typed text, a
+    // start at the trigger stays before it.
+    let typed = cursor - trigger_offset as i128;
+    let offset = |pos, is_end: bool| {
+        let pos = util::lsp_pos_to_pos(text, pos, offset_encoding)? as i128;
+        let trigger_offset = trigger_offset as i128;
+        let moved = pos > trigger_offset || (is_end && pos == trigger_offset);
+        Some(if moved { pos + typed } else { pos } - cursor)
+    };
+    Some((offset(range.start, false)?, offset(range.end, true)?, new_text, is_snippet))
+}
+
+/// Applies `item` to every cursor of the view, along with its additional edits, e.g. an import.
+fn apply_completion(
+    doc: &mut Document,
+    view_id: ViewId,
+    item: &CompletionItem,
+    offset_encoding: OffsetEncoding,
+    start_offset: usize,
+    trigger_offset: usize,
+) {
+    let Some((start, end, new_text, is_snippet)) =
+        completion_edit(doc, view_id, item, offset_encoding, start_offset, trigger_offset)
+    else {
+        tracing::warn!("completion edit out of bounds - {item:?}");
+        return;
+    };
+
+    // the additional edits are relative to the text before the completion, the completion is
+    // relative to the cursors which are moved by them. Both make a single transaction.
+    let mut text = doc.text().clone();
+    let mut selection = doc.selection(view_id).clone();
+    let mut transaction = Transaction::new(&text);
+    if let Some(edits) = item.additional_text_edits.clone().filter(|edits| !edits.is_empty()) {
+        transaction = util::generate_transaction_from_edits(&text, edits, offset_encoding);
+        transaction.apply(&mut text);
+        selection = selection.map(transaction.changes());
+    }
+
+    let completion = if is_snippet {
+        util::generate_transaction_from_snippet(
+            &text,
+            &selection,
+            start,
+            end,
+            &snippet::parse(new_text),
+            doc.line_ending.as_str(),
+        )
+    } else {
+        util::generate_transaction_from_completion_edit(&text, &selection, start, end, new_text.to_string())
+    };
+    doc.apply(&transaction.compose(completion), view_id);
+}
+
+/// The detail of an item followed by its documentation. The fences of markdown code blocks are
+/// left out, the rest of the markdown is shown as is.
+fn documentation(item: &CompletionItem) -> String {
+    let mut text = item.detail.clone().unwrap_or_default();
+
+    let documentation = match &item.documentation {
+        Some(lsp::Documentation::String(value)) => value.clone(),
+        Some(lsp::Documentation::MarkupContent(content)) => match content.kind {
+            lsp::MarkupKind::PlainText => content.value.clone(),
+            lsp::MarkupKind::Markdown => content
+                .value
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("```"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        },
+        None => String::new(),
+    };
+    if !text.is_empty() && !documentation.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str(documentation.trim_end());
+    text
+}
+
+/// Splits `text` into lines at most `width` columns wide, breaking them at spaces if possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut current_width = 0;
+        for word in line.split_inclusive(' ') {
+            if current_width + word.trim_end().width() > width && !current.is_empty() {
+                lines.push(current.trim_end().to_string());
+                current.clear();
+                current_width = 0;
+            }
+            // words longer than a line are split anywhere
+            for ch in word.chars() {
+                let ch_width = ch.width().unwrap_or(0);
+                if current_width + ch_width > width && ch != ' ' {
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
+                current.push(ch);
+                current_width += ch_width;
+            }
+        }
+        lines.push(current.trim_end().to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::term::ui::menu::Item;
+
+    #[test]
+    fn format_columns() {
+        let item = |label: &str, kind, detail: Option<&str>| CompletionItem {
+            label: label.into(),
+            kind: Some(kind),
+            detail: detail.map(String::from),
+            ..Default::default()
+        };
+        let widths = (5, 8);
+
+        assert_eq!(item("len", CompletionItemKind::FIELD, None).format(&widths), "len    field");
+        let push = item("push", CompletionItemKind::METHOD, Some("fn push(&mut self, x: T)\nAppends"));
+        assert_eq!(push.format(&widths), "push   method    fn push(&mut self, x: T)");
+    }
+
+    #[test]
+    fn wrap_documentation() {
+        assert_eq!(
+            wrap("Returns the sum of two numbers.\n\nPanics on overflow.", 12),
+            ["Returns the", "sum of two", "numbers.", "", "Panics on", "overflow."]
+        );
+        assert_eq!(wrap("a_very_long_identifier", 8), ["a_very_l", "ong_iden", "tifier"]);
+    }
+}
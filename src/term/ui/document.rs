@@ -1,16 +1,20 @@
+use std::cmp::min;
+
 use crate::{
     core::{
-        doc_formatter::{DocumentFormatter, TextFormat},
+        doc_formatter::{DocumentFormatter, GraphemeSource, TextFormat},
         graphemes::Grapheme,
         position::{visual_offset_from_block, Position},
+        str_utils::char_to_byte_idx,
         syntax::{Highlight, HighlightEvent},
         text_annotations::TextAnnotations,
-        RopeSlice,
+        RopeSlice, Selection,
     },
+    term::ui::text_decorations::LineDecoration,
     tui::buffer::Buffer as Surface,
     view::{
         document::Document,
-        editor::{WhitespaceConfig, WhitespaceRenderValue},
+        editor::{WhitespaceConfig, WhitespaceRender, WhitespaceRenderValue},
         graphics::{Rect, Style},
         theme::Theme,
         view::ViewPosition,
@@ -79,12 +83,16 @@ pub fn render_document(
     doc_annotations: &TextAnnotations,
     highlight_iter: impl Iterator<Item = HighlightEvent>,
     theme: &Theme,
-    // line_decorations: &mut [Box<dyn LineDecoration +'_>],
+    line_decorations: &mut [Box<dyn LineDecoration + '_>],
+    selection: Option<&Selection>,
     // translated_positions: &mut [TranslatedPosition],
 ) {
     tracing::info!("===========Render_document=============");
     tracing::info!("viewport={viewport:?} offse={offset:?}");
     let mut renderer = TextRenderer::new(surface, doc, theme, offset.horizontal_offset, viewport);
+    if let Some(selection) = selection {
+        renderer.selection = selection.iter().map(|range| range.from()..range.to()).collect();
+    }
     render_text(
         &mut renderer,
         doc.text().slice(..),
@@ -93,7 +101,7 @@ pub fn render_document(
         doc_annotations,
         highlight_iter,
         theme,
-        // line_decorations,
+        line_decorations,
         // translated_positions,
     )
 }
@@ -105,15 +113,19 @@ pub struct TextRenderer<'a> {
     pub whitespace_style: Style,
     pub indent_guide_char: String,
     pub indent_guide_style: Style,
+    pub whitespace: WhitespaceRender,
     pub newline: String,
     pub nbsp: String,
     pub space: String,
     pub tab: String,
-    pub tab_width: u16,
+    pub blank_tab: String,
+    pub indent_width: u16,
     pub starting_indent: usize,
     pub draw_indent_guides: bool,
     pub col_offset: usize,
     pub viewport: Rect,
+    /// Char ranges of the selection whitespace is rendered in when using [`WhitespaceRenderValue::Selection`].
+    pub selection: Vec<std::ops::Range<usize>>,
 }
 
 impl<'a> TextRenderer<'a> {
@@ -125,37 +137,65 @@ impl<'a> TextRenderer<'a> {
         } = &editor_config.whitespace;
 
         let tab_width = doc.tab_width();
-        // TODO: handle render tab
-        let tab = " ".repeat(tab_width);
-        let newline = " ".to_owned();
-        let space = " ".to_owned();
-        let nbsp = " ".to_owned();
+        let tab = std::iter::once(ws_chars.tab)
+            .chain(std::iter::repeat_n(ws_chars.tabpad, tab_width - 1))
+            .collect();
 
         let text_style = theme.get("ui.text");
 
+        // TODO: use the indent style of the document once it is detected
+        let indent_width = tab_width as u16;
+
         TextRenderer {
             surface,
-            indent_guide_char: '|'.into(),
-            newline,
-            nbsp,
-            space,
-            tab_width: tab_width as u16,
+            indent_guide_char: editor_config.indent_guides.character.into(),
+            whitespace: *ws_render,
+            newline: ws_chars.newline.into(),
+            nbsp: ws_chars.nbsp.into(),
+            space: ws_chars.space.into(),
             tab,
+            blank_tab: " ".repeat(tab_width),
             whitespace_style: theme.get("ui.virtual.whitespace"),
-            starting_indent: 2,
-            indent_guide_style: theme.get("ui.virtual.whitespace"),
+            indent_width,
+            starting_indent: col_offset / indent_width as usize
+                + !col_offset.is_multiple_of(indent_width as usize) as usize
+                + editor_config.indent_guides.skip_levels as usize,
+            indent_guide_style: text_style.patch(
+                theme
+                    .try_get("ui.virtual.indent-guide")
+                    .unwrap_or_else(|| theme.get("ui.virtual.whitespace")),
+            ),
             text_style,
-            draw_indent_guides: false,
+            draw_indent_guides: editor_config.indent_guides.render,
             viewport,
             col_offset,
+            selection: Vec::new(),
         }
     }
 
-    /// Draws a single `grapheme` at the current render position with a specified `style`.
+    /// Whether whitespace configured with `value` is rendered at `char_idx`, it never is in
+    /// virtual text.
+    fn is_whitespace_visible(&self, value: WhitespaceRenderValue, char_idx: Option<usize>) -> bool {
+        let Some(char_idx) = char_idx else {
+            return false;
+        };
+        match value {
+            WhitespaceRenderValue::None => false,
+            WhitespaceRenderValue::Selection => {
+                let i = self.selection.partition_point(|range| range.end <= char_idx);
+                self.selection.get(i).is_some_and(|range| range.start <= char_idx)
+            }
+            WhitespaceRenderValue::All => true,
+        }
+    }
+
+    /// Draws a single `grapheme` starting at `char_idx` at the current render position with a
+    /// specified `style`. `char_idx` is `None` for a grapheme of virtual text.
     pub fn draw_grapheme(
         &mut self,
         grapheme: Grapheme,
         mut style: Style,
+        char_idx: Option<usize>,
         last_indent_level: &mut usize,
         is_in_indent_area: &mut bool,
         position: Position,
@@ -163,25 +203,44 @@ impl<'a> TextRenderer<'a> {
         let cut_off_start = self.col_offset.saturating_sub(position.col);
         let is_whitespace = grapheme.is_whitespace();
 
-        if is_whitespace {
+        if is_whitespace && char_idx.is_some() {
             style = style.patch(self.whitespace_style);
         }
 
         let width = grapheme.width();
-        let space = &self.space;
-        let nbsp = &self.nbsp;
-        let tab = &self.tab;
         let grapheme = match grapheme {
             Grapheme::Tab { width } => {
-                // let grapheme_tab_width = char_to_byte_idx(tab, width);
-                // &tab[..grapheme_tab_width]
-                &tab[..]
+                let tab = if self.is_whitespace_visible(self.whitespace.tab(), char_idx) {
+                    &self.tab
+                } else {
+                    &self.blank_tab
+                };
+                let grapheme_tab_width = char_to_byte_idx(tab, width);
+                &tab[..grapheme_tab_width]
             }
             // TODO special rendering for other whitespaces?
-            Grapheme::Other { ref g } if g == " " => space,
-            Grapheme::Other { ref g } if g == "\u{00A0}" => nbsp,
+            Grapheme::Other { ref g } if g == " " => {
+                if self.is_whitespace_visible(self.whitespace.space(), char_idx) {
+                    &self.space
+                } else {
+                    " "
+                }
+            }
+            Grapheme::Other { ref g } if g == "\u{00A0}" => {
+                if self.is_whitespace_visible(self.whitespace.nbsp(), char_idx) {
+                    &self.nbsp
+                } else {
+                    " "
+                }
+            }
             Grapheme::Other { ref g } => g,
-            Grapheme::Newline => &self.newline,
+            Grapheme::Newline => {
+                if self.is_whitespace_visible(self.whitespace.newline(), char_idx) {
+                    &self.newline
+                } else {
+                    " "
+                }
+            }
         };
 
         let in_bounds = self.col_offset <= position.col && position.col < self.viewport.width as usize + self.col_offset;
@@ -194,11 +253,44 @@ impl<'a> TextRenderer<'a> {
                 style,
             );
         } else if cut_off_start != 0 && cut_off_start < width {
-            // TODO:
-            todo!()
+            // partially on screen
+            let rect = Rect::new(
+                self.viewport.x,
+                self.viewport.y + position.row as u16,
+                (width - cut_off_start) as u16,
+                1,
+            );
+            self.surface.set_style(rect, style);
+        }
+
+        if *is_in_indent_area && !is_whitespace {
+            *last_indent_level = position.col;
+            *is_in_indent_area = false;
+        }
+    }
+
+    /// Overlays indentation guides on top of a rendered line.
+    /// The indentation level is computed while drawing the line's graphemes,
+    /// so this must always be called afterwards.
+    pub fn draw_indent_guides(&mut self, indent_level: usize, row: u16) {
+        if !self.draw_indent_guides {
+            return;
         }
 
-        // TODO: handle indent_level
+        // Don't draw indent guides outside of view
+        let end_indent = min(
+            indent_level,
+            // Add indent_width - 1 to round up, since the first visible
+            // indent might be a bit after offset.col
+            self.col_offset + self.viewport.width as usize + (self.indent_width as usize - 1),
+        ) / self.indent_width as usize;
+
+        for i in self.starting_indent..end_indent {
+            let x = (self.viewport.x as usize + (i * self.indent_width as usize) - self.col_offset) as u16;
+            let y = self.viewport.y + row;
+            self.surface
+                .set_string(x, y, &self.indent_guide_char, self.indent_guide_style);
+        }
     }
 }
 
@@ -211,7 +303,7 @@ pub fn render_text<'t>(
     text_annotations: &TextAnnotations,
     highlight_iter: impl Iterator<Item = HighlightEvent>,
     theme: &Theme,
-    // line_decorations: &mut [Box<dyn LineDecoration + '_>],
+    line_decorations: &mut [Box<dyn LineDecoration + '_>],
     // translated_position: &mut [TranslatedPosition],
 ) {
     let (Position { row: mut row_off, .. }, mut char_pos) =
@@ -252,20 +344,124 @@ pub fn render_text<'t>(
             break;
         }
 
+        // apply decorations before rendering a new line
+        if pos.row as u16 != last_line_pos.visual_line {
+            if pos.row > 0 {
+                renderer.draw_indent_guides(last_line_indent_level, last_line_pos.visual_line);
+                is_in_indent_area = true;
+                for line_decoration in &mut *line_decorations {
+                    line_decoration.render_foreground(renderer, last_line_pos, char_pos);
+                }
+            }
+            last_line_pos = LinePos {
+                first_visual_line: doc_line != last_line_pos.doc_line,
+                doc_line,
+                visual_line: pos.row as u16,
+                start_char_idx: char_pos,
+            };
+            for line_decoration in &mut *line_decorations {
+                line_decoration.render_background(renderer, last_line_pos);
+            }
+        }
+
         // acquire the correct grapheme style
         if char_pos >= style_span.1 {
             style_span = styles.next().unwrap_or((Style::default(), usize::MAX));
         }
+        let grapheme_char_idx = (!grapheme.is_virtual()).then_some(char_pos);
         char_pos += grapheme.doc_chars();
 
-        let grapheme_style = style_span.0;
+        // virtual text isn't part of the highlights, it carries its own
+        let grapheme_style = match grapheme.source {
+            GraphemeSource::VirtualText { highlight } => highlight.map_or(renderer.text_style, |highlight| {
+                renderer.text_style.patch(theme.highlight(highlight.0))
+            }),
+            GraphemeSource::Document { .. } => style_span.0,
+        };
 
         renderer.draw_grapheme(
             grapheme.grapheme,
             grapheme_style,
+            grapheme_char_idx,
             &mut last_line_indent_level,
             &mut is_in_indent_area,
             pos,
         );
     }
+
+    renderer.draw_indent_guides(last_line_indent_level, last_line_pos.visual_line);
+    for line_decoration in &mut *line_decorations {
+        line_decoration.render_foreground(renderer, last_line_pos, char_pos);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arc_swap::ArcSwap;
+
+    use super::*;
+    use crate::{
+        core::Rope,
+        view::{editor::Config, theme::DEFAULT_THEME},
+    };
+
+    /// Renders `text` into a 10x2 surface and returns its rows.
+    fn render(text: &str, config: Config, selection: Selection) -> Vec<String> {
+        let config = Arc::new(ArcSwap::from_pointee(config));
+        let doc = Document::from(Rope::from(text), None, config);
+        let area = Rect::new(0, 0, 10, 2);
+        let mut surface = Surface::empty(area);
+        let offset = ViewPosition {
+            anchor: 0,
+            horizontal_offset: 0,
+            vertical_offset: 0,
+        };
+        render_document(
+            &mut surface,
+            area,
+            &doc,
+            offset,
+            &TextAnnotations::default(),
+            std::iter::once(HighlightEvent::Source {
+                start: 0,
+                end: doc.text().len_chars(),
+            }),
+            &DEFAULT_THEME,
+            &mut [],
+            Some(&selection),
+        );
+
+        (0..area.height)
+            .map(|y| (0..area.width).map(|x| surface[(x, y)].symbol.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn whitespace_in_selection() {
+        let mut config = Config::default();
+        config.whitespace.render = WhitespaceRender::Basic(WhitespaceRenderValue::Selection);
+
+        assert_eq!(
+            render("a b c\n", config, Selection::single(2, 6)),
+            vec!["a b·c⏎    ", "          "]
+        );
+    }
+
+    #[test]
+    fn indent_guides() {
+        let mut config = Config::default();
+        config.indent_guides.render = true;
+        assert_eq!(
+            render("\t\tx\n    y\n", config.clone(), Selection::point(0)),
+            vec!["│   │   x ", "│   y     "]
+        );
+
+        config.indent_guides.skip_levels = 1;
+        assert_eq!(
+            render("\t\tx\n    y\n", config, Selection::point(0)),
+            vec!["    │   x ", "    y     "]
+        );
+    }
 }
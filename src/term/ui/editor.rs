@@ -1,23 +1,31 @@
-use std::collections::HashMap;
+use std::rc::Rc;
 
 use arc_swap::access::DynGuard;
 
 use crate::{
     core::{
-        graphemes::prev_grapheme_boundary,
+        diagnostic::Severity,
+        graphemes::{next_grapheme_boundary, prev_grapheme_boundary},
+        movement::Direction,
+        position::{visual_offset_from_block, Position},
         syntax::{self, HighlightEvent},
+        text_annotations::TextAnnotations,
+        unicode::width::UnicodeWidthStr,
+        Range, Selection,
     },
     current, doc_mut,
+    lsp::{lsp, OffsetEncoding},
     term::{
         commands,
         compositor::{self, Component, Context, EventResult},
         keymap::{KeymapResult, Keymaps},
     },
+    view,
     view::{
-        document::{Document, Mode},
-        editor::{CursorShapeConfig, Editor},
-        graphics::Rect,
-        input::{Event, KeyEvent},
+        document::{Document, Mode, SCRATCH_BUFFER_NAME},
+        editor::{BufferLine, CursorShapeConfig, Editor},
+        graphics::{Color, CursorKind, Rect, Style},
+        input::{Event, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
         keyboard::{KeyCode, KeyModifiers},
         theme::Theme,
         view::View,
@@ -27,15 +35,25 @@ use crate::{
 
 use crate::tui::buffer::Buffer as Surface;
 
-use super::document::render_document;
+use super::{
+    completion::Completion,
+    document::{render_document, LinePos, TextRenderer},
+    statusline,
+    text_decorations::LineDecoration,
+};
 
 pub struct EditorView {
     pub keymaps: Keymaps,
+    /// The completion menu, open in insert mode.
+    pub completion: Option<Completion>,
 }
 
 impl EditorView {
     pub fn new(keymaps: Keymaps) -> Self {
-        Self { keymaps }
+        Self {
+            keymaps,
+            completion: None,
+        }
     }
 
     pub fn render_view(
@@ -55,6 +73,13 @@ impl EditorView {
         let text_annotations = view.text_annotations(doc, Some(theme));
 
         let mut highlights = Self::doc_syntax_highlights(doc, view.offset.anchor, inner.height, theme);
+        for diagnostics in Self::doc_diagnostics_highlights(doc, theme) {
+            // most of the time there are no diagnostics of a severity, skip merging them
+            if diagnostics.is_empty() {
+                continue;
+            }
+            highlights = Box::new(syntax::merge(highlights, diagnostics));
+        }
 
         let highlights: Box<dyn Iterator<Item = HighlightEvent>> = if is_focused {
             let highlights = syntax::merge(
@@ -67,6 +92,17 @@ impl EditorView {
             Box::new(highlights)
         };
 
+        let mut line_decorations: Vec<Box<dyn LineDecoration>> = Vec::new();
+        if is_focused && config.cursorline {
+            line_decorations.push(Self::cursorline(doc, view, theme));
+        }
+
+        if is_focused && config.cursorcolumn {
+            Self::highlight_cursorcolumn(doc, view, surface, theme, inner, &text_annotations);
+        }
+
+        Self::render_gutter(editor, doc, view, view.area, theme, is_focused, &mut line_decorations);
+
         render_document(
             surface,
             inner,
@@ -75,9 +111,224 @@ impl EditorView {
             &text_annotations,
             highlights,
             theme,
-            // &mut line_decorations,
+            &mut line_decorations,
+            Some(doc.selection(view.id)),
             // &mut translated_positions,
-        )
+        );
+
+        Self::render_rulers(editor, doc, view, inner, surface, theme);
+
+        let statusline_area = area.clip_top(area.height.saturating_sub(1));
+        let mut context = statusline::RenderContext::new(editor, doc, view, is_focused);
+        statusline::render(&mut context, statusline_area, surface);
+
+        // if we're not at the edge of the screen, draw a right border
+        if viewport.right() != area.right() {
+            let x = area.right();
+            let border_style = theme.get("ui.window");
+            for y in area.top()..area.bottom() {
+                surface[(x, y)].set_symbol("│").set_style(border_style);
+            }
+        }
+    }
+
+    /// Renders a row listing every open document, highlighting the one in the focused view.
+    /// The row is scrolled so that the current document is always visible.
+    pub fn render_bufferline(editor: &Editor, viewport: Rect, surface: &mut Surface) {
+        let theme = &editor.theme;
+        surface.clear_with(
+            viewport,
+            theme
+                .try_get("ui.bufferline.background")
+                .unwrap_or_else(|| theme.get("ui.statusline")),
+        );
+
+        let bufferline_active = theme
+            .try_get("ui.bufferline.active")
+            .unwrap_or_else(|| theme.get("ui.statusline.active"));
+        let bufferline_inactive = theme
+            .try_get("ui.bufferline")
+            .unwrap_or_else(|| theme.get("ui.statusline.inactive"));
+
+        let current_doc = view!(editor).doc;
+
+        let entries: Vec<_> = editor
+            .documents()
+            .map(|doc| {
+                let fname = doc
+                    .path()
+                    .and_then(|path| path.file_name())
+                    .map_or_else(|| SCRATCH_BUFFER_NAME.into(), |name| name.to_string_lossy());
+                let text = format!(" {}{} ", fname, if doc.is_modified() { "[+]" } else { "" });
+                (doc.id, text)
+            })
+            .collect();
+
+        // skip entries on the left until the current document fits
+        let current = entries.iter().position(|(id, _)| *id == current_doc).unwrap_or(0);
+        let mut start = 0;
+        while start < current
+            && entries[start..=current].iter().map(|(_, text)| text.width()).sum::<usize>() > viewport.width as usize
+        {
+            start += 1;
+        }
+
+        let mut x = viewport.x;
+        for (id, text) in &entries[start..] {
+            let style = if *id == current_doc {
+                bufferline_active
+            } else {
+                bufferline_inactive
+            };
+            let rem_width = viewport.right().saturating_sub(x);
+            x = surface.set_stringn(x, viewport.y, text, rem_width as usize, style).0;
+            if x >= viewport.right() {
+                break;
+            }
+        }
+    }
+
+    /// Draws the configured rulers, or the ones of the document's language if it overrides them.
+    pub fn render_rulers(editor: &Editor, doc: &Document, view: &View, viewport: Rect, surface: &mut Surface, theme: &Theme) {
+        let editor_rulers = &editor.config().rulers;
+        let ruler_theme = theme
+            .try_get("ui.virtual.ruler")
+            .unwrap_or_else(|| Style::default().bg(Color::Red));
+
+        let rulers = doc
+            .language
+            .as_ref()
+            .and_then(|config| config.rulers.as_ref())
+            .unwrap_or(editor_rulers);
+
+        rulers
+            .iter()
+            // View might be horizontally scrolled, convert from absolute distance
+            // from the 1st column to relative distance from left of viewport
+            .filter_map(|ruler| ruler.checked_sub(1 + view.offset.horizontal_offset as u16))
+            .filter(|ruler| ruler < &viewport.width)
+            .map(|ruler| viewport.clip_left(ruler).with_width(1))
+            .for_each(|area| surface.set_style(area, ruler_theme))
+    }
+
+    /// Returns a line decoration highlighting every visual line of the lines a cursor is on.
+    pub fn cursorline<'d>(doc: &Document, view: &View, theme: &Theme) -> Box<dyn LineDecoration + 'd> {
+        let text = doc.text().slice(..);
+        let primary_line = doc.selection(view.id).primary().cursor_line(text);
+        // the secondary lines also contain the primary line, which is checked first
+        let secondary_lines: Vec<_> = doc.selection(view.id).iter().map(|range| range.cursor_line(text)).collect();
+
+        let primary_style = theme.get("ui.cursorline.primary");
+        let secondary_style = theme.get("ui.cursorline.secondary");
+        let viewport = view.area;
+
+        let line_decoration = move |renderer: &mut TextRenderer, pos: LinePos| {
+            let area = Rect::new(viewport.x, viewport.y + pos.visual_line, viewport.width, 1);
+            if primary_line == pos.doc_line {
+                renderer.surface.set_style(area, primary_style);
+            } else if secondary_lines.binary_search(&pos.doc_line).is_ok() {
+                renderer.surface.set_style(area, secondary_style);
+            }
+        };
+
+        Box::new(line_decoration)
+    }
+
+    /// Highlights the columns the cursors are on, relative to the start of their visual line.
+    pub fn highlight_cursorcolumn(
+        doc: &Document,
+        view: &View,
+        surface: &mut Surface,
+        theme: &Theme,
+        viewport: Rect,
+        text_annotations: &TextAnnotations,
+    ) {
+        let text = doc.text().slice(..);
+
+        // Manual fallback behaviour:
+        // ui.cursorcolumn.{p/s} -> ui.cursorcolumn -> ui.cursorline.{p/s}
+        let primary_style = theme
+            .try_get_exact("ui.cursorcolumn.primary")
+            .or_else(|| theme.try_get_exact("ui.cursorcolumn"))
+            .unwrap_or_else(|| theme.get("ui.cursorline.primary"));
+        let secondary_style = theme
+            .try_get_exact("ui.cursorcolumn.secondary")
+            .or_else(|| theme.try_get_exact("ui.cursorcolumn"))
+            .unwrap_or_else(|| theme.get("ui.cursorline.secondary"));
+
+        let selection = doc.selection(view.id);
+        let primary_idx = selection.primary_index();
+        let text_format = doc.text_format(viewport.width, None);
+        for (i, range) in selection.iter().enumerate() {
+            let cursor = range.cursor(text);
+            let Position { col, .. } = visual_offset_from_block(text, cursor, cursor, &text_format, text_annotations).0;
+
+            // if the cursor is horizontally in the view
+            if col >= view.offset.horizontal_offset && viewport.width > (col - view.offset.horizontal_offset) as u16 {
+                let area = Rect::new(
+                    viewport.x + (col - view.offset.horizontal_offset) as u16,
+                    view.area.y,
+                    1,
+                    viewport.height,
+                );
+                let style = if i == primary_idx { primary_style } else { secondary_style };
+                surface.set_style(area, style);
+            }
+        }
+    }
+
+    /// Pushes a line decoration drawing each of the configured gutters, left to right from the
+    /// left edge of `viewport`.
+    pub fn render_gutter<'d>(
+        editor: &'d Editor,
+        doc: &'d Document,
+        view: &View,
+        viewport: Rect,
+        theme: &Theme,
+        is_focused: bool,
+        line_decorations: &mut Vec<Box<dyn LineDecoration + 'd>>,
+    ) {
+        // the gutters are left out when they wouldn't leave room for any text
+        if view.gutter_offset(doc) == 0 {
+            return;
+        }
+
+        let text = doc.text().slice(..);
+        let cursors: Rc<[_]> = doc
+            .selection(view.id)
+            .iter()
+            .map(|range| text.char_to_line(range.cursor(text)))
+            .collect();
+
+        let mut offset = 0;
+
+        let gutter_style = theme.get("ui.gutter");
+        let gutter_selected_style = theme.get("ui.gutter.selected");
+
+        for gutter_type in view.gutters(doc) {
+            let mut gutter = gutter_type.style(editor, doc, view, theme, is_focused);
+            let width = gutter_type.width(view, doc);
+            // avoid lots of small allocations by reusing a text buffer for each line
+            let mut text = String::with_capacity(width);
+            let cursors = cursors.clone();
+            let gutter_decoration = move |renderer: &mut TextRenderer, pos: LinePos| {
+                let selected = cursors.contains(&pos.doc_line);
+                let x = viewport.x + offset;
+                let y = viewport.y + pos.visual_line;
+
+                let gutter_style = if selected { gutter_selected_style } else { gutter_style };
+
+                if let Some(style) = gutter(pos.doc_line, selected, pos.first_visual_line, &mut text) {
+                    renderer.surface.set_stringn(x, y, &text, width, gutter_style.patch(style));
+                } else {
+                    renderer.surface.set_style(Rect::new(x, y, width as u16, 1), gutter_style);
+                }
+                text.clear();
+            };
+            line_decorations.push(Box::new(gutter_decoration));
+
+            offset += width as u16;
+        }
     }
 
     /// Get syntax highlights for a document in a view represented by the first line
@@ -113,6 +364,46 @@ impl EditorView {
         )
     }
 
+    /// The spans of the diagnostics of a document, a list per severity from the least to the most
+    /// severe. Diagnostics overlapping one of the same severity are merged into it, the lists
+    /// are merged over one another so the most severe diagnostic wins. Diagnostics are only
+    /// highlighted if the theme has a `diagnostic` scope for them, the ones without a severity
+    /// are warnings.
+    pub fn doc_diagnostics_highlights(doc: &Document, theme: &Theme) -> [Vec<(usize, std::ops::Range<usize>)>; 4] {
+        // `diagnostic.error` falls back to `diagnostic`
+        let hint = theme.find_scope_index("diagnostic.hint");
+        let info = theme.find_scope_index("diagnostic.info");
+        let warning = theme.find_scope_index("diagnostic.warning");
+        let error = theme.find_scope_index("diagnostic.error");
+
+        let mut hint_vec: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
+        let mut info_vec = Vec::new();
+        let mut warning_vec = Vec::new();
+        let mut error_vec = Vec::new();
+
+        for diagnostic in doc.diagnostics() {
+            let (vec, scope) = match diagnostic.severity {
+                Some(Severity::Hint) => (&mut hint_vec, hint),
+                Some(Severity::Info) => (&mut info_vec, info),
+                Some(Severity::Warning) | None => (&mut warning_vec, warning),
+                Some(Severity::Error) => (&mut error_vec, error),
+            };
+            let Some(scope) = scope else {
+                continue;
+            };
+
+            // the diagnostics are sorted by position, so only the previous span can overlap
+            match vec.last_mut() {
+                Some((_, range)) if diagnostic.range.start <= range.end => {
+                    range.end = diagnostic.range.end.max(range.end);
+                }
+                _ => vec.push((scope, diagnostic.range.start..diagnostic.range.end)),
+            }
+        }
+
+        [hint_vec, info_vec, warning_vec, error_vec]
+    }
+
     pub fn doc_selection_highlights(
         mode: Mode,
         doc: &Document,
@@ -122,32 +413,81 @@ impl EditorView {
     ) -> Vec<(usize, std::ops::Range<usize>)> {
         let text = doc.text().slice(..);
         let selection = doc.selection(view.id);
-        tracing::info!("selection={selection:?}");
-        let primary_idx = 0;
-        let cursor_is_block = true;
-        let cursor_scope = theme
-            .find_scope_index_exact("ui.cursor")
-            .expect("could not find `ui.cursor` scope in theme");
+        let primary_idx = selection.primary_index();
+
+        let cursor_kind = cursor_shape_config.from_mode(mode);
+        let cursor_is_block = cursor_kind == CursorKind::Block;
+
+        let selection_scope = theme
+            .find_scope_index_exact("ui.selection")
+            .expect("could not find `ui.selection` scope in theme");
+        let primary_selection_scope = theme
+            .find_scope_index_exact("ui.selection.primary")
+            .unwrap_or(selection_scope);
+
+        let base_cursor_scope = theme.find_scope_index_exact("ui.cursor").unwrap_or(selection_scope);
+        let base_primary_cursor_scope = theme.find_scope_index("ui.cursor.primary").unwrap_or(base_cursor_scope);
+
+        let cursor_scope = match mode {
+            Mode::Insert => theme.find_scope_index_exact("ui.cursor.insert"),
+            Mode::Select => theme.find_scope_index_exact("ui.cursor.select"),
+            Mode::Normal => theme.find_scope_index_exact("ui.cursor.normal"),
+        }
+        .unwrap_or(base_cursor_scope);
+
+        let primary_cursor_scope = match mode {
+            Mode::Insert => theme.find_scope_index_exact("ui.cursor.primary.insert"),
+            Mode::Select => theme.find_scope_index_exact("ui.cursor.primary.select"),
+            Mode::Normal => theme.find_scope_index_exact("ui.cursor.primary.normal"),
+        }
+        .unwrap_or(base_primary_cursor_scope);
+
         let mut spans: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
         for (i, range) in selection.iter().enumerate() {
             let selection_is_primary = i == primary_idx;
+            let (cursor_scope, selection_scope) = if selection_is_primary {
+                (primary_cursor_scope, primary_selection_scope)
+            } else {
+                (cursor_scope, selection_scope)
+            };
 
             // Special-case: cursor at end of the rope.
             if range.head == range.anchor && range.head == text.len_chars() {
                 if !selection_is_primary || cursor_is_block {
+                    // Bar and underline cursors are drawn by the terminal.
                     spans.push((cursor_scope, range.head..range.head + 1));
                 }
                 continue;
             }
-            // TODO: handle Reverse case(range.head < range.anchor)
-            // TODO: handle min_width_1
+
+            let range = range.min_width_1(text);
             if range.head > range.anchor {
+                // Standard case.
                 let cursor_start = prev_grapheme_boundary(text, range.head);
-                // let selection_end = cursor_start;
-                // TODO: push selection range
-                spans.push((cursor_scope, cursor_start..range.head))
+                // non block cursors look like they exclude the cursor
+                let selection_end = if selection_is_primary && !cursor_is_block && mode != Mode::Insert {
+                    range.head
+                } else {
+                    cursor_start
+                };
+                spans.push((selection_scope, range.anchor..selection_end));
+                if !selection_is_primary || cursor_is_block {
+                    spans.push((cursor_scope, cursor_start..range.head));
+                }
             } else {
-                todo!()
+                // Reverse case.
+                let cursor_end = next_grapheme_boundary(text, range.head);
+                if !selection_is_primary || cursor_is_block {
+                    spans.push((cursor_scope, range.head..cursor_end));
+                }
+                // non block cursors look like they exclude the cursor
+                let selection_start =
+                    if selection_is_primary && !cursor_is_block && !(mode == Mode::Insert && cursor_end == range.anchor) {
+                        range.head
+                    } else {
+                        cursor_end
+                    };
+                spans.push((selection_scope, selection_start..range.anchor));
             }
         }
 
@@ -155,7 +495,6 @@ impl EditorView {
     }
 
     fn handle_keymap_event(&mut self, mode: Mode, ctx: &mut commands::Context, event: KeyEvent) -> Option<KeymapResult> {
-        // TODO: handle pending
         let key_result = self.keymaps.get(mode, event);
 
         let mut execute_command = |command: &commands::MappableCommand| {
@@ -167,13 +506,63 @@ impl EditorView {
             KeymapResult::Matched(command) => {
                 execute_command(command);
             }
-            KeymapResult::Pending(_) => todo!(),
-            KeymapResult::MatchedSequence(_) => todo!(),
+            KeymapResult::Pending(_) => {}
+            KeymapResult::MatchedSequence(commands) => {
+                for command in commands {
+                    execute_command(command);
+                }
+            }
             KeymapResult::NotFound | KeymapResult::Cancelled(_) => return Some(key_result),
         }
         None
     }
 
+    /// Opens the completion menu with `items`, unless none of them matches the text typed since
+    /// `start_offset`.
+    pub fn set_completion(
+        &mut self,
+        editor: &Editor,
+        items: Vec<lsp::CompletionItem>,
+        offset_encoding: OffsetEncoding,
+        start_offset: usize,
+        trigger_offset: usize,
+    ) {
+        let completion = Completion::new(editor, items, offset_encoding, start_offset, trigger_offset);
+        self.completion = (!completion.is_empty()).then_some(completion);
+    }
+
+    /// Requests completions once typing paused in insert mode, or the documentation of the
+    /// selected completion if the menu is open.
+    pub fn handle_idle_timeout(&mut self, cx: &mut commands::Context) -> EventResult {
+        if let Some(completion) = &mut self.completion {
+            return if completion.ensure_item_resolved(cx) {
+                EventResult::Consumed(None)
+            } else {
+                EventResult::Ignored(None)
+            };
+        }
+
+        if cx.editor.mode() != Mode::Insert || !cx.editor.config().auto_completion {
+            return EventResult::Ignored(None);
+        }
+        commands::insert::idle_completion(cx);
+        EventResult::Consumed(None)
+    }
+
+    fn insert_mode(&mut self, cx: &mut commands::Context, event: KeyEvent) {
+        if let Some(keyresult) = self.handle_keymap_event(Mode::Insert, cx, event) {
+            match keyresult {
+                KeymapResult::NotFound => {
+                    if let Some(ch) = event.char() {
+                        commands::insert::insert_char(cx, ch)
+                    }
+                }
+                KeymapResult::Cancelled(_) => {}
+                _ => unreachable!(),
+            }
+        }
+    }
+
     fn command_mode(&mut self, mode: Mode, ctx: &mut commands::Context, event: KeyEvent) {
         match (event, ctx.editor.count) {
             // TODO: handle count and '.'
@@ -185,13 +574,121 @@ impl EditorView {
             }
         }
     }
+
+    fn handle_mouse_event(&mut self, event: &MouseEvent, cxt: &mut commands::Context) -> EventResult {
+        let config = cxt.editor.config();
+        if !config.mouse {
+            return EventResult::Ignored(None);
+        }
+        if event.kind != MouseEventKind::Moved {
+            cxt.editor.reset_idle_timer();
+        }
+
+        let MouseEvent {
+            kind,
+            row,
+            column,
+            modifiers,
+        } = *event;
+
+        let pos_and_view = |editor: &Editor, row, column, ignore_virtual_text| {
+            editor.tree.views().find_map(|(view, _focus)| {
+                view.pos_at_screen_coords(&editor.documents[&view.doc], row, column, ignore_virtual_text)
+                    .map(|pos| (pos, view.id))
+            })
+        };
+
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let editor = &mut cxt.editor;
+
+                if let Some((pos, view_id)) = pos_and_view(editor, row, column, true) {
+                    let doc = doc_mut!(editor, &view!(editor, view_id).doc);
+
+                    if modifiers == KeyModifiers::ALT {
+                        let selection = doc.selection(view_id).clone();
+                        doc.set_selection(view_id, selection.push(Range::point(pos)));
+                    } else if editor.mode == Mode::Select {
+                        // Discards non-primary selections for consistent UX with normal mode
+                        let primary = doc.selection(view_id).primary().put_cursor(doc.text().slice(..), pos, true);
+                        doc.set_selection(view_id, Selection::single(primary.anchor, primary.head));
+                    } else {
+                        doc.set_selection(view_id, Selection::point(pos));
+                    }
+
+                    editor.focus(view_id);
+                    editor.ensure_cursor_in_view(view_id);
+
+                    return EventResult::Consumed(None);
+                }
+
+                // clicks on the gutter or statusline of a split only focus it
+                let view_id = editor.tree.views().find_map(|(view, _focus)| {
+                    let area = view.area;
+                    (column >= area.left() && column < area.right() && row >= area.top() && row < area.bottom())
+                        .then_some(view.id)
+                });
+                match view_id {
+                    Some(view_id) => {
+                        editor.focus(view_id);
+                        EventResult::Consumed(None)
+                    }
+                    None => EventResult::Ignored(None),
+                }
+            }
+
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let (view, doc) = current!(cxt.editor);
+
+                let pos = match view.pos_at_screen_coords(doc, row, column, true) {
+                    Some(pos) => pos,
+                    None => return EventResult::Ignored(None),
+                };
+
+                let mut selection = doc.selection(view.id).clone();
+                let primary = selection.primary_mut();
+                *primary = primary.put_cursor(doc.text().slice(..), pos, true);
+                doc.set_selection(view.id, selection);
+                let view_id = view.id;
+                cxt.editor.ensure_cursor_in_view(view_id);
+                EventResult::Consumed(None)
+            }
+
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let current_view = cxt.editor.tree.focus;
+
+                let direction = match kind {
+                    MouseEventKind::ScrollUp => Direction::Backward,
+                    _ => Direction::Forward,
+                };
+
+                // scroll the view under the pointer without changing the focus
+                match pos_and_view(cxt.editor, row, column, false) {
+                    Some((_, view_id)) => cxt.editor.tree.focus = view_id,
+                    None => return EventResult::Ignored(None),
+                }
+
+                let offset = config.scroll_lines.unsigned_abs();
+                commands::scroll(cxt, offset, direction);
+
+                cxt.editor.tree.focus = current_view;
+                cxt.editor.ensure_cursor_in_view(current_view);
+
+                EventResult::Consumed(None)
+            }
+
+            _ => EventResult::Ignored(None),
+        }
+    }
 }
 
 impl Component for EditorView {
     fn handle_event(&mut self, event: &Event, context: &mut compositor::Context) -> EventResult {
         let mut cx = commands::Context {
             editor: context.editor,
+            jobs: context.jobs,
             count: None,
+            callback: None,
         };
 
         match event {
@@ -199,7 +696,8 @@ impl Component for EditorView {
                 cx.editor.reset_idle_timer();
                 canonicalize_key(&mut key);
 
-                // TODO: clear editor status msg
+                // clear status
+                cx.editor.status_msg = None;
 
                 let mode = cx.editor.mode();
                 let (view, _) = current!(cx.editor);
@@ -207,10 +705,43 @@ impl Component for EditorView {
 
                 // TODO: handle on_next_key
                 match mode {
-                    Mode::Insert => unimplemented!("insert mode"),
+                    Mode::Insert => {
+                        // the completion menu handles its navigation keys
+                        let mut consumed = false;
+                        if let Some(completion) = &mut self.completion {
+                            let mut cx = compositor::Context {
+                                editor: cx.editor,
+                                jobs: cx.jobs,
+                                scroll: None,
+                            };
+                            if let EventResult::Consumed(callback) = completion.handle_event(event, &mut cx) {
+                                consumed = true;
+                                // the callback closes the menu, which isn't a layer
+                                if callback.is_some() {
+                                    self.completion = None;
+                                }
+                            }
+                        }
+
+                        if !consumed {
+                            self.insert_mode(&mut cx, key);
+
+                            // filter the completions with what was typed
+                            if let Some(completion) = &mut self.completion {
+                                completion.update(&mut cx);
+                                if completion.is_empty() {
+                                    self.completion = None;
+                                }
+                            }
+                        }
+                    }
                     mode => self.command_mode(mode, &mut cx, key),
                 }
 
+                if cx.editor.mode() != Mode::Insert {
+                    self.completion = None;
+                }
+
                 // if the focused view still exists and wasn't closed
                 if cx.editor.tree.contains(focus) {
                     let config = cx.editor.config();
@@ -220,27 +751,68 @@ impl Component for EditorView {
                     view.ensure_cursor_in_view(doc, config.scrolloff);
                 }
 
+                EventResult::Consumed(cx.callback)
+            }
+            Event::Mouse(event) => self.handle_mouse_event(event, &mut cx),
+            Event::Resize(_width, _height) => {
+                // Ignore this event, we handle resizing just before rendering to screen.
+                // Handling it here but not re-rendering will cause flashing
                 EventResult::Consumed(None)
             }
+            Event::IdleTimeout => self.handle_idle_timeout(&mut cx),
             event => todo!("{event:?}"),
         }
     }
 
+    fn cursor(&self, _area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        match editor.cursor() {
+            // all block cursors are drawn manually
+            (pos, CursorKind::Block) => (pos, CursorKind::Hidden),
+            cursor => cursor,
+        }
+    }
+
     fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
         surface.set_style(area, cx.editor.theme.get("ui.background"));
 
-        // TODO: buffer line
-        // -1 for command line
+        // check if bufferline should be rendered
+        let use_bufferline = match cx.editor.config().bufferline {
+            BufferLine::Always => true,
+            BufferLine::Multiple if cx.editor.documents.len() > 1 => true,
+            _ => false,
+        };
+
+        // -1 for command line and -1 for bufferline
         let mut editor_area = area.clip_bottom(1);
+        if use_bufferline {
+            editor_area = editor_area.clip_top(1);
+        }
 
-        // TODO: editor.resize
+        // if the terminal size suddenly changed, we need to trigger a resize
+        cx.editor.resize(editor_area);
+
+        if use_bufferline {
+            Self::render_bufferline(cx.editor, area.with_height(1), surface);
+        }
 
         for (view, is_focused) in cx.editor.tree.views() {
             let doc = cx.editor.document(view.doc).unwrap();
-            self.render_view(cx.editor, doc, view, area, surface, is_focused);
+            self.render_view(cx.editor, doc, view, editor_area, surface, is_focused);
+        }
+
+        if let Some((status_msg, severity)) = cx.editor.get_status() {
+            let style = match severity {
+                Severity::Error => cx.editor.theme.get("error"),
+                Severity::Warning => cx.editor.theme.get("warning"),
+                _ => cx.editor.theme.get("ui.text"),
+            };
+
+            surface.set_string(area.x, area.y + area.height.saturating_sub(1), status_msg, style);
         }
 
-        // TODO
+        if let Some(completion) = &mut self.completion {
+            completion.render(area, surface, cx);
+        }
     }
 }
 
@@ -253,3 +825,295 @@ fn canonicalize_key(key: &mut KeyEvent) {
         key.modifiers.remove(KeyModifiers::SHIFT)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arc_swap::ArcSwap;
+    use smallvec::smallvec;
+
+    use super::*;
+    use crate::{
+        core::{Range, Rope, Selection},
+        view::{editor::Config, theme::DEFAULT_THEME},
+    };
+
+    fn selection_spans(mode: Mode, cursor_shape: &str) -> Vec<(usize, std::ops::Range<usize>)> {
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("hello world\n"), None, config);
+        let view = View::new(doc.id);
+        // a forward secondary range and a reversed primary range
+        doc.set_selection(view.id, Selection::new(smallvec![Range::new(0, 5), Range::new(11, 6)], 1));
+
+        let cursor_shape: CursorShapeConfig = toml::from_str(cursor_shape).unwrap();
+        EditorView::doc_selection_highlights(mode, &doc, &view, &DEFAULT_THEME, &cursor_shape)
+    }
+
+    #[test]
+    fn selection_highlights() {
+        let theme = &*DEFAULT_THEME;
+        let selection = theme.find_scope_index_exact("ui.selection").unwrap();
+        let primary_selection = theme.find_scope_index_exact("ui.selection.primary").unwrap();
+        let cursor = theme.find_scope_index_exact("ui.cursor").unwrap();
+        let insert_cursor = theme.find_scope_index_exact("ui.cursor.insert").unwrap();
+
+        assert_eq!(
+            selection_spans(Mode::Normal, ""),
+            vec![(selection, 0..4), (cursor, 4..5), (cursor, 6..7), (primary_selection, 7..11)]
+        );
+
+        // the primary bar cursor is drawn by the terminal and the selection covers its cell
+        assert_eq!(
+            selection_spans(Mode::Insert, "insert = \"bar\""),
+            vec![(selection, 0..4), (insert_cursor, 4..5), (primary_selection, 6..11)]
+        );
+    }
+
+    #[test]
+    fn diagnostics_highlights() {
+        use crate::core::diagnostic::{self, Diagnostic};
+
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("hello world\n"), None, config);
+        let diagnostic = |start, end, severity| Diagnostic {
+            range: diagnostic::Range { start, end },
+            line: 0,
+            message: String::new(),
+            severity,
+        };
+        doc.set_diagnostics(vec![
+            diagnostic(0, 3, Some(Severity::Warning)),
+            diagnostic(2, 4, None),
+            diagnostic(3, 5, Some(Severity::Warning)),
+            diagnostic(6, 11, Some(Severity::Error)),
+            diagnostic(8, 9, None),
+        ]);
+
+        let theme = &*DEFAULT_THEME;
+        let warning = theme.find_scope_index_exact("diagnostic.warning").unwrap();
+        let error = theme.find_scope_index_exact("diagnostic.error").unwrap();
+
+        // overlapping diagnostics of the same severity are merged, the ones without a severity
+        // are warnings
+        let [hint_spans, info_spans, warning_spans, error_spans] = EditorView::doc_diagnostics_highlights(&doc, theme);
+        assert!(hint_spans.is_empty() && info_spans.is_empty());
+        assert_eq!(warning_spans, vec![(warning, 0..5), (warning, 8..9)]);
+        assert_eq!(error_spans, vec![(error, 6..11)]);
+    }
+
+    /// The background color of each cell of row `y`.
+    fn backgrounds(surface: &Surface, y: u16) -> Vec<Color> {
+        (0..surface.area.width).map(|x| surface[(x, y)].bg).collect()
+    }
+
+    #[tokio::test]
+    async fn rulers() {
+        use crate::core::syntax::LanguageConfiguration;
+
+        let config = Config {
+            rulers: vec![3, 12],
+            ..Config::default()
+        };
+        let editor = Editor::for_test(config, Rect::new(0, 0, 10, 3));
+        let mut doc = Document::from(Rope::from("hello world\n"), None, editor.config.clone());
+        let mut view = View::new(doc.id);
+
+        let theme = &*DEFAULT_THEME;
+        let ruler = theme.get("ui.virtual.ruler").bg.unwrap();
+        let area = Rect::new(0, 0, 10, 2);
+        let rulers_at = |doc: &Document, view: &View| {
+            let mut surface = Surface::empty(area);
+            EditorView::render_rulers(&editor, doc, view, area, &mut surface, theme);
+            assert_eq!(backgrounds(&surface, 0), backgrounds(&surface, 1));
+            let columns: Vec<_> = backgrounds(&surface, 0)
+                .into_iter()
+                .enumerate()
+                .filter_map(|(x, bg)| (bg == ruler).then_some(x))
+                .collect();
+            columns
+        };
+
+        // the ruler past the viewport isn't drawn
+        assert_eq!(rulers_at(&doc, &view), vec![2]);
+
+        view.offset.horizontal_offset = 5;
+        assert_eq!(rulers_at(&doc, &view), vec![6]);
+
+        view.offset.horizontal_offset = 0;
+        doc.language = Some(Arc::new(LanguageConfiguration {
+            language_id: String::from("text"),
+            file_types: Vec::new(),
+            roots: Vec::new(),
+            rulers: Some(vec![1, 5]),
+            language_server: None,
+            config: None,
+        }));
+        assert_eq!(rulers_at(&doc, &view), vec![0, 4]);
+    }
+
+    #[tokio::test]
+    async fn bufferline() {
+        use crate::{
+            core::Transaction,
+            view::{editor::Action, theme},
+        };
+
+        let mut editor = Editor::for_test(Config::default(), Rect::new(0, 0, 24, 4));
+        for (name, action) in [
+            ("one.txt", Action::VerticalSplit),
+            ("two.txt", Action::Load),
+            ("three.txt", Action::Load),
+        ] {
+            let mut doc = Document::from(Rope::from("\n"), None, editor.config.clone());
+            doc.set_path(Some(std::path::Path::new(name))).unwrap();
+            editor.new_file_from_document(action, doc);
+        }
+        let [one, two, three]: [_; 3] = editor.documents().map(|doc| doc.id).collect::<Vec<_>>().try_into().unwrap();
+        editor.switch(three, Action::Replace);
+
+        let theme = &*theme::DEFAULT_THEME;
+        let active = theme.get("ui.bufferline.active").bg.unwrap();
+        let inactive = theme.get("ui.bufferline").bg.unwrap();
+        let render = |editor: &Editor| {
+            let area = Rect::new(0, 0, 24, 1);
+            let mut surface = Surface::empty(area);
+            EditorView::render_bufferline(editor, area, &mut surface);
+            let symbols: String = (0..area.width).map(|x| surface[(x, 0)].symbol.as_str()).collect();
+            (symbols, backgrounds(&surface, 0))
+        };
+
+        // the entries on the left are skipped until the current document fits
+        let (symbols, bgs) = render(&editor);
+        assert_eq!(symbols, " two.txt  three.txt     ");
+        assert_eq!(bgs[..9], [inactive; 9]);
+        assert_eq!(bgs[9..20], [active; 11]);
+
+        // modified documents are marked, the entries past the viewport are cut off
+        editor.switch(one, Action::Replace);
+        let view_id = editor.tree.focus;
+        let doc = editor.documents.get_mut(&two).unwrap();
+        let transaction = Transaction::change(doc.text(), std::iter::once((0, 0, Some("x".into()))));
+        doc.apply(&transaction, view_id);
+        let (symbols, bgs) = render(&editor);
+        assert_eq!(symbols, " one.txt  two.txt[+]  th");
+        assert_eq!(bgs[..9], [active; 9]);
+        assert_eq!(bgs[9..], [inactive; 15]);
+    }
+
+    #[test]
+    fn cursorline() {
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("a\nb\nc\n"), None, config);
+        let mut view = View::new(doc.id);
+        view.area = Rect::new(0, 0, 4, 3);
+        doc.set_selection(view.id, Selection::new(smallvec![Range::point(0), Range::point(4)], 1));
+
+        let theme = &*DEFAULT_THEME;
+        let primary = theme.get("ui.cursorline.primary").bg.unwrap();
+        let secondary = theme.get("ui.cursorline.secondary").bg.unwrap();
+        let mut surface = Surface::empty(view.area);
+        let mut renderer = TextRenderer::new(&mut surface, &doc, theme, 0, view.area);
+        let mut decoration = EditorView::cursorline(&doc, &view, theme);
+        for line in 0..3 {
+            let pos = LinePos {
+                first_visual_line: true,
+                doc_line: line,
+                visual_line: line as u16,
+                start_char_idx: doc.text().line_to_char(line),
+            };
+            decoration.render_background(&mut renderer, pos);
+        }
+
+        assert_eq!(backgrounds(&surface, 0), vec![secondary; 4]);
+        assert_eq!(backgrounds(&surface, 1), vec![Color::Reset; 4]);
+        assert_eq!(backgrounds(&surface, 2), vec![primary; 4]);
+    }
+
+    #[test]
+    fn cursorcolumn() {
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("abc\nabc\n"), None, config);
+        let mut view = View::new(doc.id);
+        view.area = Rect::new(0, 0, 4, 2);
+        doc.set_selection(view.id, Selection::new(smallvec![Range::point(1), Range::point(6)], 0));
+
+        // without cursorcolumn scopes in the theme the cursorline ones are used
+        let theme = &*DEFAULT_THEME;
+        let primary = theme.get("ui.cursorline.primary").bg.unwrap();
+        let secondary = theme.get("ui.cursorline.secondary").bg.unwrap();
+        let mut surface = Surface::empty(view.area);
+        EditorView::highlight_cursorcolumn(&doc, &view, &mut surface, theme, view.area, &TextAnnotations::default());
+        for y in 0..2 {
+            assert_eq!(backgrounds(&surface, y), vec![Color::Reset, primary, secondary, Color::Reset]);
+        }
+
+        // columns scrolled out of view aren't highlighted
+        view.offset.horizontal_offset = 2;
+        let mut surface = Surface::empty(view.area);
+        EditorView::highlight_cursorcolumn(&doc, &view, &mut surface, theme, view.area, &TextAnnotations::default());
+        assert_eq!(
+            backgrounds(&surface, 0),
+            vec![secondary, Color::Reset, Color::Reset, Color::Reset]
+        );
+    }
+
+    fn soft_wrapped_doc(text: &str, selection: Selection) -> (Document, View) {
+        let config = Config {
+            soft_wrap: view::editor::SoftWrap {
+                enable: Some(true),
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let mut doc = Document::from(Rope::from(text), None, Arc::new(ArcSwap::from_pointee(config)));
+        let mut view = View::new(doc.id);
+        view.area = Rect::new(0, 0, 20, 3);
+        doc.set_selection(view.id, selection);
+        (doc, view)
+    }
+
+    #[test]
+    fn cursorline_soft_wrap() {
+        // the first line is wrapped before "five"
+        let (doc, view) = soft_wrapped_doc("one two three four five\nsix\n", Selection::point(0));
+
+        let theme = &*DEFAULT_THEME;
+        let primary = theme.get("ui.cursorline.primary").bg.unwrap();
+        let mut surface = Surface::empty(view.area);
+        let mut decorations = vec![EditorView::cursorline(&doc, &view, theme)];
+        render_document(
+            &mut surface,
+            view.area,
+            &doc,
+            view.offset,
+            &TextAnnotations::default(),
+            std::iter::empty(),
+            theme,
+            &mut decorations,
+            None,
+        );
+
+        // every visual line of the cursor line is highlighted
+        assert_eq!(backgrounds(&surface, 0), vec![primary; 20]);
+        assert_eq!(backgrounds(&surface, 1), vec![primary; 20]);
+        assert_eq!(backgrounds(&surface, 2), vec![Color::Reset; 20]);
+    }
+
+    #[test]
+    fn cursorcolumn_soft_wrap() {
+        // the cursor is on the "i" of "five", which is wrapped behind the "↪ " indicator
+        let (doc, view) = soft_wrapped_doc("one two three four five\nsix\n", Selection::point(20));
+
+        let theme = &*DEFAULT_THEME;
+        let primary = theme.get("ui.cursorline.primary").bg.unwrap();
+        let mut surface = Surface::empty(view.area);
+        EditorView::highlight_cursorcolumn(&doc, &view, &mut surface, theme, view.area, &TextAnnotations::default());
+
+        let mut expected = vec![Color::Reset; 20];
+        expected[3] = primary;
+        for y in 0..3 {
+            assert_eq!(backgrounds(&surface, y), expected);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Fuzzy matching of a query against candidate strings, used to filter menus and pickers.
+//!
+//! Every character of the query has to appear in the candidate in order. Among the possible
+//! alignments the one with the highest score is chosen, where matches at the start of words and
+//! consecutive matches are rewarded and gaps between matches are penalized.
+
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP: i64 = 1;
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Bonus for matching the first character of the candidate.
+const BONUS_FIRST_CHAR: i64 = 12;
+/// Bonus for matching a character following a separator like `/`, `_` or a space.
+const BONUS_BOUNDARY: i64 = 10;
+/// Bonus for matching an uppercase character following a lowercase one.
+const BONUS_CAMEL_CASE: i64 = 8;
+
+/// A query made of whitespace separated terms that all have to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzyQuery {
+    terms: Vec<Vec<char>>,
+    case_sensitive: bool,
+}
+
+impl FuzzyQuery {
+    /// Parses `query`. Matching ignores case unless the query contains an uppercase character.
+    pub fn new(query: &str) -> Self {
+        Self {
+            terms: query.split_whitespace().map(|term| term.chars().collect()).collect(),
+            case_sensitive: query.chars().any(char::is_uppercase),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Scores `text` against the query, or returns `None` if it doesn't match.
+    pub fn fuzzy_match(&self, text: &str) -> Option<i64> {
+        self.fuzzy_indices(text).map(|(score, _)| score)
+    }
+
+    /// Like [`FuzzyQuery::fuzzy_match`] but also returns the sorted char indices of the matched
+    /// characters in `text`, used to highlight them.
+    pub fn fuzzy_indices(&self, text: &str) -> Option<(i64, Vec<usize>)> {
+        let text: Vec<char> = text.chars().collect();
+        let mut score = 0;
+        let mut indices = Vec::new();
+        for term in &self.terms {
+            let (term_score, term_indices) = match_term(term, &text, self.case_sensitive)?;
+            score += term_score;
+            indices.extend(term_indices);
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        Some((score, indices))
+    }
+}
+
+fn chars_eq(a: char, b: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        a == b || a.to_lowercase().eq(b.to_lowercase())
+    }
+}
+
+/// The bonus for a match at `idx` based on the characters around it.
+fn position_bonus(text: &[char], idx: usize) -> i64 {
+    let Some(prev) = idx.checked_sub(1).map(|i| text[i]) else {
+        return BONUS_FIRST_CHAR;
+    };
+    let current = text[idx];
+    if matches!(prev, '/' | '\\' | '_' | '-' | '.' | ' ' | ':') {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && current.is_uppercase() {
+        BONUS_CAMEL_CASE
+    } else {
+        0
+    }
+}
+
+/// Finds the best scoring alignment of `term` in `text` using dynamic programming over the
+/// position of each term character.
+fn match_term(term: &[char], text: &[char], case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    if term.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    if term.len() > text.len() {
+        return None;
+    }
+
+    let width = text.len();
+    // scores[i * width + j]: best score with term[i] matched at text[j]
+    let mut scores = vec![None; term.len() * width];
+    // the position of term[i - 1] in the best alignment ending with term[i] at text[j]
+    let mut parents = vec![0; term.len() * width];
+
+    for (i, &c) in term.iter().enumerate() {
+        // best `score + gap penalty` of the previous row up to `j - 2`, with its position
+        let mut best_gap: Option<(i64, usize)> = None;
+        for j in i..width {
+            if i > 0 && j >= 2 {
+                if let Some(prev) = scores[(i - 1) * width + j - 2] {
+                    let candidate = prev + PENALTY_GAP * (j - 2) as i64;
+                    if best_gap.is_none_or(|(best, _)| candidate > best) {
+                        best_gap = Some((candidate, j - 2));
+                    }
+                }
+            }
+
+            if !chars_eq(text[j], c, case_sensitive) {
+                continue;
+            }
+
+            let bonus = SCORE_MATCH + position_bonus(text, j);
+            let cell = i * width + j;
+            if i == 0 {
+                // leading characters that are skipped are not penalized
+                scores[cell] = Some(bonus);
+                continue;
+            }
+
+            let consecutive = scores[(i - 1) * width + j - 1].map(|prev| (prev + BONUS_CONSECUTIVE, j - 1));
+            let gap = best_gap.map(|(best, k)| (best - PENALTY_GAP * (j - 1) as i64, k));
+            let best = match (consecutive, gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, parent)) = best {
+                scores[cell] = Some(score + bonus);
+                parents[cell] = parent;
+            }
+        }
+    }
+
+    let last = term.len() - 1;
+    let (mut j, score) = (0..width)
+        .filter_map(|j| scores[last * width + j].map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+
+    let mut indices = vec![0; term.len()];
+    for i in (0..term.len()).rev() {
+        indices[i] = j;
+        j = parents[i * width + j];
+    }
+    Some((score, indices))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matching() {
+        let query = FuzzyQuery::new("fb");
+        assert_eq!(query.fuzzy_indices("foo/bar").map(|(_, i)| i), Some(vec![0, 4]));
+        assert!(query.fuzzy_match("bf").is_none());
+
+        // word starts and consecutive matches score higher
+        let query = FuzzyQuery::new("ed");
+        let boundary = query.fuzzy_match("ui/editor.rs").unwrap();
+        let inside = query.fuzzy_match("tui/used.rs").unwrap();
+        assert!(boundary > inside);
+        assert_eq!(query.fuzzy_indices("seed/edit").map(|(_, i)| i), Some(vec![5, 6]));
+
+        // smart case
+        assert!(FuzzyQuery::new("ED").fuzzy_match("editor").is_none());
+        assert!(FuzzyQuery::new("ed").fuzzy_match("EDITOR").is_some());
+
+        // all terms have to match
+        let query = FuzzyQuery::new("view rs");
+        assert_eq!(
+            query.fuzzy_indices("src/view.rs").map(|(_, i)| i),
+            Some(vec![4, 5, 6, 7, 9, 10])
+        );
+        assert!(query.fuzzy_match("src/view.toml").is_none());
+    }
+}
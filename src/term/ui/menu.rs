@@ -0,0 +1,334 @@
+use std::{borrow::Cow, cmp::Reverse};
+
+use crate::{
+    core::unicode::width::UnicodeWidthStr,
+    term::{
+        compositor::{Callback, Component, Compositor, Context, EventResult},
+        ui::fuzzy_match::FuzzyQuery,
+    },
+    tui::buffer::Buffer as Surface,
+    view::{
+        editor::Editor,
+        graphics::Rect,
+        input::{Event, KeyEvent},
+        keyboard::{KeyCode, KeyModifiers},
+    },
+};
+
+pub use super::PromptEvent as MenuEvent;
+
+/// Maximum number of options shown at once.
+const MAX_HEIGHT: usize = 10;
+
+/// An option of a [`Menu`].
+pub trait Item {
+    /// Additional editor state that is used for label calculation.
+    type Data;
+
+    fn format(&self, data: &Self::Data) -> Cow<'_, str>;
+
+    /// The text the menu query is matched against, the label by default.
+    fn filter_text(&self, data: &Self::Data) -> Cow<'_, str> {
+        self.format(data)
+    }
+}
+
+impl Item for String {
+    type Data = ();
+
+    fn format(&self, _data: &Self::Data) -> Cow<'_, str> {
+        self.into()
+    }
+}
+
+pub type MenuCallback<T> = Box<dyn Fn(&mut Editor, Option<&T>, MenuEvent)>;
+
+/// A list of options filtered with a fuzzy query, navigated with the arrow keys or `Tab`.
+/// The callback is notified when the selection changes, is accepted or the menu is closed.
+pub struct Menu<T: Item> {
+    options: Vec<T>,
+    editor_data: T::Data,
+
+    /// Index into `matches` of the selected option, if any.
+    cursor: Option<usize>,
+
+    /// Indices into `options` of the options matching the query, with their score.
+    matches: Vec<(usize, i64)>,
+
+    callback_fn: MenuCallback<T>,
+
+    scroll: usize,
+    size: (u16, u16),
+}
+
+impl<T: Item> Menu<T> {
+    pub fn new(
+        options: Vec<T>,
+        editor_data: T::Data,
+        callback_fn: impl Fn(&mut Editor, Option<&T>, MenuEvent) + 'static,
+    ) -> Self {
+        let matches = (0..options.len()).map(|i| (i, 0)).collect();
+        Self {
+            options,
+            editor_data,
+            matches,
+            cursor: None,
+            callback_fn: Box::new(callback_fn),
+            scroll: 0,
+            size: (0, 0),
+        }
+    }
+
+    /// Filters and sorts the options by how well they match `pattern`.
+    pub fn score(&mut self, pattern: &str) {
+        let query = FuzzyQuery::new(pattern);
+        self.matches.clear();
+        self.matches
+            .extend(self.options.iter().enumerate().filter_map(|(index, option)| {
+                let text = option.filter_text(&self.editor_data);
+                query.fuzzy_match(&text).map(|score| (index, score))
+            }));
+        // the sort is stable, options with the same score keep their order
+        self.matches.sort_by_key(|&(_, score)| Reverse(score));
+
+        // reset cursor position
+        self.cursor = None;
+        self.scroll = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.matches.clear();
+
+        // reset cursor position
+        self.cursor = None;
+        self.scroll = 0;
+    }
+
+    pub fn move_up(&mut self) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        let max_index = len - 1;
+        let pos = self.cursor.map_or(max_index, |i| (i + max_index) % len);
+        self.cursor = Some(pos);
+        self.adjust_scroll();
+    }
+
+    pub fn move_down(&mut self) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        let pos = self.cursor.map_or(0, |i| (i + 1) % len);
+        self.cursor = Some(pos);
+        self.adjust_scroll();
+    }
+
+    /// Scrolls so that the selected option is visible.
+    fn adjust_scroll(&mut self) {
+        let win_height = self.size.1 as usize;
+        if let Some(cursor) = self.cursor {
+            if cursor + 1 > win_height + self.scroll {
+                self.scroll = cursor + 1 - win_height;
+            } else if cursor < self.scroll {
+                self.scroll = cursor;
+            }
+        }
+    }
+
+    pub fn selection(&self) -> Option<&T> {
+        self.cursor
+            .and_then(|cursor| self.matches.get(cursor))
+            .map(|&(index, _)| &self.options[index])
+    }
+
+    pub fn selection_mut(&mut self) -> Option<&mut T> {
+        self.cursor
+            .and_then(|cursor| self.matches.get(cursor))
+            .map(|&(index, _)| &mut self.options[index])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+}
+
+impl<T: Item + PartialEq> Menu<T> {
+    /// Replaces the first option equal to `old_option`, e.g. once more of it is known.
+    pub fn replace_option(&mut self, old_option: &T, new_option: T) {
+        if let Some(option) = self.options.iter_mut().find(|option| *option == old_option) {
+            *option = new_option;
+        }
+    }
+}
+
+impl<T: Item + 'static> Component for Menu<T> {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let event = match event {
+            Event::Key(event) => *event,
+            _ => return EventResult::Ignored(None),
+        };
+
+        let close_fn: Option<Callback> = Some(Box::new(|compositor: &mut Compositor, _| {
+            // remove the layer
+            compositor.pop();
+        }));
+
+        let ctrl = |c| KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        };
+
+        match event {
+            KeyEvent { code: KeyCode::Esc, .. } => {
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Abort);
+                return EventResult::Consumed(close_fn);
+            }
+            k if k == ctrl('c') => {
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Abort);
+                return EventResult::Consumed(close_fn);
+            }
+            // arrow up/ctrl-p/shift-tab prev completion choice (including updating the doc)
+            KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::SHIFT,
+            }
+            | KeyEvent { code: KeyCode::Up, .. } => {
+                self.move_up();
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Update);
+                return EventResult::Consumed(None);
+            }
+            k if k == ctrl('p') => {
+                self.move_up();
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Update);
+                return EventResult::Consumed(None);
+            }
+            // arrow down/ctrl-n/tab advances completion choice (including updating the doc)
+            KeyEvent { code: KeyCode::Tab, .. } | KeyEvent { code: KeyCode::Down, .. } => {
+                self.move_down();
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Update);
+                return EventResult::Consumed(None);
+            }
+            k if k == ctrl('n') => {
+                self.move_down();
+                (self.callback_fn)(cx.editor, self.selection(), MenuEvent::Update);
+                return EventResult::Consumed(None);
+            }
+            KeyEvent {
+                code: KeyCode::Enter, ..
+            } => {
+                if let Some(selection) = self.selection() {
+                    (self.callback_fn)(cx.editor, Some(selection), MenuEvent::Validate);
+                    return EventResult::Consumed(close_fn);
+                } else {
+                    return EventResult::Ignored(close_fn);
+                }
+            }
+            // KeyEvent {
+            //     code: KeyCode::Char(c),
+            //     modifiers: KeyModifiers::NONE,
+            // } => {
+            //     self.insert_char(c);
+            //     (self.callback_fn)(cx.editor, &self.line, MenuEvent::Update);
+            // }
+
+            // / -> edit_filter?
+            //
+            // enter confirms the match and closes the menu
+            // typing filters the menu
+            // if we run out of options the menu closes itself
+            _ => (),
+        }
+
+        // for some events, we want to process them but send ignore, specifically all input except
+        // tab/enter/ctrl-k or whatever will confirm the selection/ ctrl-n/ctrl-p for scroll.
+        EventResult::Ignored(None)
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        let label_width = self
+            .matches
+            .iter()
+            .map(|&(index, _)| self.options[index].format(&self.editor_data).width())
+            .max()
+            .unwrap_or(0);
+        // one column of padding on each side
+        let width = (label_width + 2).min(viewport.0 as usize) as u16;
+        let height = self.matches.len().min(MAX_HEIGHT).min(viewport.1 as usize) as u16;
+
+        self.size = (width, height);
+        self.adjust_scroll();
+
+        Some(self.size)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        let theme = &cx.editor.theme;
+        let style = theme.try_get("ui.menu").unwrap_or_else(|| theme.get("ui.text"));
+        let selected = theme.get("ui.menu.selected");
+        surface.clear_with(area, style);
+
+        let win_height = area.height as usize;
+        for (i, &(index, _)) in self.matches.iter().skip(self.scroll).take(win_height).enumerate() {
+            let y = area.y + i as u16;
+            let style = if self.cursor == Some(self.scroll + i) {
+                surface.set_style(area.clip_top(i as u16).with_height(1), selected);
+                style.patch(selected)
+            } else {
+                style
+            };
+            let label = self.options[index].format(&self.editor_data);
+            surface.set_stringn(area.x + 1, y, label, area.width.saturating_sub(2) as usize, style);
+        }
+
+        // render a scrollbar on the right edge if there are more options than rows
+        let len = self.matches.len();
+        if len > win_height && area.width > 0 {
+            let scroll_style = theme.get("ui.menu.scroll");
+            let scroll_height = (win_height * win_height / len).max(1);
+            let scroll_line = (win_height - scroll_height) * self.scroll / (len - win_height);
+            for y in scroll_line..(scroll_line + scroll_height).min(win_height) {
+                surface[(area.right() - 1, area.y + y as u16)]
+                    .set_symbol("▐")
+                    .set_style(scroll_style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn menu_navigation() {
+        let options = ["src/menu.rs", "src/popup.rs", "src/mod.rs"].map(String::from).to_vec();
+        let mut menu = Menu::new(options, (), |_, _, _| {});
+        menu.required_size((80, 2));
+        assert_eq!(menu.len(), 3);
+        assert_eq!(menu.selection(), None);
+
+        menu.move_up();
+        assert_eq!(menu.selection().map(String::as_str), Some("src/mod.rs"));
+        assert_eq!(menu.scroll, 1);
+        menu.move_down();
+        assert_eq!(menu.selection().map(String::as_str), Some("src/menu.rs"));
+        assert_eq!(menu.scroll, 0);
+
+        menu.score("mrs");
+        assert_eq!(menu.len(), 2);
+        assert_eq!(menu.selection(), None);
+        menu.move_down();
+        assert_eq!(menu.selection().map(String::as_str), Some("src/mod.rs"));
+
+        menu.score("xyz");
+        assert!(menu.is_empty());
+        menu.move_down();
+        assert_eq!(menu.selection(), None);
+    }
+}
@@ -0,0 +1,600 @@
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, TryRecvError},
+        Arc,
+    },
+};
+
+use futures_util::future::BoxFuture;
+use tokio::sync::Notify;
+
+use crate::{
+    core::{text_annotations::TextAnnotations, unicode::width::UnicodeWidthChar, Position},
+    term::{
+        compositor::{Component, Compositor, Context, EventResult},
+        job,
+        ui::{document::render_document, editor::EditorView, fuzzy_match::FuzzyQuery, Overlay, Prompt, PromptEvent},
+    },
+    tui::buffer::Buffer as Surface,
+    view::{
+        document::Document,
+        editor::{Action, Editor},
+        graphics::{CursorKind, Rect},
+        input::{Event, KeyEvent},
+        keyboard::{KeyCode, KeyModifiers},
+        view::ViewPosition,
+    },
+};
+
+/// The picker is split to show a preview of the selected file if it is wider than this.
+pub const MIN_AREA_WIDTH_FOR_PREVIEW: u16 = 72;
+/// Biggest file size to preview in bytes
+pub const MAX_FILE_SIZE_FOR_PREVIEW: u64 = 10 * 1024 * 1024;
+
+pub type PickerCallback<T> = Box<dyn Fn(&mut Context, &T, Action)>;
+pub type FormatFn<T> = Box<dyn Fn(&T) -> Cow<str>>;
+
+/// A file to preview, with the position to center the preview on if it is known.
+pub type FileLocation = (PathBuf, Option<Position>);
+pub type FileCallback<T> = Box<dyn Fn(&Editor, &T) -> Option<FileLocation>>;
+pub type DynQueryCallback<T> = Box<dyn Fn(String, &mut Editor) -> BoxFuture<'static, anyhow::Result<Vec<T>>>>;
+
+/// A file loaded for the preview, or the reason it can't be shown.
+pub enum CachedPreview {
+    Document(Box<Document>),
+    Binary,
+    LargeFile,
+    NotFound,
+}
+
+impl CachedPreview {
+    fn load(path: &Path, editor: &Editor) -> Self {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return CachedPreview::NotFound;
+        };
+        if metadata.len() > MAX_FILE_SIZE_FOR_PREVIEW {
+            return CachedPreview::LargeFile;
+        }
+
+        // treat files with a null byte in their first kilobyte as binary
+        let mut prefix = [0; 1024];
+        let is_binary = File::open(path)
+            .and_then(|mut file| file.read(&mut prefix))
+            .is_ok_and(|len| prefix[..len].contains(&0));
+        if is_binary {
+            return CachedPreview::Binary;
+        }
+
+        Document::open(path, None, None, editor.config.clone())
+            .map(|doc| CachedPreview::Document(Box::new(doc)))
+            .unwrap_or(CachedPreview::NotFound)
+    }
+
+    /// The text shown instead of the preview if it can't be rendered.
+    fn placeholder(&self) -> &str {
+        match *self {
+            CachedPreview::Document(_) => "<File preview>",
+            CachedPreview::Binary => "<Binary file>",
+            CachedPreview::LargeFile => "<File too large to preview>",
+            CachedPreview::NotFound => "<File not found>",
+        }
+    }
+}
+
+/// A document to preview, either one that is open in the editor or one loaded by the picker.
+enum Preview<'picker, 'editor> {
+    EditorDocument(&'editor Document),
+    Cached(&'picker CachedPreview),
+}
+
+impl Preview<'_, '_> {
+    fn document(&self) -> Option<&Document> {
+        match self {
+            Preview::EditorDocument(doc) => Some(doc),
+            Preview::Cached(CachedPreview::Document(doc)) => Some(doc),
+            Preview::Cached(_) => None,
+        }
+    }
+
+    fn placeholder(&self) -> &str {
+        match self {
+            Preview::EditorDocument(_) => "<File preview>",
+            Preview::Cached(preview) => preview.placeholder(),
+        }
+    }
+}
+
+/// Sends options to a [`Picker`] created with [`Picker::stream`], e.g. from a background thread.
+pub struct Injector<T> {
+    sender: mpsc::Sender<T>,
+    redraw_handle: Arc<Notify>,
+}
+
+impl<T> Clone for Injector<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            redraw_handle: self.redraw_handle.clone(),
+        }
+    }
+}
+
+impl<T> Injector<T> {
+    /// Adds an option to the picker. Returns false once the picker has been closed.
+    pub fn push(&self, option: T) -> bool {
+        self.sender.send(option).is_ok()
+    }
+
+    /// Requests a redraw so that the options pushed so far are shown.
+    pub fn redraw(&self) {
+        self.redraw_handle.notify_one();
+    }
+}
+
+/// A list of options filtered by the fuzzy query typed into its prompt, with the matched
+/// characters highlighted. It fills the area it is rendered in, see
+/// [`overlaid`](super::overlay::overlaid) to center it on the screen.
+///
+/// If a [`FileCallback`] is set with [`Picker::with_preview`], the file of the selected option
+/// is previewed on the right hand side.
+pub struct Picker<T> {
+    options: Vec<T>,
+    format_fn: FormatFn<T>,
+    callback_fn: PickerCallback<T>,
+    file_fn: Option<FileCallback<T>>,
+    /// Files loaded for the preview that aren't open in the editor.
+    preview_cache: HashMap<PathBuf, CachedPreview>,
+    /// Receives the options of a streaming picker until the sender is dropped.
+    receiver: Option<mpsc::Receiver<T>>,
+
+    /// Indices into `options` of the options matching the current query, with their score.
+    matches: Vec<(usize, i64)>,
+    /// Index into `matches` of the selected option.
+    cursor: usize,
+
+    prompt: Prompt,
+    previous_pattern: String,
+    query: FuzzyQuery,
+}
+
+impl<T: 'static> Picker<T> {
+    pub fn new(
+        options: Vec<T>,
+        format_fn: impl Fn(&T) -> Cow<str> + 'static,
+        callback_fn: impl Fn(&mut Context, &T, Action) + 'static,
+    ) -> Self {
+        let prompt = Prompt::new("".into(), |_cx: &mut Context, _input: &str, _event: PromptEvent| {});
+
+        let mut picker = Self {
+            options,
+            format_fn: Box::new(format_fn),
+            callback_fn: Box::new(callback_fn),
+            file_fn: None,
+            preview_cache: HashMap::new(),
+            receiver: None,
+            matches: Vec::new(),
+            cursor: 0,
+            prompt,
+            previous_pattern: String::new(),
+            query: FuzzyQuery::default(),
+        };
+        picker.score();
+        picker
+    }
+
+    /// Creates an empty picker whose options are pushed with the returned [`Injector`]. The
+    /// options are picked up whenever the picker handles an event or is rendered.
+    pub fn stream(
+        format_fn: impl Fn(&T) -> Cow<str> + 'static,
+        callback_fn: impl Fn(&mut Context, &T, Action) + 'static,
+        redraw_handle: Arc<Notify>,
+    ) -> (Self, Injector<T>) {
+        let (sender, receiver) = mpsc::channel();
+        let mut picker = Self::new(Vec::new(), format_fn, callback_fn);
+        picker.receiver = Some(receiver);
+        (picker, Injector { sender, redraw_handle })
+    }
+
+    /// Shows a preview of the file returned by `file_fn` for the selected option.
+    pub fn with_preview(mut self, file_fn: impl Fn(&Editor, &T) -> Option<FileLocation> + 'static) -> Self {
+        self.file_fn = Some(Box::new(file_fn));
+        self
+    }
+
+    /// Replaces the options, e.g. with the ones fetched for a new query.
+    pub fn set_options(&mut self, options: Vec<T>) {
+        self.options = options;
+        // the new options aren't a subset of the matches
+        self.previous_pattern.clear();
+        self.score();
+    }
+
+    /// Recomputes the matching options for the current query.
+    fn score(&mut self) {
+        let pattern = self.prompt.line();
+        let query = FuzzyQuery::new(pattern);
+
+        // a query that extends the previous one only matches a subset of its matches
+        let candidates: Vec<usize> = if !self.previous_pattern.is_empty() && pattern.starts_with(&self.previous_pattern) {
+            self.matches.iter().map(|&(index, _)| index).collect()
+        } else {
+            (0..self.options.len()).collect()
+        };
+
+        self.matches = candidates
+            .into_iter()
+            .filter_map(|index| {
+                let text = (self.format_fn)(&self.options[index]);
+                query.fuzzy_match(&text).map(|score| (index, score))
+            })
+            .collect();
+        // the sort is stable, options with the same score keep their order
+        self.matches.sort_by_key(|&(_, score)| Reverse(score));
+
+        self.cursor = 0;
+        self.previous_pattern = pattern.to_owned();
+        self.query = query;
+    }
+
+    /// Adds the options received from the [`Injector`] since the last call.
+    fn receive(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        let start = self.options.len();
+        loop {
+            match receiver.try_recv() {
+                Ok(option) => self.options.push(option),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    break;
+                }
+            }
+        }
+
+        if self.options.len() == start {
+            return;
+        }
+        for index in start..self.options.len() {
+            let text = (self.format_fn)(&self.options[index]);
+            if let Some(score) = self.query.fuzzy_match(&text) {
+                self.matches.push((index, score));
+            }
+        }
+        if !self.query.is_empty() {
+            self.matches.sort_by_key(|&(_, score)| Reverse(score));
+        }
+    }
+
+    /// Moves the cursor by `amount`, wrapping around the list of matches.
+    fn move_by(&mut self, amount: isize) {
+        let len = self.matches.len() as isize;
+        if len == 0 {
+            return;
+        }
+        self.cursor = (self.cursor as isize + amount).rem_euclid(len) as usize;
+    }
+
+    pub fn selection(&self) -> Option<&T> {
+        self.matches.get(self.cursor).map(|&(index, _)| &self.options[index])
+    }
+
+    /// The area of the list of options, the left half of `area` if the preview is shown.
+    fn picker_area(&self, area: Rect) -> Rect {
+        if self.file_fn.is_some() && area.width > MIN_AREA_WIDTH_FOR_PREVIEW {
+            area.with_width(area.width / 2)
+        } else {
+            area
+        }
+    }
+
+    /// The area of the input bar at the top of the picker, leaving room for the match count.
+    fn prompt_area(&self, inner: Rect) -> Rect {
+        let count_width = format!("{}/{}", self.matches.len(), self.options.len()).len() as u16;
+        Rect::new(inner.x, inner.y, inner.width.saturating_sub(count_width + 1), 1)
+    }
+
+    fn close() -> EventResult {
+        EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+            compositor.pop();
+        })))
+    }
+
+    fn get_preview<'picker, 'editor>(&'picker mut self, path: &Path, editor: &'editor Editor) -> Preview<'picker, 'editor> {
+        if let Some(doc) = editor.document_by_path(path) {
+            return Preview::EditorDocument(doc);
+        }
+        let preview = self
+            .preview_cache
+            .entry(path.to_path_buf())
+            .or_insert_with(|| CachedPreview::load(path, editor));
+        Preview::Cached(preview)
+    }
+
+    fn render_picker(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        let theme = &cx.editor.theme;
+        let text_style = theme.get("ui.text");
+        let selected_style = theme.get("ui.menu.selected");
+        let highlight_style = theme.get("special");
+
+        // -- Render the input bar
+        let count = format!("{}/{}", self.matches.len(), self.options.len());
+        self.prompt.render(self.prompt_area(area), surface, cx);
+        surface.set_stringn(
+            area.right().saturating_sub(count.len() as u16 + 1),
+            area.y,
+            &count,
+            count.len(),
+            text_style,
+        );
+
+        // -- Render the contents
+        let rows = area.height as usize - 1;
+        let offset = self.cursor - (self.cursor % rows.max(1));
+        let max_width = area.width.saturating_sub(2) as usize;
+
+        for (i, &(index, _)) in self.matches.iter().skip(offset).take(rows).enumerate() {
+            let y = area.y + 1 + i as u16;
+            let style = if offset + i == self.cursor {
+                surface.set_style(Rect::new(area.x, y, area.width, 1), selected_style);
+                selected_style
+            } else {
+                text_style
+            };
+            let label = (self.format_fn)(&self.options[index]);
+            surface.set_stringn(area.x + 1, y, &label, max_width, style);
+
+            // highlight the characters matched by the query
+            let Some((_, indices)) = self.query.fuzzy_indices(&label) else {
+                continue;
+            };
+            let mut indices = indices.into_iter().peekable();
+            let mut col = 0;
+            for (char_idx, ch) in label.chars().enumerate() {
+                let width = ch.width().unwrap_or(0);
+                if col + width > max_width || indices.peek().is_none() {
+                    break;
+                }
+                if indices.next_if_eq(&char_idx).is_some() {
+                    let x = area.x + 1 + col as u16;
+                    surface.set_style(Rect::new(x, y, width.max(1) as u16, 1), highlight_style);
+                }
+                col += width;
+            }
+        }
+    }
+
+    fn render_preview(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        let theme = &cx.editor.theme;
+        let border_style = theme.get("ui.window");
+        for y in area.top()..area.bottom() {
+            surface[(area.x, y)].set_symbol("│").set_style(border_style);
+        }
+        let inner = area.clip_left(2);
+
+        let location = self
+            .selection()
+            .zip(self.file_fn.as_ref())
+            .and_then(|(option, file_fn)| file_fn(cx.editor, option));
+        let Some((path, position)) = location else {
+            return;
+        };
+
+        let editor: &Editor = cx.editor;
+        let preview = self.get_preview(&path, editor);
+        let Some(doc) = preview.document() else {
+            let placeholder = preview.placeholder();
+            let x = inner.x + inner.width.saturating_sub(placeholder.len() as u16) / 2;
+            let y = inner.y + inner.height / 2;
+            surface.set_stringn(x, y, placeholder, inner.width as usize, editor.theme.get("ui.text"));
+            return;
+        };
+
+        // center the preview on the position if there is one
+        let text = doc.text().slice(..);
+        let last_line = text.len_lines().saturating_sub(1);
+        let first_line = position.map_or(0, |pos| pos.row.min(last_line).saturating_sub(inner.height as usize / 2));
+        let offset = ViewPosition {
+            anchor: text.line_to_char(first_line),
+            ..ViewPosition::default()
+        };
+
+        let highlights = EditorView::doc_syntax_highlights(doc, offset.anchor, inner.height, &editor.theme);
+        render_document(
+            surface,
+            inner,
+            doc,
+            offset,
+            &TextAnnotations::default(),
+            highlights,
+            &editor.theme,
+            &mut [],
+            None,
+        );
+
+        if let Some(pos) = position {
+            let row = pos.row.saturating_sub(first_line);
+            if pos.row >= first_line && row < inner.height as usize {
+                let line_area = Rect::new(inner.x, inner.y + row as u16, inner.width, 1);
+                surface.set_style(line_area, editor.theme.get("ui.highlight"));
+            }
+        }
+    }
+}
+
+impl<T: 'static> Component for Picker<T> {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key = match event {
+            Event::Key(event) => *event,
+            // the picker covers the editor, clicks and scrolling must not reach it
+            Event::Mouse(_) => return EventResult::Consumed(None),
+            _ => return EventResult::Ignored(None),
+        };
+        self.receive();
+
+        let ctrl = |c| KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        };
+
+        match key {
+            KeyEvent { code: KeyCode::Esc, .. } => return Self::close(),
+            k if k == ctrl('c') => return Self::close(),
+            KeyEvent {
+                code: KeyCode::Enter, ..
+            } => {
+                if let Some(option) = self.selection() {
+                    (self.callback_fn)(cx, option, Action::Replace);
+                }
+                return Self::close();
+            }
+            k if k == ctrl('v') => {
+                if let Some(option) = self.selection() {
+                    (self.callback_fn)(cx, option, Action::VerticalSplit);
+                }
+                return Self::close();
+            }
+            KeyEvent { code: KeyCode::Up, .. }
+            | KeyEvent {
+                code: KeyCode::Tab,
+                modifiers: KeyModifiers::SHIFT,
+            } => self.move_by(-1),
+            k if k == ctrl('p') => self.move_by(-1),
+            KeyEvent { code: KeyCode::Down, .. } | KeyEvent { code: KeyCode::Tab, .. } => self.move_by(1),
+            k if k == ctrl('n') => self.move_by(1),
+            _ => {
+                if let EventResult::Consumed(_) = self.prompt.handle_event(event, cx) {
+                    if self.prompt.line() != self.previous_pattern {
+                        self.score();
+                    }
+                }
+            }
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        if area.height < 2 {
+            return;
+        }
+        self.receive();
+
+        surface.clear_with(area, cx.editor.theme.get("ui.popup"));
+
+        let picker_area = self.picker_area(area);
+        self.render_picker(picker_area, surface, cx);
+
+        if picker_area != area {
+            self.render_preview(area.clip_left(picker_area.width), surface, cx);
+        }
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        if area.height < 2 {
+            return (None, CursorKind::Hidden);
+        }
+        self.prompt.cursor(self.prompt_area(self.picker_area(area)), editor)
+    }
+}
+
+/// A [`Picker`] whose options are fetched again once its query changed and typing paused for the
+/// idle timeout, e.g. the symbols of a workspace which a language server only lists for a query.
+/// The options are still filtered with the query while they are fetched.
+pub struct DynamicPicker<T> {
+    picker: Picker<T>,
+    query_callback: DynQueryCallback<T>,
+    /// The query the options were last fetched for.
+    query: String,
+}
+
+impl<T: Send + 'static> DynamicPicker<T> {
+    pub const ID: &'static str = "dynamic-picker";
+
+    /// `picker` holds the options of the empty query.
+    pub fn new(picker: Picker<T>, query_callback: DynQueryCallback<T>) -> Self {
+        Self {
+            picker,
+            query_callback,
+            query: String::new(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Component for DynamicPicker<T> {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let result = self.picker.handle_event(event, cx);
+        let query = self.picker.prompt.line();
+        if query == self.query {
+            return result;
+        }
+        if !matches!(event, Event::IdleTimeout) {
+            // the editor view resets the timer on keys, but they don't reach it under the picker
+            cx.editor.reset_idle_timer();
+            return result;
+        }
+
+        let query = query.to_owned();
+        self.query.clone_from(&query);
+        let options = (self.query_callback)(query.clone(), cx.editor);
+        cx.jobs.callback(async move {
+            let options = options.await?;
+            let callback: job::Callback = Box::new(move |_editor: &mut Editor, compositor: &mut Compositor| {
+                // the options of an outdated query are dropped
+                let Some(overlay) = compositor.find_id::<Overlay<Self>>(Self::ID) else {
+                    return;
+                };
+                if overlay.content.query == query {
+                    overlay.content.picker.set_options(options);
+                }
+            });
+            Ok(callback)
+        });
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.picker.render(area, surface, cx)
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        self.picker.cursor(area, editor)
+    }
+
+    fn id(&self) -> Option<&'static str> {
+        Some(Self::ID)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn streamed_options_are_scored() {
+        let redraw_handle = Arc::new(Notify::new());
+        let (mut picker, injector) = Picker::stream(|option: &String| option.into(), |_, _, _| {}, redraw_handle);
+        assert!(injector.push("src/term/ui/picker.rs".to_owned()));
+        assert!(injector.push("src/view/editor.rs".to_owned()));
+        picker.receive();
+        assert_eq!(picker.matches.len(), 2);
+
+        picker.query = FuzzyQuery::new("edit");
+        picker.matches.clear();
+        assert!(injector.push("src/term/ui/editor.rs".to_owned()));
+        assert!(injector.push("Cargo.toml".to_owned()));
+        drop(injector);
+        picker.receive();
+        assert_eq!(picker.options.len(), 4);
+        assert_eq!(picker.selection().map(String::as_str), Some("src/term/ui/editor.rs"));
+        assert!(picker.receiver.is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    core::Position,
+    term::compositor::{Callback, Component, Compositor, Context, EventResult},
+    tui::buffer::Buffer as Surface,
+    view::{
+        editor::Editor,
+        graphics::Rect,
+        input::{Event, KeyEvent},
+        keyboard::{KeyCode, KeyModifiers},
+    },
+};
+
+/// Maximum size of a popup, its contents are scrolled if they are larger.
+const MAX_SIZE: (u16, u16) = (120, 26);
+
+/// Shows a component in a floating box next to a position on the screen, by default the cursor.
+/// The contents scroll with `C-d`/`C-u` when they don't fit.
+pub struct Popup<T: Component> {
+    contents: T,
+    position: Option<Position>,
+    /// Size of the popup itself, the child size clamped to the available space.
+    size: (u16, u16),
+    /// Size requested by the contents.
+    child_size: (u16, u16),
+    scroll: usize,
+    auto_close: bool,
+    ignore_escape_key: bool,
+    id: &'static str,
+}
+
+impl<T: Component> Popup<T> {
+    pub fn new(id: &'static str, contents: T) -> Self {
+        Self {
+            contents,
+            position: None,
+            size: (0, 0),
+            child_size: (0, 0),
+            scroll: 0,
+            auto_close: false,
+            ignore_escape_key: false,
+            id,
+        }
+    }
+
+    /// Places the popup at `pos` instead of the cursor.
+    pub fn position(mut self, pos: Option<Position>) -> Self {
+        self.position = pos;
+        self
+    }
+
+    /// Closes the popup on any key its contents don't handle.
+    pub fn auto_close(mut self, auto_close: bool) -> Self {
+        self.auto_close = auto_close;
+        self
+    }
+
+    /// Ignores the escape key so that it reaches the layer below, e.g. to leave insert mode
+    /// while a completion menu is open.
+    pub fn ignore_escape_key(mut self, ignore: bool) -> Self {
+        self.ignore_escape_key = ignore;
+        self
+    }
+
+    /// Position of the top left corner of the popup relative to `viewport`. The popup is placed
+    /// below its position, or above it if there isn't enough room, and moved left if it would
+    /// stick out of the viewport.
+    pub fn get_rel_position(&mut self, viewport: Rect, editor: &Editor) -> (u16, u16) {
+        let position = *self.position.get_or_insert_with(|| editor.cursor().0.unwrap_or_default());
+
+        let (width, height) = self.size;
+
+        let mut rel_x = position.col as u16;
+        let mut rel_y = position.row as u16;
+        if viewport.width <= rel_x + width {
+            rel_x = rel_x.saturating_sub((rel_x + width).saturating_sub(viewport.width));
+        }
+
+        if viewport.height > rel_y + height {
+            rel_y += 1 // position below point
+        } else {
+            rel_y = rel_y.saturating_sub(height) // position above point
+        }
+
+        (rel_x, rel_y)
+    }
+
+    /// Scrolls the contents by `offset` lines, downwards if `direction` is true.
+    pub fn scroll(&mut self, offset: usize, direction: bool) {
+        if direction {
+            let max_offset = self.child_size.1.saturating_sub(self.size.1) as usize;
+            self.scroll = (self.scroll + offset).min(max_offset);
+        } else {
+            self.scroll = self.scroll.saturating_sub(offset);
+        }
+    }
+
+    pub fn contents(&self) -> &T {
+        &self.contents
+    }
+
+    pub fn contents_mut(&mut self) -> &mut T {
+        &mut self.contents
+    }
+
+    /// The area the popup is drawn in, within `viewport`.
+    pub fn area(&mut self, viewport: Rect, editor: &Editor) -> Rect {
+        let (rel_x, rel_y) = self.get_rel_position(viewport, editor);
+        Rect::new(viewport.x + rel_x, viewport.y + rel_y, self.size.0, self.size.1).intersection(viewport)
+    }
+}
+
+impl<T: Component> Component for Popup<T> {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key = match event {
+            Event::Key(event) => *event,
+            _ => return EventResult::Ignored(None),
+        };
+
+        if key.code == KeyCode::Esc && self.ignore_escape_key {
+            return EventResult::Ignored(None);
+        }
+
+        let id = self.id;
+        let close_fn: Callback = Box::new(move |compositor: &mut Compositor, _| {
+            compositor.remove(id);
+        });
+
+        let ctrl = |c| KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        };
+
+        match key {
+            // esc or ctrl-c aborts the completion and closes the menu
+            KeyEvent { code: KeyCode::Esc, .. } => {
+                let _ = self.contents.handle_event(event, cx);
+                EventResult::Consumed(Some(close_fn))
+            }
+            k if k == ctrl('c') => {
+                let _ = self.contents.handle_event(event, cx);
+                EventResult::Consumed(Some(close_fn))
+            }
+            k if k == ctrl('d') => {
+                self.scroll(self.size.1 as usize / 2, true);
+                EventResult::Consumed(None)
+            }
+            k if k == ctrl('u') => {
+                self.scroll(self.size.1 as usize / 2, false);
+                EventResult::Consumed(None)
+            }
+            _ => {
+                let contents_event_result = self.contents.handle_event(event, cx);
+
+                if self.auto_close {
+                    if let EventResult::Ignored(None) = contents_event_result {
+                        return EventResult::Ignored(Some(close_fn));
+                    }
+                }
+
+                contents_event_result
+            }
+        }
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        let max_width = MAX_SIZE.0.min(viewport.0);
+        // leave some spacing in the viewport
+        let max_height = MAX_SIZE.1.min(viewport.1.saturating_sub(2));
+
+        let (width, height) = self
+            .contents
+            .required_size((max_width, max_height))
+            .expect("Component needs required_size implemented in order to be embedded in a popup");
+
+        self.child_size = (width, height);
+        self.size = (width.min(max_width), height.min(max_height));
+
+        // re-clamp scroll offset
+        let max_offset = self.child_size.1.saturating_sub(self.size.1) as usize;
+        self.scroll = self.scroll.min(max_offset);
+
+        Some(self.size)
+    }
+
+    fn render(&mut self, viewport: Rect, surface: &mut Surface, cx: &mut Context) {
+        // trigger required_size so we recalculate if the child changed
+        self.required_size((viewport.width, viewport.height));
+
+        let area = self.area(viewport, cx.editor);
+        let background = cx.editor.theme.get("ui.popup");
+        surface.clear_with(area, background);
+
+        cx.scroll = Some(self.scroll);
+        self.contents.render(area, surface, cx);
+        cx.scroll = None;
+
+        // render a scrollbar on the right edge if the contents don't fit
+        let win_height = area.height as usize;
+        let len = self.child_size.1 as usize;
+        if len > win_height && area.width > 0 {
+            let scroll_style = cx.editor.theme.get("ui.menu.scroll");
+            let scroll_height = (win_height * win_height / len).max(1);
+            let scroll_line = (win_height - scroll_height) * self.scroll / (len - win_height);
+            for y in scroll_line..(scroll_line + scroll_height).min(win_height) {
+                surface[(area.right() - 1, area.y + y as u16)]
+                    .set_symbol("▐")
+                    .set_style(scroll_style);
+            }
+        }
+    }
+
+    fn id(&self) -> Option<&'static str> {
+        Some(self.id)
+    }
+}
@@ -0,0 +1,245 @@
+use std::{borrow::Cow, ops::RangeFrom};
+
+use crate::{
+    core::{
+        graphemes::{nth_next_grapheme_boundary, nth_prev_grapheme_boundary},
+        unicode::width::UnicodeWidthStr,
+        Position,
+    },
+    term::compositor::{Component, Compositor, Context, EventResult},
+    tui::buffer::Buffer as Surface,
+    view::{
+        document::Mode,
+        editor::Editor,
+        graphics::{CursorKind, Rect},
+        input::{Event, KeyEvent},
+        keyboard::{KeyCode, KeyModifiers},
+    },
+};
+
+pub type PromptCallback = Box<dyn FnMut(&mut Context, &str, PromptEvent)>;
+/// A completion of the line: the byte range it replaces and the text it is replaced with.
+pub type Completion = (RangeFrom<usize>, Cow<'static, str>);
+pub type CompletionFn = Box<dyn FnMut(&Editor, &str) -> Vec<Completion>>;
+
+/// Maximum number of rows of completions shown above the prompt.
+const MAX_COMPLETION_ROWS: u16 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptEvent {
+    /// The prompt input has been updated.
+    Update,
+    /// Validate and finalize the change.
+    Validate,
+    /// Abort the change, reverting to the initial state.
+    Abort,
+}
+
+/// A single line input rendered in the command line row at the bottom of the screen.
+pub struct Prompt {
+    prompt: Cow<'static, str>,
+    line: String,
+    /// Char index of the cursor in `line`.
+    cursor: usize,
+    callback_fn: PromptCallback,
+    completion_fn: Option<CompletionFn>,
+    completion: Vec<Completion>,
+    /// Index into `completion` of the completion applied with `Tab`, if any.
+    selection: Option<usize>,
+}
+
+impl Prompt {
+    pub fn new(prompt: Cow<'static, str>, callback_fn: impl FnMut(&mut Context, &str, PromptEvent) + 'static) -> Self {
+        Self {
+            prompt,
+            line: String::new(),
+            cursor: 0,
+            callback_fn: Box::new(callback_fn),
+            completion_fn: None,
+            completion: Vec::new(),
+            selection: None,
+        }
+    }
+
+    /// Completes the line with the completions returned by `completion_fn`, shown above the
+    /// prompt and cycled through with `Tab` and `Shift-Tab`.
+    pub fn with_completion(mut self, completion_fn: impl FnMut(&Editor, &str) -> Vec<Completion> + 'static) -> Self {
+        self.completion_fn = Some(Box::new(completion_fn));
+        self
+    }
+
+    /// Recomputes the completions for the current line.
+    pub fn recalculate_completion(&mut self, editor: &Editor) {
+        self.selection = None;
+        self.completion = match &mut self.completion_fn {
+            Some(completion_fn) => completion_fn(editor, &self.line),
+            None => Vec::new(),
+        };
+    }
+
+    /// Replaces the line with the next (or previous) completion.
+    fn change_completion_selection(&mut self, forward: bool) {
+        let len = self.completion.len();
+        if len == 0 {
+            return;
+        }
+        let index = match self.selection {
+            Some(index) if forward => (index + 1) % len,
+            Some(index) => (index + len - 1) % len,
+            None if forward => 0,
+            None => len - 1,
+        };
+        self.selection = Some(index);
+
+        let (range, text) = &self.completion[index];
+        self.line.replace_range(range.clone(), text);
+        self.cursor = self.line.chars().count();
+    }
+
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    fn byte_cursor(&self) -> usize {
+        self.line.char_indices().nth(self.cursor).map_or(self.line.len(), |(i, _)| i)
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let pos = self.byte_cursor();
+        self.line.insert(pos, c);
+        self.cursor += 1;
+    }
+
+    fn delete_char_backwards(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        let rope = ropey::Rope::from_str(&self.line);
+        let start = nth_prev_grapheme_boundary(rope.slice(..), self.cursor, 1);
+        let (from, to) = (rope.char_to_byte(start), self.byte_cursor());
+        self.line.replace_range(from..to, "");
+        self.cursor = start;
+    }
+
+    fn move_cursor(&mut self, forward: bool) {
+        let rope = ropey::Rope::from_str(&self.line);
+        self.cursor = if forward {
+            nth_next_grapheme_boundary(rope.slice(..), self.cursor, 1)
+        } else {
+            nth_prev_grapheme_boundary(rope.slice(..), self.cursor, 1)
+        };
+    }
+
+    fn close() -> EventResult {
+        EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+            compositor.pop();
+        })))
+    }
+
+    /// Lists the completions in columns at the bottom of `area`.
+    fn render_completion(&self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        let theme = &cx.editor.theme;
+        let style = theme.get("ui.menu");
+        let selected_style = theme.get("ui.menu.selected");
+
+        let max_len = self.completion.iter().map(|(_, text)| text.width()).max().unwrap_or(0);
+        let col_width = (max_len as u16 + 1).clamp(1, area.width.max(1));
+        let cols = (area.width / col_width).max(1) as usize;
+        let len = self.completion.len();
+        let rows = len.div_ceil(cols).min(MAX_COMPLETION_ROWS.min(area.height) as usize);
+        if rows == 0 {
+            return;
+        }
+
+        let completion_area = area.clip_top(area.height - rows as u16);
+        surface.clear_with(completion_area, style);
+
+        // the completions fill the columns top to bottom, page by page
+        let page_size = rows * cols;
+        let offset = self.selection.map_or(0, |selection| selection - selection % page_size);
+        for (i, (_, text)) in self.completion.iter().enumerate().skip(offset).take(page_size) {
+            let index = i - offset;
+            let x = completion_area.x + (index / rows) as u16 * col_width;
+            let y = completion_area.y + (index % rows) as u16;
+            let style = if self.selection == Some(i) { selected_style } else { style };
+            surface.set_stringn(x, y, text, col_width.saturating_sub(1) as usize, style);
+        }
+    }
+}
+
+impl Component for Prompt {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let event = match event {
+            Event::Key(event) => *event,
+            _ => return EventResult::Ignored(None),
+        };
+
+        match event {
+            KeyEvent { code: KeyCode::Esc, .. }
+            | KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+            } => {
+                (self.callback_fn)(cx, &self.line, PromptEvent::Abort);
+                return Self::close();
+            }
+            KeyEvent {
+                code: KeyCode::Enter, ..
+            } => {
+                (self.callback_fn)(cx, &self.line, PromptEvent::Validate);
+                return Self::close();
+            }
+            KeyEvent {
+                code: KeyCode::Tab,
+                modifiers,
+            } => {
+                self.change_completion_selection(!modifiers.contains(KeyModifiers::SHIFT));
+                (self.callback_fn)(cx, &self.line, PromptEvent::Update);
+                return EventResult::Consumed(None);
+            }
+            KeyEvent {
+                code: KeyCode::Backspace,
+                ..
+            } => self.delete_char_backwards(),
+            KeyEvent { code: KeyCode::Left, .. } => self.move_cursor(false),
+            KeyEvent {
+                code: KeyCode::Right, ..
+            } => self.move_cursor(true),
+            KeyEvent { code: KeyCode::Home, .. } => self.cursor = 0,
+            KeyEvent { code: KeyCode::End, .. } => self.cursor = self.line.chars().count(),
+            KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers,
+            } if !modifiers.contains(KeyModifiers::CONTROL) && !modifiers.contains(KeyModifiers::ALT) => self.insert_char(c),
+            _ => return EventResult::Consumed(None),
+        }
+
+        self.recalculate_completion(cx.editor);
+        (self.callback_fn)(cx, &self.line, PromptEvent::Update);
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        let theme = &cx.editor.theme;
+        let line_area = area.clip_top(area.height.saturating_sub(1));
+
+        surface.set_style(line_area, theme.get("ui.background"));
+        let (x, y) = surface.set_stringn(line_area.x, line_area.y, &self.prompt, usize::MAX, theme.get("ui.text"));
+        surface.set_stringn(x, y, &self.line, usize::MAX, theme.get("ui.text"));
+
+        if !self.completion.is_empty() {
+            self.render_completion(area.clip_bottom(1), surface, cx);
+        }
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        let line = area.height.saturating_sub(1) as usize;
+        (
+            Some(Position::new(
+                area.y as usize + line,
+                area.x as usize + self.prompt.width() + self.line[..self.byte_cursor()].width(),
+            )),
+            editor.config().cursor_shape.from_mode(Mode::Insert),
+        )
+    }
+}
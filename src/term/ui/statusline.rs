@@ -0,0 +1,253 @@
+use crate::{
+    core::{coords_at_pos, diagnostic::Severity, encoding, path::get_relative_path, unicode::width::UnicodeWidthStr, Position},
+    tui::buffer::Buffer as Surface,
+    view::{
+        document::{Document, Mode, SCRATCH_BUFFER_NAME},
+        editor::{Editor, StatusLineElement},
+        graphics::{Rect, Style},
+        view::View,
+    },
+};
+
+/// Text of a statusline area, as spans with an optional style patched over the base style.
+type Spans = Vec<(String, Option<Style>)>;
+
+pub struct RenderContext<'a> {
+    pub editor: &'a Editor,
+    pub doc: &'a Document,
+    pub view: &'a View,
+    pub focused: bool,
+    pub parts: RenderBuffer,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new(editor: &'a Editor, doc: &'a Document, view: &'a View, focused: bool) -> Self {
+        RenderContext {
+            editor,
+            doc,
+            view,
+            focused,
+            parts: RenderBuffer::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderBuffer {
+    pub left: Spans,
+    pub center: Spans,
+    pub right: Spans,
+}
+
+fn width(spans: &Spans) -> u16 {
+    spans.iter().map(|(text, _)| text.width()).sum::<usize>() as u16
+}
+
+/// Draws `spans` starting at `x`, cut off after `max_width` columns.
+fn set_spans(surface: &mut Surface, x: u16, y: u16, spans: &Spans, max_width: u16, base_style: Style) {
+    let (mut x, end) = (x, x.saturating_add(max_width));
+    for (text, style) in spans {
+        if x >= end {
+            break;
+        }
+        let style = style.map_or(base_style, |style| base_style.patch(style));
+        (x, _) = surface.set_stringn(x, y, text, (end - x) as usize, style);
+    }
+}
+
+/// Renders the statusline of a view into `viewport`, which is expected to be a single row.
+pub fn render(context: &mut RenderContext, viewport: Rect, surface: &mut Surface) {
+    let theme = &context.editor.theme;
+    let base_style = if context.focused {
+        theme.get("ui.statusline")
+    } else {
+        theme.get("ui.statusline.inactive")
+    };
+
+    surface.set_style(viewport, base_style);
+
+    let config = context.editor.config();
+
+    // Left side of the status line.
+    for element in &config.statusline.left {
+        let text = render_element(context, *element);
+        context.parts.left.extend(text);
+    }
+    set_spans(
+        surface,
+        viewport.x,
+        viewport.y,
+        &context.parts.left,
+        viewport.width,
+        base_style,
+    );
+
+    // Right side of the status line.
+    for element in &config.statusline.right {
+        let text = render_element(context, *element);
+        context.parts.right.extend(text);
+    }
+    let right_width = width(&context.parts.right);
+    set_spans(
+        surface,
+        viewport.x + viewport.width.saturating_sub(right_width),
+        viewport.y,
+        &context.parts.right,
+        right_width.min(viewport.width),
+        base_style,
+    );
+
+    // Center of the status line.
+    for element in &config.statusline.center {
+        let text = render_element(context, *element);
+        context.parts.center.extend(text);
+    }
+
+    // Width of the empty space between the left and center area and between the center and right area.
+    let spacing = 1u16;
+
+    let edge_width = width(&context.parts.left).max(right_width);
+    let center_max_width = viewport.width.saturating_sub(2 * edge_width + 2 * spacing);
+    let center_width = center_max_width.min(width(&context.parts.center));
+
+    set_spans(
+        surface,
+        viewport.x + viewport.width / 2 - center_width / 2,
+        viewport.y,
+        &context.parts.center,
+        center_width,
+        base_style,
+    );
+}
+
+fn render_element(context: &RenderContext, element: StatusLineElement) -> Spans {
+    match element {
+        StatusLineElement::Mode => render_mode(context),
+        StatusLineElement::FileBaseName => render_file_base_name(context),
+        StatusLineElement::FileName => render_file_name(context),
+        StatusLineElement::FileModificationIndicator => render_file_modification_indicator(context),
+        StatusLineElement::FileEncoding => render_file_encoding(context),
+        StatusLineElement::FileLineEnding => render_file_line_ending(context),
+        StatusLineElement::FileType => render_file_type(context),
+        StatusLineElement::Diagnostics => render_diagnostics(context),
+        StatusLineElement::Selections => render_selections(context),
+        StatusLineElement::Position => render_position(context),
+        StatusLineElement::Separator => render_separator(context),
+        StatusLineElement::TotalLineNumbers => render_total_line_numbers(context),
+        StatusLineElement::Spacer => render_spacer(context),
+    }
+}
+
+fn render_mode(context: &RenderContext) -> Spans {
+    let modenames = &context.editor.config().statusline.mode;
+
+    if !context.focused {
+        // If not focused, explicitly leave an empty space instead of nothing.
+        return vec![(format!(" {} ", " ".repeat(modenames.normal.width())), None)];
+    }
+
+    let (name, scope) = match context.editor.mode() {
+        Mode::Insert => (&modenames.insert, "ui.statusline.insert"),
+        Mode::Select => (&modenames.select, "ui.statusline.select"),
+        Mode::Normal => (&modenames.normal, "ui.statusline.normal"),
+    };
+    vec![(format!(" {} ", name), context.editor.theme.try_get(scope))]
+}
+
+fn render_diagnostics(context: &RenderContext) -> Spans {
+    let (warnings, errors) = context.doc.diagnostics().iter().fold((0, 0), |mut counts, diag| {
+        match diag.severity() {
+            Severity::Warning => counts.0 += 1,
+            Severity::Error => counts.1 += 1,
+            _ => {}
+        }
+        counts
+    });
+
+    let mut spans = Spans::new();
+    let theme = &context.editor.theme;
+    if warnings > 0 {
+        spans.push(("●".to_string(), Some(theme.get("warning"))));
+        spans.push((format!(" {} ", warnings), None));
+    }
+    if errors > 0 {
+        spans.push(("●".to_string(), Some(theme.get("error"))));
+        spans.push((format!(" {} ", errors), None));
+    }
+    spans
+}
+
+fn render_selections(context: &RenderContext) -> Spans {
+    let count = context.doc.selection(context.view.id).len();
+    vec![(format!(" {} sel{} ", count, if count == 1 { "" } else { "s" }), None)]
+}
+
+fn get_position(context: &RenderContext) -> Position {
+    let text = context.doc.text().slice(..);
+    coords_at_pos(text, context.doc.selection(context.view.id).primary().cursor(text))
+}
+
+fn render_position(context: &RenderContext) -> Spans {
+    let position = get_position(context);
+    vec![(format!(" {}:{} ", position.row + 1, position.col + 1), None)]
+}
+
+fn render_total_line_numbers(context: &RenderContext) -> Spans {
+    let total_line_numbers = context.doc.text().len_lines();
+    vec![(format!(" {} ", total_line_numbers), None)]
+}
+
+fn render_file_encoding(context: &RenderContext) -> Spans {
+    let enc = context.doc.encoding();
+    let bom = if context.doc.has_bom() { " (BOM)" } else { "" };
+
+    if enc != encoding::UTF_8 || context.doc.has_bom() {
+        vec![(format!(" {}{} ", enc.name(), bom), None)]
+    } else {
+        Vec::new()
+    }
+}
+
+fn render_file_line_ending(context: &RenderContext) -> Spans {
+    vec![(format!(" {} ", context.doc.line_ending.name()), None)]
+}
+
+fn render_file_type(context: &RenderContext) -> Spans {
+    let file_type = context
+        .doc
+        .language
+        .as_ref()
+        .map_or("text", |language| language.language_id.as_str());
+    vec![(format!(" {} ", file_type), None)]
+}
+
+fn render_file_name(context: &RenderContext) -> Spans {
+    let path = context.doc.path().map_or_else(
+        || SCRATCH_BUFFER_NAME.into(),
+        |path| get_relative_path(path).to_string_lossy().into_owned(),
+    );
+    vec![(format!(" {} ", path), None)]
+}
+
+fn render_file_base_name(context: &RenderContext) -> Spans {
+    let path = context
+        .doc
+        .path()
+        .and_then(|path| path.file_name())
+        .map_or_else(|| SCRATCH_BUFFER_NAME.into(), |name| name.to_string_lossy().into_owned());
+    vec![(format!(" {} ", path), None)]
+}
+
+fn render_file_modification_indicator(context: &RenderContext) -> Spans {
+    let title = if context.doc.is_modified() { "[+]" } else { "   " };
+    vec![(title.to_string(), None)]
+}
+
+fn render_separator(context: &RenderContext) -> Spans {
+    let sep = &context.editor.config().statusline.separator;
+    vec![(sep.to_string(), Some(context.editor.theme.get("ui.statusline.separator")))]
+}
+
+fn render_spacer(_context: &RenderContext) -> Spans {
+    vec![(String::from(" "), None)]
+}
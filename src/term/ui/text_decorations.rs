@@ -0,0 +1,22 @@
+use crate::term::ui::document::{LinePos, TextRenderer};
+
+/// Decorations are the primary mechanism for extending the text rendering.
+///
+/// Any on-screen element which is anchored to the rendered text in some form
+/// should be implemented using this trait. Translating char positions to
+/// on-screen positions can be expensive and should not be done manually in the
+/// ui loop.
+pub trait LineDecoration {
+    /// Called before any text of the visual line at `pos` is drawn.
+    fn render_background(&mut self, _renderer: &mut TextRenderer, _pos: LinePos) {}
+
+    /// Called after all the text of the visual line at `pos` has been drawn, `end_char_idx`
+    /// is the char index following the line.
+    fn render_foreground(&mut self, _renderer: &mut TextRenderer, _pos: LinePos, _end_char_idx: usize) {}
+}
+
+impl<F: FnMut(&mut TextRenderer, LinePos)> LineDecoration for F {
+    fn render_background(&mut self, renderer: &mut TextRenderer, pos: LinePos) {
+        self(renderer, pos)
+    }
+}
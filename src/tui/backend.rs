@@ -1,10 +1,11 @@
 use std::io;
 
-use crate::view::graphics::Rect;
+use crate::view::graphics::{ColorSupport, CursorKind, Rect};
 
 use super::buffer::Cell;
 
 pub mod crossterm;
+pub mod test;
 
 pub trait Backend {
     fn size(&self) -> Result<Rect, io::Error>;
@@ -12,5 +13,13 @@ pub trait Backend {
     where
         I: Iterator<Item = (u16, u16, &'a Cell)>;
     fn hide_cursor(&mut self) -> Result<(), io::Error>;
+    fn show_cursor(&mut self, kind: CursorKind) -> Result<(), io::Error>;
+    fn get_cursor(&mut self) -> Result<(u16, u16), io::Error>;
+    fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), io::Error>;
+    fn clear(&mut self) -> Result<(), io::Error>;
     fn flush(&mut self) -> Result<(), io::Error>;
+    /// The colors the terminal is able to display.
+    fn color_support(&self) -> ColorSupport {
+        ColorSupport::TrueColor
+    }
 }
@@ -1,31 +1,105 @@
-use std::io::{self, Write};
+use std::{
+    env,
+    io::{self, Write},
+};
 
 use crossterm::{
-    cursor::{Hide, MoveTo},
+    cursor::{Hide, MoveTo, SetCursorStyle, Show},
     execute, queue,
     style::{
         Attribute as CAttribute, Color as CColor, Print, SetAttribute, SetBackgroundColor, SetForegroundColor, SetUnderlineColor,
     },
     terminal,
 };
+use terminfo::{capability as cap, Database};
 
 use crate::{
     tui::buffer::Cell,
-    view::graphics::{Color, Modifier, Rect, UnderlineStyle},
+    view::{
+        editor::Config as EditorConfig,
+        graphics::{Color, ColorSupport, CursorKind, Modifier, Rect, UnderlineStyle},
+    },
 };
 
 use super::Backend;
 
+/// Terminals known to support truecolor and extended underlines, whose terminfo entries are often
+/// missing or incomplete.
+const KNOWN_TERMS: &[&str] = &["xterm-kitty", "wezterm", "foot", "foot-extra", "alacritty", "xterm-ghostty"];
+const KNOWN_TERM_PROGRAMS: &[&str] = &["WezTerm", "ghostty"];
+/// Terminals known to support truecolor.
+const TRUECOLOR_TERM_PROGRAMS: &[&str] = &["iTerm.app", "vscode", "Hyper", "WezTerm", "ghostty"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub has_extended_underlines: bool,
+    pub colors: ColorSupport,
+}
+
+impl Capabilities {
+    /// Detects the terminal capabilities from its terminfo entry and environment variables,
+    /// falling back to the values set in the config.
+    pub fn from_env_or_default(config: &EditorConfig) -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+        let terminfo = Database::from_env().ok();
+        let has_cap = |name| terminfo.as_ref().is_some_and(|info| info.raw(name).is_some());
+        let vte_version = env::var("VTE_VERSION").ok().and_then(|version| version.parse::<usize>().ok());
+        let known_term = KNOWN_TERMS.contains(&term.as_str()) || KNOWN_TERM_PROGRAMS.contains(&term_program.as_str());
+
+        // Smulx: styled underlines (used by VTE based terminals), Setulc: underline color,
+        // Su: styled underlines (used by kitty)
+        let has_extended_underlines =
+            config.undercurl || has_cap("Smulx") || has_cap("Setulc") || has_cap("Su") || vte_version >= Some(5102) || known_term;
+
+        let true_color = config.true_color
+            || matches!(env::var("COLORTERM").as_deref(), Ok("truecolor" | "24bit"))
+            || has_cap("Tc")
+            || has_cap("RGB")
+            || term.ends_with("-direct")
+            || vte_version >= Some(3600)
+            || known_term
+            || TRUECOLOR_TERM_PROGRAMS.contains(&term_program.as_str());
+
+        let max_colors = terminfo
+            .as_ref()
+            .and_then(|info| info.get::<cap::MaxColors>())
+            .map_or(0, i32::from);
+        let colors = if true_color {
+            ColorSupport::TrueColor
+        } else if max_colors >= 256 || term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        };
+
+        let capabilities = Capabilities {
+            has_extended_underlines,
+            colors,
+        };
+        tracing::debug!("detected terminal capabilities: {capabilities:?}");
+        capabilities
+    }
+}
+
 pub struct CrosstermBackend<W: Write> {
     buffer: W,
+    capabilities: Capabilities,
 }
 
 impl<W> CrosstermBackend<W>
 where
     W: Write,
 {
-    pub fn new(buffer: W) -> CrosstermBackend<W> {
-        CrosstermBackend { buffer }
+    pub fn new(buffer: W, config: &EditorConfig) -> CrosstermBackend<W> {
+        CrosstermBackend {
+            buffer,
+            capabilities: Capabilities::from_env_or_default(config),
+        }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
     }
 }
 
@@ -34,7 +108,7 @@ where
     W: Write,
 {
     fn size(&self) -> Result<crate::view::graphics::Rect, std::io::Error> {
-        let (width, height) = terminal::size().map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let (width, height) = terminal::size().map_err(|e| io::Error::other(e.to_string()))?;
 
         Ok(Rect::new(0, 0, width, height))
     }
@@ -64,22 +138,20 @@ where
                 modifier = cell.modifier;
             }
             if cell.fg != fg {
-                let color = CColor::from(cell.fg);
+                let color = CColor::from(cell.fg.downgrade(self.capabilities.colors));
                 map_error(queue!(self.buffer, SetForegroundColor(color)))?;
                 fg = cell.fg;
             }
             if cell.bg != bg {
-                let color = CColor::from(cell.bg);
+                let color = CColor::from(cell.bg.downgrade(self.capabilities.colors));
                 map_error(queue!(self.buffer, SetBackgroundColor(color)))?;
                 bg = cell.bg;
             }
 
-            // TODO: handle underline
-            /*
             let mut new_underline_style = cell.underline_style;
             if self.capabilities.has_extended_underlines {
                 if cell.underline_color != underline_color {
-                    let color = CColor::from(cell.underline_color);
+                    let color = CColor::from(cell.underline_color.downgrade(self.capabilities.colors));
                     map_error(queue!(self.buffer, SetUnderlineColor(color)))?;
                     underline_color = cell.underline_color;
                 }
@@ -95,7 +167,7 @@ where
                 map_error(queue!(self.buffer, SetAttribute(attr)))?;
                 underline_style = new_underline_style;
             }
-            */
+
             map_error(queue!(self.buffer, Print(&cell.symbol)))?;
         }
 
@@ -108,17 +180,43 @@ where
         ))
     }
 
+    fn clear(&mut self) -> Result<(), io::Error> {
+        map_error(execute!(self.buffer, terminal::Clear(terminal::ClearType::All)))
+    }
+
     fn flush(&mut self) -> Result<(), io::Error> {
         self.buffer.flush()
     }
 
+    fn color_support(&self) -> ColorSupport {
+        self.capabilities.colors
+    }
+
     fn hide_cursor(&mut self) -> Result<(), io::Error> {
         map_error(execute!(self.buffer, Hide))
     }
+
+    fn show_cursor(&mut self, kind: CursorKind) -> Result<(), io::Error> {
+        let shape = match kind {
+            CursorKind::Block => SetCursorStyle::SteadyBlock,
+            CursorKind::Bar => SetCursorStyle::SteadyBar,
+            CursorKind::Underline => SetCursorStyle::SteadyUnderScore,
+            CursorKind::Hidden => unreachable!(),
+        };
+        map_error(execute!(self.buffer, Show, shape))
+    }
+
+    fn get_cursor(&mut self) -> Result<(u16, u16), io::Error> {
+        crossterm::cursor::position().map_err(|e| io::Error::other(e.to_string()))
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        map_error(execute!(self.buffer, MoveTo(x, y)))
+    }
 }
 
 fn map_error(error: crossterm::Result<()>) -> io::Result<()> {
-    error.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    error.map_err(|e| io::Error::other(e.to_string()))
 }
 
 #[derive(Debug)]
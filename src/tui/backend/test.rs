@@ -0,0 +1,123 @@
+use std::{fmt::Write, io};
+
+use crate::{
+    core::unicode::width::UnicodeWidthStr,
+    tui::buffer::{Buffer, Cell},
+    view::graphics::{CursorKind, Rect},
+};
+
+use super::Backend;
+
+/// A backend that draws into an in-memory [`Buffer`] instead of a terminal, used to test the
+/// rendering of the editor.
+#[derive(Debug)]
+pub struct TestBackend {
+    width: u16,
+    height: u16,
+    buffer: Buffer,
+    cursor: Option<CursorKind>,
+    pos: (u16, u16),
+}
+
+impl TestBackend {
+    pub fn new(width: u16, height: u16) -> TestBackend {
+        TestBackend {
+            width,
+            height,
+            buffer: Buffer::empty(Rect::new(0, 0, width, height)),
+            cursor: None,
+            pos: (0, 0),
+        }
+    }
+
+    /// The cells drawn so far.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// The kind of the cursor if it is shown.
+    pub fn cursor(&self) -> Option<CursorKind> {
+        self.cursor
+    }
+
+    /// The position the cursor was last moved to.
+    pub fn cursor_position(&self) -> (u16, u16) {
+        self.pos
+    }
+
+    /// Changes the size reported to the terminal, which picks it up on the next render.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.buffer.resize(Rect::new(0, 0, width, height));
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Returns the drawn symbols as text, one line per row with trailing whitespace removed.
+    pub fn screen(&self) -> String {
+        buffer_view(&self.buffer)
+    }
+}
+
+/// Returns the symbols of `buffer` as text. The cells hidden behind wide symbols are skipped so
+/// that each line has the width of the buffer.
+pub fn buffer_view(buffer: &Buffer) -> String {
+    let mut view = String::with_capacity(buffer.content.len() + buffer.area.height as usize);
+    for cells in buffer.content.chunks(buffer.area.width as usize) {
+        let mut line = String::new();
+        let mut skip = 0;
+        for cell in cells {
+            if skip == 0 {
+                line.push_str(&cell.symbol);
+                skip = cell.symbol.width().saturating_sub(1);
+            } else {
+                skip -= 1;
+            }
+        }
+        writeln!(view, "{}", line.trim_end()).unwrap();
+    }
+    view
+}
+
+impl Backend for TestBackend {
+    fn size(&self) -> Result<Rect, io::Error> {
+        Ok(Rect::new(0, 0, self.width, self.height))
+    }
+
+    fn draw<'a, I>(&mut self, content: I) -> Result<(), io::Error>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        for (x, y, cell) in content {
+            self.buffer[(x, y)] = cell.clone();
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), io::Error> {
+        self.cursor = None;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self, kind: CursorKind) -> Result<(), io::Error> {
+        self.cursor = Some(kind);
+        Ok(())
+    }
+
+    fn get_cursor(&mut self) -> Result<(u16, u16), io::Error> {
+        Ok(self.pos)
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> Result<(), io::Error> {
+        self.pos = (x, y);
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), io::Error> {
+        self.buffer.reset();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
@@ -99,6 +99,29 @@ impl Buffer {
         }
     }
 
+    /// Resets the cells of `area` and applies `style` to them.
+    pub fn clear_with(&mut self, area: Rect, style: Style) {
+        for x in area.left()..area.right() {
+            for y in area.top()..area.bottom() {
+                let cell = &mut self[(x, y)];
+                cell.reset();
+                cell.set_style(style);
+            }
+        }
+    }
+
+    /// Resizes the buffer so that the mapped area matches the given area and that the buffer
+    /// length is equal to area.width * area.height
+    pub fn resize(&mut self, area: Rect) {
+        let length = area.area();
+        if self.content.len() > length {
+            self.content.truncate(length);
+        } else {
+            self.content.resize(length, Default::default());
+        }
+        self.area = area;
+    }
+
     pub fn reset(&mut self) {
         for c in &mut self.content {
             c.reset();
@@ -28,8 +28,11 @@ where
     /// Holds the results of the current adn previous draw calls. The two are compared at the end
     /// of each draw pass to output the necessary updates to the terminal
     buffers: [Buffer; 2],
+    viewport: Viewport,
     /// Index of the current buffer in the previous array
     pub current: usize,
+    /// Kind of cursor (hidden or others)
+    cursor_kind: CursorKind,
 }
 
 impl<B> Terminal<B>
@@ -51,20 +54,47 @@ where
         Ok(Terminal {
             backend,
             buffers: [Buffer::empty(options.viewport.area), Buffer::empty(options.viewport.area)],
+            viewport: options.viewport,
             current: 0,
+            cursor_kind: CursorKind::Block,
         })
     }
 
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
     pub fn current_buffer_mut(&mut self) -> &mut Buffer {
         &mut self.buffers[self.current]
     }
 
+    /// Updates the Terminal so that internal buffers match the requested size. Requested size will
+    /// be saved so the size can remain consistent when rendering.
+    /// This leads to a full clear of the screen.
+    pub fn resize(&mut self, area: Rect) -> io::Result<()> {
+        self.buffers[self.current].resize(area);
+        self.buffers[1 - self.current].resize(area);
+        self.viewport.area = area;
+        self.clear()
+    }
+
+    /// Queries the backend for size and resizes if it doesn't match the previous size.
     pub fn autoresize(&mut self) -> io::Result<Rect> {
         let size = self.size()?;
-        // TODO: compare self.viewport.area
+        if size != self.viewport.area {
+            self.resize(size)?;
+        };
         Ok(size)
     }
 
+    /// Clears the terminal and forces a full redraw on the next draw call.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.backend.clear()?;
+        // Reset the back buffer to make sure the next update will redraw everything.
+        self.buffers[1 - self.current].reset();
+        Ok(())
+    }
+
     pub fn size(&self) -> io::Result<Rect> {
         self.backend.size()
     }
@@ -81,8 +111,14 @@ where
     pub fn draw(&mut self, cursor_position: Option<(u16, u16)>, cursor_kind: CursorKind) -> io::Result<()> {
         self.flush()?;
 
-        // TODO: handle cursor
-        self.hide_cursor()?;
+        if let Some((x, y)) = cursor_position {
+            self.set_cursor(x, y)?;
+        }
+
+        match cursor_kind {
+            CursorKind::Hidden => self.hide_cursor()?,
+            kind => self.show_cursor(kind)?,
+        }
 
         self.buffers[1 - self.current].reset();
         self.current = 1 - self.current;
@@ -92,6 +128,52 @@ where
     }
 
     pub fn hide_cursor(&mut self) -> io::Result<()> {
-        self.backend.hide_cursor()
+        self.backend.hide_cursor()?;
+        self.cursor_kind = CursorKind::Hidden;
+        Ok(())
+    }
+
+    pub fn show_cursor(&mut self, kind: CursorKind) -> io::Result<()> {
+        self.backend.show_cursor(kind)?;
+        self.cursor_kind = kind;
+        Ok(())
+    }
+
+    pub fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        self.backend.get_cursor()
+    }
+
+    pub fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.backend.set_cursor(x, y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{tui::backend::test::TestBackend, view::graphics::Style};
+
+    #[test]
+    fn autoresize() {
+        let mut terminal = Terminal::new(TestBackend::new(4, 2)).unwrap();
+        terminal.current_buffer_mut().set_string(0, 0, "ab", Style::default());
+        terminal.draw(None, CursorKind::Hidden).unwrap();
+        assert_eq!(terminal.backend().screen(), "ab\n\n");
+
+        // an unchanged size keeps the screen
+        assert_eq!(terminal.autoresize().unwrap(), Rect::new(0, 0, 4, 2));
+        assert_eq!(terminal.backend().screen(), "ab\n\n");
+
+        let area = Rect::new(0, 0, 6, 3);
+        terminal.backend.resize(area.width, area.height);
+        assert_eq!(terminal.autoresize().unwrap(), area);
+        assert_eq!(terminal.current_buffer_mut().area, area);
+        assert_eq!(terminal.backend().screen(), "\n\n\n");
+
+        // the screen was cleared, the next draw redraws the unchanged cells as well
+        terminal.current_buffer_mut().set_string(0, 0, "ab", Style::default());
+        terminal.current_buffer_mut().set_string(0, 2, "cd", Style::default());
+        terminal.draw(None, CursorKind::Hidden).unwrap();
+        assert_eq!(terminal.backend().screen(), "ab\n\ncd\n");
     }
 }
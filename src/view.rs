@@ -6,6 +6,7 @@ use std::num::NonZeroUsize;
 pub mod document;
 pub mod editor;
 pub mod graphics;
+pub mod gutter;
 pub mod input;
 pub mod keyboard;
 pub mod theme;
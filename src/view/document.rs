@@ -1,24 +1,35 @@
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use arc_swap::access::DynAccess;
 use std::{
     collections::HashMap,
     fmt::{self, Display},
     path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use crate::core::{
-    doc_formatter::TextFormat, encoding, syntax::LanguageConfiguration, text_annotations::TextAnnotations, Range, Rope,
-    RopeBuilder, Selection,
+    diagnostic::Diagnostic,
+    diff::{self, Hunk},
+    doc_formatter::TextFormat,
+    encoding,
+    line_ending::auto_detect_line_ending,
+    syntax::{self, Highlight, LanguageConfiguration},
+    text_annotations::TextAnnotations,
+    Assoc, LineEnding, Range, Rope, RopeBuilder, Selection, Transaction, DEFAULT_LINE_ENDING,
 };
 
+use crate::lsp::{self, lsp::Url};
+
 use super::{editor::Config, theme::Theme, DocumentId, ViewId};
 
 /// 8kB of buffer space for encoding and decoding Repos.
 const BUF_SIZE: usize = 8192;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub const SCRATCH_BUFFER_NAME: &str = "[scratch]";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Mode {
     Normal = 0,
     Select = 1,
@@ -55,32 +66,119 @@ pub struct Document {
 
     path: Option<PathBuf>,
     encoding: &'static encoding::Encoding,
+    /// Whether the file started with a byte order mark. It is written back on save.
+    has_bom: bool,
+
+    /// Current line ending. Newlines inserted into the document use it.
+    pub line_ending: LineEnding,
+
+    pub language: Option<Arc<LanguageConfiguration>>,
+    /// The language server the document is synchronized with.
+    language_server: Option<Arc<lsp::Client>>,
+
+    /// Incremented every time a transaction is applied.
+    version: i32,
+    /// The version the document had when it was last written to disk.
+    last_saved_version: i32,
+    /// The text last read from or written to disk, changes are shown against it.
+    diff_base: Option<Rope>,
+    /// The hunks of the changes against `diff_base` and the version they were computed for,
+    /// cleared when the diff base changes.
+    diff_hunks: Mutex<Option<(i32, Arc<[Hunk]>)>>,
 
-    language: Option<Arc<LanguageConfiguration>>,
+    /// Diagnostics sorted by position.
+    diagnostics: Vec<Diagnostic>,
 
     pub config: Arc<dyn DynAccess<Config>>,
 }
 
+enum Encoder {
+    Utf16Be,
+    Utf16Le,
+    EncodingRs(encoding::Encoder),
+}
+
+impl Encoder {
+    fn from_encoding(encoding: &'static encoding::Encoding) -> Self {
+        if encoding == encoding::UTF_16BE {
+            Self::Utf16Be
+        } else if encoding == encoding::UTF_16LE {
+            Self::Utf16Le
+        } else {
+            Self::EncodingRs(encoding.new_encoder())
+        }
+    }
+
+    fn encode_from_utf8(&mut self, src: &str, dst: &mut [u8], is_empty: bool) -> (encoding::CoderResult, usize, usize) {
+        // encoding_rs only decodes UTF-16, so encode it by hand.
+        let mut write_to_buf = |convert: fn(u16) -> [u8; 2]| {
+            if src.is_empty() {
+                return (encoding::CoderResult::InputEmpty, 0, 0);
+            }
+            let mut total_written = 0usize;
+
+            for (index, ch) in src.char_indices() {
+                let mut encoded = [0u16; 2];
+                let units = ch.encode_utf16(&mut encoded);
+                let char_size = units.len() * 2;
+
+                if dst.len() < total_written + char_size {
+                    return (encoding::CoderResult::OutputFull, index, total_written);
+                }
+                for unit in units.iter() {
+                    dst[total_written..total_written + 2].copy_from_slice(&convert(*unit));
+                    total_written += 2;
+                }
+            }
+
+            (encoding::CoderResult::InputEmpty, src.len(), total_written)
+        };
+
+        match self {
+            Self::Utf16Be => write_to_buf(u16::to_be_bytes),
+            Self::Utf16Le => write_to_buf(u16::to_le_bytes),
+            Self::EncodingRs(encoder) => {
+                let (result, read, written, ..) = encoder.encode_from_utf8(src, dst, is_empty);
+                (result, read, written)
+            }
+        }
+    }
+}
+
+/// Decodes a stream of bytes into UTF-8, returning a `Rope`, the encoding it was decoded as and
+/// whether a byte order mark was present.
+///
+/// The encoding is auto-detected (BOM first, then `chardetng`) unless one is given. A BOM is only
+/// stripped when it matches the given encoding.
 pub fn from_reader<R: std::io::Read + ?Sized>(
     reader: &mut R,
     encoding: Option<&'static encoding::Encoding>,
-) -> Result<(Rope, &'static encoding::Encoding)> {
+) -> Result<(Rope, &'static encoding::Encoding, bool)> {
     let mut buf = [0u8; BUF_SIZE];
     let mut buf_out = [0u8; BUF_SIZE];
     let mut builder = RopeBuilder::new();
 
-    let (encoding, mut decoder, mut slice, mut is_empty) = {
+    let (encoding, has_bom, mut decoder, mut slice, mut is_empty) = {
         let read = reader.read(&mut buf)?;
         let is_empty = read == 0;
-        let encoding = encoding.unwrap_or_else(|| {
-            let mut encoding_detector = chardetng::EncodingDetector::new();
-            encoding_detector.feed(&buf, is_empty);
-            encoding_detector.guess(None, true)
-        });
-        let decoder = encoding.new_decoder();
+        let bom = encoding::Encoding::for_bom(&buf[..read]).map(|(encoding, _)| encoding);
+        let (encoding, has_bom) = match (encoding, bom) {
+            (Some(encoding), bom) => (encoding, bom == Some(encoding)),
+            (None, Some(bom)) => (bom, true),
+            (None, None) => {
+                let mut encoding_detector = chardetng::EncodingDetector::new();
+                encoding_detector.feed(&buf[..read], is_empty);
+                (encoding_detector.guess(None, true), false)
+            }
+        };
+        let decoder = if has_bom {
+            encoding.new_decoder_with_bom_removal()
+        } else {
+            encoding.new_decoder_without_bom_handling()
+        };
 
         let slice = &buf[..read];
-        (encoding, decoder, slice, is_empty)
+        (encoding, has_bom, decoder, slice, is_empty)
     };
 
     let buf_str = unsafe { std::str::from_utf8_unchecked_mut(&mut buf_out[..]) };
@@ -118,12 +216,103 @@ pub fn from_reader<R: std::io::Read + ?Sized>(
         is_empty = read == 0;
     }
     let rope = builder.finish();
-    Ok((rope, encoding))
+    Ok((rope, encoding, has_bom))
+}
+
+/// Encodes the text inside `rope` into the given `encoding` and writes the
+/// encoded output into `writer.` As a `Rope` can only contain valid UTF-8,
+/// replacement characters may appear in the encoded text.
+/// A byte order mark is written first if `has_bom` is set and the encoding is UTF-8 or UTF-16.
+pub fn to_writer<W: std::io::Write + ?Sized>(
+    writer: &mut W,
+    (encoding, has_bom): (&'static encoding::Encoding, bool),
+    rope: &Rope,
+) -> Result<()> {
+    // Text inside a `Rope` is stored as non-contiguous blocks of data called
+    // chunks. The absolute size of each chunk is unknown, thus it is impossible
+    // to predict the end of the chunk iterator ahead of time. Instead, it is
+    // determined by filtering the iterator to remove all empty chunks and then
+    // appending an empty chunk to it. This is valuable for detecting when all
+    // chunks in the `Rope` have been iterated over in the subsequent loop.
+    let iter = rope.chunks().filter(|c| !c.is_empty()).chain(std::iter::once(""));
+    let mut buf = [0u8; BUF_SIZE];
+    let mut encoder = Encoder::from_encoding(encoding);
+    let mut total_written = 0usize;
+
+    if has_bom {
+        let bom: &[u8] = if encoding == encoding::UTF_8 {
+            &[0xef, 0xbb, 0xbf]
+        } else if encoding == encoding::UTF_16BE {
+            &[0xfe, 0xff]
+        } else if encoding == encoding::UTF_16LE {
+            &[0xff, 0xfe]
+        } else {
+            &[]
+        };
+        buf[..bom.len()].copy_from_slice(bom);
+        total_written += bom.len();
+    }
+
+    for chunk in iter {
+        let is_empty = chunk.is_empty();
+        let mut total_read = 0usize;
+
+        // An inner loop is necessary as it is possible that the input buffer
+        // may not be completely encoded in a single call to the encoder.
+        loop {
+            let (result, read, written, ..) = encoder.encode_from_utf8(&chunk[total_read..], &mut buf[total_written..], is_empty);
+
+            total_read += read;
+            total_written += written;
+            match result {
+                encoding::CoderResult::InputEmpty => {
+                    debug_assert_eq!(chunk.len(), total_read);
+                    debug_assert!(buf.len() >= total_written);
+                    break;
+                }
+                encoding::CoderResult::OutputFull => {
+                    debug_assert!(chunk.len() > total_read);
+                    writer.write_all(&buf[..total_written])?;
+                    total_written = 0;
+                }
+            }
+        }
+
+        // Once the end of the iterator is reached, the output buffer is
+        // flushed and the outer loop terminates.
+        if is_empty {
+            writer.write_all(&buf[..total_written])?;
+            writer.flush()?;
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a transaction replacing `old` with `new`. Only the part between the common prefix and
+/// the common suffix is changed so that positions outside of it are kept.
+fn replace_text(old: &Rope, new: &Rope) -> Transaction {
+    let prefix = old.chars().zip(new.chars()).take_while(|(a, b)| a == b).count();
+    let suffix = old
+        .chars_at(old.len_chars())
+        .reversed()
+        .zip(new.chars_at(new.len_chars()).reversed())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(old.len_chars() - prefix)
+        .min(new.len_chars() - prefix);
+
+    let replacement = new.slice(prefix..new.len_chars() - suffix).to_string();
+    Transaction::change(
+        old,
+        std::iter::once((prefix, old.len_chars() - suffix, Some(replacement.into()))),
+    )
 }
 
 impl Document {
     pub fn from(text: Rope, encoding: Option<&'static encoding::Encoding>, config: Arc<dyn DynAccess<Config>>) -> Self {
         let encoding = encoding.unwrap_or(encoding::UTF_8);
+        let line_ending = auto_detect_line_ending(&text).unwrap_or(DEFAULT_LINE_ENDING);
 
         Self {
             id: DocumentId::default(),
@@ -131,7 +320,15 @@ impl Document {
             selections: HashMap::default(),
             path: None,
             encoding,
+            has_bom: false,
+            line_ending,
             language: None,
+            language_server: None,
+            version: 0,
+            last_saved_version: 0,
+            diff_base: None,
+            diff_hunks: Mutex::new(None),
+            diagnostics: Vec::new(),
             config,
         }
     }
@@ -140,25 +337,208 @@ impl Document {
     pub fn open(
         path: &Path,
         encoding: Option<&'static encoding::Encoding>,
-        // config_laoder: Option<Arc<syntax::Loader>>,
+        config_loader: Option<&syntax::Loader>,
         config: Arc<dyn DynAccess<Config>>,
     ) -> anyhow::Result<Self> {
-        let (rope, encoding) = if path.exists() {
+        let exists = path.exists();
+        let (rope, encoding, has_bom) = if exists {
             let mut file = std::fs::File::open(path).context(format!("unable to open {path:?}"))?;
             from_reader(&mut file, encoding)?
         } else {
-            bail!("open file which does not exists not implemented")
+            // The file is created on first save.
+            let encoding = encoding.unwrap_or(encoding::UTF_8);
+            (Rope::from(DEFAULT_LINE_ENDING.as_str()), encoding, false)
         };
 
         let mut doc = Self::from(rope, Some(encoding), config);
+        doc.has_bom = has_bom;
+        if exists {
+            doc.set_diff_base(doc.text.clone());
+        }
 
         doc.set_path(Some(path))?;
-        // TODO: detect language
-        // TODO: detect indent and line ending
+        if let Some(loader) = config_loader {
+            doc.detect_language(loader);
+        }
+        // TODO: detect indent
 
         Ok(doc)
     }
 
+    /// Creates an empty document without a path.
+    pub fn default(config: Arc<dyn DynAccess<Config>>) -> Self {
+        let text = Rope::from(DEFAULT_LINE_ENDING.as_str());
+        Self::from(text, None, config)
+    }
+
+    /// Writes the document to its path, encoded with the document's encoding.
+    /// Missing parent directories are only created if `force` is set.
+    pub fn save(&mut self, force: bool) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("cannot save a buffer without a filename"))?;
+
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                if force {
+                    std::fs::create_dir_all(parent).context(format!("unable to create {parent:?}"))?;
+                } else {
+                    bail!("can't save file, parent directory does not exist (use :w! to create it)");
+                }
+            }
+        }
+
+        let mut file = std::fs::File::create(path).context(format!("unable to create {path:?}"))?;
+        to_writer(&mut file, self.encoding_with_bom_info(), &self.text)?;
+
+        self.last_saved_version = self.version;
+        self.set_diff_base(self.text.clone());
+
+        if let Some(language_server) = self.language_server() {
+            if let Some(Err(err)) = language_server.text_document_did_save(self.identifier(), &self.text) {
+                tracing::error!("failed to notify the language server of the save: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies a transaction to the document. The selections of every view are mapped through
+    /// the changes, the one of `view_id` is replaced if the transaction carries a selection.
+    pub fn apply(&mut self, transaction: &Transaction, view_id: ViewId) -> bool {
+        let old_text = self.text.clone();
+        let success = transaction.changes().apply(&mut self.text);
+
+        if success {
+            for selection in self.selections.values_mut() {
+                *selection = selection
+                    .clone()
+                    .map(transaction.changes())
+                    .ensure_invariants(self.text.slice(..));
+            }
+
+            if let Some(selection) = transaction.selection() {
+                self.selections
+                    .insert(view_id, selection.clone().ensure_invariants(self.text.slice(..)));
+            }
+
+            if !transaction.changes().is_empty() {
+                self.version += 1;
+
+                // keep the diagnostics on the text they were reported for until new ones arrive
+                let changes = transaction.changes();
+                for diagnostic in &mut self.diagnostics {
+                    diagnostic.range.start = changes.map_pos(diagnostic.range.start, Assoc::After);
+                    diagnostic.range.end = changes.map_pos(diagnostic.range.end, Assoc::After);
+                    diagnostic.line = self.text.char_to_line(diagnostic.range.start);
+                }
+                self.diagnostics.sort_unstable_by_key(|diagnostic| diagnostic.range);
+
+                if let Some(language_server) = self.language_server() {
+                    let notify = language_server.text_document_did_change(
+                        self.versioned_identifier(),
+                        &old_text,
+                        &self.text,
+                        transaction.changes(),
+                    );
+                    if let Some(Err(err)) = notify {
+                        tracing::error!("failed to notify the language server of the changes: {err}");
+                    }
+                }
+            }
+        }
+
+        success
+    }
+
+    /// If there are unsaved modifications.
+    pub fn is_modified(&self) -> bool {
+        self.version != self.last_saved_version
+    }
+
+    /// Re-decodes the document from disk with `encoding`, replacing its text in a single
+    /// transaction so that selections of every view are kept in bounds.
+    pub fn reload(&mut self, view_id: ViewId, encoding: &'static encoding::Encoding) -> Result<()> {
+        let path = self.path().context("scratch buffer cannot be reloaded")?;
+        let reopened = Document::open(path, Some(encoding), None, self.config.clone())?;
+
+        let transaction = replace_text(self.text(), reopened.text());
+        if !self.apply(&transaction, view_id) {
+            bail!("failed to reload document");
+        }
+
+        self.encoding = reopened.encoding;
+        self.has_bom = reopened.has_bom;
+        self.line_ending = reopened.line_ending;
+        self.last_saved_version = self.version;
+        self.set_diff_base(self.text.clone());
+
+        Ok(())
+    }
+
+    fn set_diff_base(&mut self, diff_base: Rope) {
+        self.diff_base = Some(diff_base);
+        *self.diff_hunks.get_mut().unwrap() = None;
+    }
+
+    /// The changes since the document was last read from or written to disk. They are only
+    /// computed again once the document changed.
+    pub fn diff_hunks(&self) -> Arc<[Hunk]> {
+        let Some(base) = &self.diff_base else {
+            return Arc::new([]);
+        };
+        let mut cache = self.diff_hunks.lock().unwrap();
+        match &*cache {
+            Some((version, hunks)) if *version == self.version => hunks.clone(),
+            _ => {
+                let hunks: Arc<[Hunk]> = diff::compare_ropes(base.slice(..), self.text.slice(..)).into();
+                *cache = Some((self.version, hunks.clone()));
+                hunks
+            }
+        }
+    }
+
+    #[inline]
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Replaces the diagnostics of the document, e.g. with the ones published by its language
+    /// server.
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+        self.diagnostics.sort_unstable_by_key(|diagnostic| diagnostic.range);
+    }
+
+    pub fn encoding(&self) -> &'static encoding::Encoding {
+        self.encoding
+    }
+
+    /// Sets the encoding used when the document is written to disk.
+    pub fn set_encoding(&mut self, encoding: &'static encoding::Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    pub fn encoding_with_bom_info(&self) -> (&'static encoding::Encoding, bool) {
+        (self.encoding, self.has_bom)
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    pub fn display_name(&self) -> std::borrow::Cow<'static, str> {
+        self.path
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string().into())
+            .unwrap_or_else(|| SCRATCH_BUFFER_NAME.into())
+    }
+
     pub fn path(&self) -> Option<&PathBuf> {
         self.path.as_ref()
     }
@@ -171,10 +551,45 @@ impl Document {
         Ok(())
     }
 
+    /// Sets the language matching the path of the document, if any.
+    pub fn detect_language(&mut self, config_loader: &syntax::Loader) {
+        self.language = self
+            .path
+            .as_ref()
+            .and_then(|path| config_loader.language_config_for_file_name(path));
+    }
+
     pub fn language_config(&self) -> Option<&LanguageConfiguration> {
         self.language.as_deref()
     }
 
+    /// The name of the language of the document, e.g. `rust`.
+    pub fn language_id(&self) -> Option<&str> {
+        self.language_config().map(|config| config.language_id.as_str())
+    }
+
+    /// The language server of the document, once it is initialized.
+    pub fn language_server(&self) -> Option<&lsp::Client> {
+        self.language_server.as_deref().filter(|server| server.is_initialized())
+    }
+
+    pub fn set_language_server(&mut self, language_server: Option<Arc<lsp::Client>>) {
+        self.language_server = language_server;
+    }
+
+    /// The URL of the document, `None` for a scratch buffer.
+    pub fn url(&self) -> Option<Url> {
+        Url::from_file_path(self.path()?).ok()
+    }
+
+    pub fn identifier(&self) -> lsp::lsp::TextDocumentIdentifier {
+        lsp::lsp::TextDocumentIdentifier::new(self.url().unwrap())
+    }
+
+    pub fn versioned_identifier(&self) -> lsp::lsp::VersionedTextDocumentIdentifier {
+        lsp::lsp::VersionedTextDocumentIdentifier::new(self.url().unwrap(), self.version)
+    }
+
     pub fn text(&self) -> &Rope {
         &self.text
     }
@@ -189,6 +604,11 @@ impl Document {
         &self.selections[&view_id]
     }
 
+    /// The selections of the views showing the document.
+    pub fn selections(&self) -> &HashMap<ViewId, Selection> {
+        &self.selections
+    }
+
     pub fn set_selection(&mut self, view_id: ViewId, selection: Selection) {
         self.selections
             .insert(view_id, selection.ensure_invariants(self.text().slice(..)));
@@ -212,25 +632,37 @@ impl Document {
 
     /// Initializes a new selection for the given view if it does not already have one.
     pub fn ensure_view_init(&mut self, view_id: ViewId) {
-        if self.selections.get(&view_id).is_none() {
+        if !self.selections.contains_key(&view_id) {
             self.reset_selection(view_id);
         }
     }
 
-    pub fn text_format(&self, mut viewport_width: u16, theme: Option<&Theme>) -> TextFormat {
+    /// Remove a view's selection from this document.
+    pub fn remove_view(&mut self, view_id: ViewId) {
+        self.selections.remove(&view_id);
+    }
+
+    pub fn text_format(&self, viewport_width: u16, theme: Option<&Theme>) -> TextFormat {
         // TODO: handle language config
 
         let config = self.config.load();
-        // let soft_wrap = &config.soft_wrap;
+        let soft_wrap = &config.soft_wrap;
+        let enable_soft_wrap = soft_wrap.enable.unwrap_or(false);
+        let max_wrap = soft_wrap.max_wrap.unwrap_or(20);
+        let max_indent_retain = soft_wrap.max_indent_retain.unwrap_or(40);
+        let wrap_indicator = soft_wrap.wrap_indicator.clone().unwrap_or_else(|| "↪ ".into());
         let tab_width = self.tab_width() as u16;
 
         TextFormat {
-            soft_wrap: false,
+            // avoid spinning forever when the view is tiny
+            soft_wrap: enable_soft_wrap && viewport_width > 10,
             tab_width,
-            max_wrap: viewport_width / 4,
-            max_indent_retain: viewport_width * 2 / 5,
-            wrap_indicator: "".to_owned().into_boxed_str(),
-            wrap_indicator_highlight: None,
+            max_wrap: max_wrap.min(viewport_width / 4),
+            max_indent_retain: max_indent_retain.min(viewport_width * 2 / 5),
+            wrap_indicator: wrap_indicator.into_boxed_str(),
+            wrap_indicator_highlight: theme
+                .and_then(|theme| theme.find_scope_index("ui.virtual.wrap"))
+                .map(Highlight),
             viewport_width,
         }
     }
@@ -239,3 +671,104 @@ impl Document {
         TextAnnotations::default()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip(bytes: &[u8], encoding: Option<&'static encoding::Encoding>) -> (Rope, &'static encoding::Encoding, bool) {
+        let (rope, encoding, has_bom) = from_reader(&mut &bytes[..], encoding).unwrap();
+        let mut written = Vec::new();
+        to_writer(&mut written, (encoding, has_bom), &rope).unwrap();
+        assert_eq!(written, bytes);
+        (rope, encoding, has_bom)
+    }
+
+    #[test]
+    fn bom_is_detected_and_preserved() {
+        let (rope, encoding, has_bom) = roundtrip(b"\xef\xbb\xbfhello\n", None);
+        assert_eq!(
+            (rope.to_string().as_str(), encoding, has_bom),
+            ("hello\n", encoding::UTF_8, true)
+        );
+
+        let (rope, encoding, has_bom) = roundtrip(b"\xff\xfeh\x00i\x00", None);
+        assert_eq!(
+            (rope.to_string().as_str(), encoding, has_bom),
+            ("hi", encoding::UTF_16LE, true)
+        );
+
+        let (rope, encoding, has_bom) = roundtrip(b"\xfe\xff\x00h\x00i", None);
+        assert_eq!(
+            (rope.to_string().as_str(), encoding, has_bom),
+            ("hi", encoding::UTF_16BE, true)
+        );
+    }
+
+    #[test]
+    fn explicit_encoding_overrides_detection() {
+        let (rope, encoding, has_bom) = roundtrip(b"caf\xe9", Some(encoding::WINDOWS_1252));
+        assert_eq!(
+            (rope.to_string().as_str(), encoding, has_bom),
+            ("café", encoding::WINDOWS_1252, false)
+        );
+
+        let (rope, _, _) = roundtrip(b"\x82\xa0", Some(encoding::SHIFT_JIS));
+        assert_eq!(rope.to_string(), "あ");
+    }
+
+    #[test]
+    fn diagnostics_follow_changes() {
+        use crate::core::diagnostic::{self, Severity};
+        use arc_swap::ArcSwap;
+
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("let x = y;\nlet z;\n"), None, config);
+        let view_id = ViewId::default();
+        doc.ensure_view_init(view_id);
+        let diagnostic = |start, end, line, severity| Diagnostic {
+            range: diagnostic::Range { start, end },
+            line,
+            message: String::new(),
+            severity: Some(severity),
+        };
+        doc.set_diagnostics(vec![
+            diagnostic(15, 16, 1, Severity::Warning),
+            diagnostic(8, 9, 0, Severity::Error),
+        ]);
+
+        // a line is inserted above the first diagnostic
+        let transaction = Transaction::change(doc.text(), [(0, 0, Some("fn f() {}\n".into()))].into_iter());
+        assert!(doc.apply(&transaction, view_id));
+        let ranges: Vec<_> = doc
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start, diagnostic.range.end, diagnostic.line))
+            .collect();
+        assert_eq!(ranges, vec![(18, 19, 1), (25, 26, 2)]);
+    }
+
+    #[test]
+    fn diff_hunks_are_cached_per_version() {
+        use arc_swap::ArcSwap;
+
+        let config = Arc::new(ArcSwap::from_pointee(Config::default()));
+        let mut doc = Document::from(Rope::from("a\nb\n"), None, config);
+        let view_id = ViewId::default();
+        doc.ensure_view_init(view_id);
+        doc.set_diff_base(doc.text.clone());
+
+        let transaction = Transaction::change(doc.text(), [(2, 3, Some("c".into()))].into_iter());
+        assert!(doc.apply(&transaction, view_id));
+        let hunks = doc.diff_hunks();
+        assert_eq!(hunks.len(), 1);
+        assert!(Arc::ptr_eq(&hunks, &doc.diff_hunks()));
+
+        // a new diff base and further changes compute the hunks again
+        doc.set_diff_base(doc.text.clone());
+        assert!(doc.diff_hunks().is_empty());
+        let transaction = Transaction::change(doc.text(), [(0, 0, Some("z\n".into()))].into_iter());
+        assert!(doc.apply(&transaction, view_id));
+        assert_eq!(doc.diff_hunks().len(), 1);
+    }
+}
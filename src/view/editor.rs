@@ -1,15 +1,29 @@
 use arc_swap::access::{DynAccess, DynGuard};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, num::NonZeroUsize, path::Path, pin::Pin, sync::Arc};
-use tokio::time::{sleep, Duration, Instant, Sleep};
-
-use crate::view::{
-    document::{Document, Mode},
-    graphics::{CursorKind, Rect},
-    theme::{Theme, DEFAULT_THEME},
-    tree::{Layout, Tree},
-    view::View,
-    DocumentId,
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    num::NonZeroUsize,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+};
+use tokio::{
+    sync::Notify,
+    time::{sleep, sleep_until, Duration, Instant, Sleep},
+};
+
+use crate::{
+    core::{diagnostic::Severity, encoding, syntax, Position},
+    lsp,
+    view::{
+        document::{Document, Mode},
+        graphics::{CursorKind, Rect},
+        theme::{self, Theme},
+        tree::{self, Layout, Tree},
+        view::{View, ViewPosition},
+        DocumentId, ViewId,
+    },
 };
 
 // Cursor shape is read and used on every rendered frame and so needs
@@ -23,6 +37,21 @@ impl CursorShapeConfig {
     }
 }
 
+impl<'de> Deserialize<'de> for CursorShapeConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let m = HashMap::<Mode, CursorKind>::deserialize(deserializer)?;
+        let into_cursor = |mode: Mode| m.get(&mode).copied().unwrap_or_default();
+        Ok(CursorShapeConfig([
+            into_cursor(Mode::Normal),
+            into_cursor(Mode::Select),
+            into_cursor(Mode::Insert),
+        ]))
+    }
+}
+
 impl std::ops::Deref for CursorShapeConfig {
     type Target = [CursorKind; 3];
 
@@ -37,34 +66,320 @@ impl Default for CursorShapeConfig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-// #[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
 pub struct Config {
     /// Padding to keep between the edge of the screen and the cursor when scrolling. Defaults to 5.
     pub scrolloff: usize,
-    // #[serde(default)]
+    /// Number of lines to scroll at once. Defaults to 3.
+    pub scroll_lines: isize,
+    /// Mouse support. Defaults to true.
+    pub mouse: bool,
+    /// Which files the file picker lists.
+    pub file_picker: FilePickerConfig,
     pub whitespace: WhitespaceConfig,
+    /// Vertical guides drawn at each indentation level.
+    pub indent_guides: IndentGuidesConfig,
     /// Shape for cursor in each mode
     pub cursor_shape: CursorShapeConfig,
     /// Time in milliseconds since last keypress before idle timers trigger.
     /// used for autocompletion, set to 0 for instant.
+    #[serde(deserialize_with = "deserialize_duration_millis")]
     pub idle_timeout: Duration,
+    /// Time in milliseconds after which a status or error message is cleared, even without a
+    /// keypress. Defaults to 5 seconds.
+    #[serde(deserialize_with = "deserialize_duration_millis")]
+    pub status_timeout: Duration,
+    /// Whether completions are requested automatically once `idle_timeout` elapses in insert
+    /// mode. Defaults to true.
+    pub auto_completion: bool,
+    /// Number of word characters that must precede the cursor for automatic completion.
+    /// Defaults to 2.
+    pub completion_trigger_len: u8,
     pub lsp: LspConfig,
+    /// Gutters shown left of the text, in order. Defaults to
+    /// `["diagnostics", "spacer", "line-numbers", "spacer", "diff"]`.
+    pub gutters: Vec<GutterType>,
+    /// Line number mode.
+    pub line_number: LineNumber,
+    /// Highlight the lines the cursors are on. Defaults to false.
+    pub cursorline: bool,
+    /// Highlight the columns the cursors are on. Defaults to false.
+    pub cursorcolumn: bool,
+    /// Columns to draw a vertical ruler at, e.g. `[80, 100]`. Defaults to none.
+    pub rulers: Vec<u16>,
+    /// Wrapping of the lines that don't fit the view.
+    pub soft_wrap: SoftWrap,
+    /// Whether to show a row listing the open documents above the views. Defaults to `never`.
+    pub bufferline: BufferLine,
+    /// Elements shown in the statusline of each view.
+    pub statusline: StatusLineConfig,
+    /// Set to `true` to override automatic detection of terminal truecolor support in the event
+    /// of a false negative. Defaults to `false`.
+    pub true_color: bool,
+    /// Set to `true` to override automatic detection of terminal undercurl support in the event
+    /// of a false negative. Defaults to `false`.
+    pub undercurl: bool,
+}
+
+fn deserialize_duration_millis<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let millis = u64::deserialize(deserializer)?;
+    Ok(Duration::from_millis(millis))
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             scrolloff: 5,
+            scroll_lines: 3,
+            mouse: true,
+            file_picker: FilePickerConfig::default(),
             whitespace: WhitespaceConfig::default(),
+            indent_guides: IndentGuidesConfig::default(),
             cursor_shape: CursorShapeConfig::default(),
             idle_timeout: Duration::from_millis(400),
+            status_timeout: Duration::from_secs(5),
+            auto_completion: true,
+            completion_trigger_len: 2,
             lsp: LspConfig::default(),
+            gutters: vec![
+                GutterType::Diagnostics,
+                GutterType::Spacer,
+                GutterType::LineNumbers,
+                GutterType::Spacer,
+                GutterType::Diff,
+            ],
+            line_number: LineNumber::Absolute,
+            cursorline: false,
+            cursorcolumn: false,
+            rulers: Vec::new(),
+            soft_wrap: SoftWrap::default(),
+            bufferline: BufferLine::default(),
+            statusline: StatusLineConfig::default(),
+            true_color: false,
+            undercurl: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct FilePickerConfig {
+    /// Whether to hide hidden files, whose name starts with a dot. Defaults to true.
+    pub hidden: bool,
+    /// Whether to follow symbolic links. Defaults to true.
+    pub follow_symlinks: bool,
+    /// Whether to read the ignore files of the parent directories. Defaults to true.
+    pub parents: bool,
+    /// Whether to honour `.ignore` files. Defaults to true.
+    pub ignore: bool,
+    /// Whether to honour `.gitignore` files. Defaults to true.
+    pub git_ignore: bool,
+    /// Whether to honour the `.git/info/exclude` file. Defaults to true.
+    pub git_exclude: bool,
+    /// Maximum depth of the directories to list. Defaults to `None`.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for FilePickerConfig {
+    fn default() -> Self {
+        Self {
+            hidden: true,
+            follow_symlinks: true,
+            parents: true,
+            ignore: true,
+            git_ignore: true,
+            git_exclude: true,
+            max_depth: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct SoftWrap {
+    /// Soft wrap lines that exceed the width of the view. Defaults to false.
+    pub enable: Option<bool>,
+    /// Maximum space left free at the end of a wrapped line. Defaults to 20.
+    pub max_wrap: Option<u16>,
+    /// Maximum indentation carried over from the previous line when wrapping. Defaults to 40.
+    pub max_indent_retain: Option<u16>,
+    /// Indicator placed at the start of wrapped lines. Defaults to `"↪ "`.
+    pub wrap_indicator: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct StatusLineConfig {
+    pub left: Vec<StatusLineElement>,
+    pub center: Vec<StatusLineElement>,
+    pub right: Vec<StatusLineElement>,
+    pub separator: String,
+    pub mode: ModeConfig,
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        use StatusLineElement as E;
+
+        Self {
+            left: vec![E::Mode, E::FileName, E::FileModificationIndicator],
+            center: vec![],
+            right: vec![
+                E::Diagnostics,
+                E::Selections,
+                E::Position,
+                E::FileEncoding,
+                E::FileLineEnding,
+            ],
+            separator: String::from("│"),
+            mode: ModeConfig::default(),
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct ModeConfig {
+    pub normal: String,
+    pub insert: String,
+    pub select: String,
+}
+
+impl Default for ModeConfig {
+    fn default() -> Self {
+        Self {
+            normal: String::from("NOR"),
+            insert: String::from("INS"),
+            select: String::from("SEL"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatusLineElement {
+    /// The editor mode (Normal, Insert, Select)
+    Mode,
+
+    /// The base file name
+    FileBaseName,
+
+    /// The relative file path to the workspace, or the scratch buffer name
+    FileName,
+
+    /// An indicator that shows `"[+]"` when a file is modified
+    FileModificationIndicator,
+
+    /// The file encoding
+    FileEncoding,
+
+    /// The file line endings (CRLF or LF)
+    FileLineEnding,
+
+    /// The file type (language ID or "text")
+    FileType,
+
+    /// A summary of the number of errors and warnings
+    Diagnostics,
+
+    /// The number of selections (cursors)
+    Selections,
+
+    /// The cursor position
+    Position,
+
+    /// The separator string
+    Separator,
+
+    /// The total line numbers of the current file
+    TotalLineNumbers,
+
+    /// A single space
+    Spacer,
+}
+
+/// Bufferline render modes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BufferLine {
+    /// Don't render bufferline
+    #[default]
+    Never,
+    /// Always render
+    Always,
+    /// Only if multiple buffers are open
+    Multiple,
+}
+
+impl std::str::FromStr for BufferLine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(Self::Never),
+            "always" => Ok(Self::Always),
+            "multiple" => Ok(Self::Multiple),
+            _ => anyhow::bail!("Bufferline can only be `never`, `always` or `multiple`."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineNumber {
+    /// Show absolute line number
+    Absolute,
+
+    /// If focused and in normal/select mode, show relative line number to the primary cursor.
+    /// If unfocused or in insert mode, show absolute line number.
+    Relative,
+}
+
+impl std::str::FromStr for LineNumber {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "absolute" | "abs" => Ok(Self::Absolute),
+            "relative" | "rel" => Ok(Self::Relative),
+            _ => anyhow::bail!("Line number can only be `absolute` or `relative`."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GutterType {
+    /// Show diagnostics
+    Diagnostics,
+    /// Show line numbers
+    LineNumbers,
+    /// Show one blank space
+    Spacer,
+    /// Highlight local changes
+    Diff,
+}
+
+impl std::str::FromStr for GutterType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "diagnostics" => Ok(Self::Diagnostics),
+            "spacer" => Ok(Self::Spacer),
+            "line-numbers" => Ok(Self::LineNumbers),
+            "diff" => Ok(Self::Diff),
+            _ => anyhow::bail!("Gutter type can only be `diagnostics`, `spacer`, `line-numbers` or `diff`."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
 pub struct LspConfig {
     pub enable: bool,
     pub display_message: bool,
@@ -96,16 +411,50 @@ pub struct Editor {
     pub config: Arc<dyn DynAccess<Config>>,
     pub exit_code: i32,
     pub theme: Theme,
+    /// The theme that was active before a theme was previewed, restored if the preview is
+    /// cancelled.
+    pub last_theme: Option<Theme>,
+    pub theme_loader: Arc<theme::Loader>,
+
+    pub syn_loader: Arc<syntax::Loader>,
+    pub language_servers: lsp::Registry,
+    /// The diagnostics published by the language servers with the id of their server, including
+    /// the ones of files that aren't open. Sorted by severity, then position.
+    pub diagnostics: BTreeMap<lsp::lsp::Url, Vec<(lsp::lsp::Diagnostic, usize)>>,
+
+    /// Message shown in the command line until the next keypress or the status timeout.
+    pub status_msg: Option<(Cow<'static, str>, Severity)>,
+    /// The last [`MAX_MESSAGES`] messages set during this session, oldest first.
+    pub messages: VecDeque<(Cow<'static, str>, Severity)>,
 
     pub idle_timer: Pin<Box<Sleep>>,
+    /// Fires once the status message has been shown for `status_timeout`.
+    pub status_timer: Pin<Box<Sleep>>,
+
+    /// Notified by background tasks, e.g. the file picker's walker, to request a redraw.
+    pub redraw_handle: Arc<Notify>,
+}
+
+/// Number of messages kept in [`Editor::messages`], older ones are dropped.
+pub const MAX_MESSAGES: usize = 1000;
+
+/// There is no way to disable a sleep, timers are pushed far into the future instead.
+fn far_future() -> Instant {
+    Instant::now() + Duration::from_secs(86400 * 365 * 30)
 }
 
 impl Editor {
-    pub fn new(mut area: Rect, config: Arc<dyn DynAccess<Config>>) -> Self {
+    pub fn new(
+        mut area: Rect,
+        theme_loader: Arc<theme::Loader>,
+        syn_loader: Arc<syntax::Loader>,
+        config: Arc<dyn DynAccess<Config>>,
+    ) -> Self {
         let conf = config.load();
 
-        // TODO: load from loader;
-        let theme = DEFAULT_THEME.clone();
+        let theme = theme_loader.default();
+        // offset the render area height by 1 to account for the command line
+        area.height = area.height.saturating_sub(1);
         let tree = Tree::new(area);
         Self {
             mode: Mode::Normal,
@@ -116,7 +465,16 @@ impl Editor {
             config,
             exit_code: 0,
             theme,
+            last_theme: None,
+            theme_loader,
+            syn_loader,
+            language_servers: lsp::Registry::new(),
+            diagnostics: BTreeMap::new(),
+            status_msg: None,
+            messages: VecDeque::new(),
             idle_timer: Box::pin(sleep(conf.idle_timeout)),
+            status_timer: Box::pin(sleep_until(far_future())),
+            redraw_handle: Arc::new(Notify::new()),
         }
     }
 
@@ -124,31 +482,136 @@ impl Editor {
         self.mode
     }
 
+    /// Switches to `theme`, dropping the theme saved by a preview.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.last_theme = None;
+        self.theme = theme;
+    }
+
+    /// Shows `theme` until [`Editor::set_theme`] or [`Editor::unset_theme_preview`] is called.
+    pub fn set_theme_preview(&mut self, theme: Theme) {
+        let previous = std::mem::replace(&mut self.theme, theme);
+        self.last_theme.get_or_insert(previous);
+    }
+
+    /// Restores the theme that was active before the preview started.
+    pub fn unset_theme_preview(&mut self) {
+        if let Some(theme) = self.last_theme.take() {
+            self.theme = theme;
+        }
+    }
+
+    pub fn clear_status(&mut self) {
+        self.status_msg = None;
+    }
+
+    #[inline]
+    pub fn set_status<T: Into<Cow<'static, str>>>(&mut self, status: T) {
+        let status = status.into();
+        tracing::debug!("editor status: {}", status);
+        self.set_message(status, Severity::Info);
+    }
+
+    #[inline]
+    pub fn set_error<T: Into<Cow<'static, str>>>(&mut self, error: T) {
+        let error = error.into();
+        tracing::error!("editor error: {}", error);
+        self.set_message(error, Severity::Error);
+    }
+
+    fn set_message(&mut self, message: Cow<'static, str>, severity: Severity) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((message.clone(), severity));
+        self.status_msg = Some((message, severity));
+        let timeout = self.config().status_timeout;
+        self.status_timer.as_mut().reset(Instant::now() + timeout);
+    }
+
+    /// Clears the status message once the status timer fired.
+    pub fn handle_status_timeout(&mut self) {
+        // the timer fires once, until the next message resets it
+        self.status_timer.as_mut().reset(far_future());
+        self.clear_status();
+    }
+
+    #[inline]
+    pub fn get_status(&self) -> Option<(&Cow<'static, str>, &Severity)> {
+        self.status_msg.as_ref().map(|(status, sev)| (status, sev))
+    }
+
+    /// The screen position of the primary cursor of the focused view and the cursor shape of the
+    /// current mode.
+    pub fn cursor(&self) -> (Option<Position>, CursorKind) {
+        let config = self.config();
+        let view = view!(self);
+        let doc = doc!(self, &view.doc);
+        let cursor = doc.selection(view.id).primary().cursor(doc.text().slice(..));
+        if let Some(mut pos) = view.screen_coords_at_pos(doc, doc.text().slice(..), cursor) {
+            let inner = view.inner_area(doc);
+            pos.col += inner.x as usize;
+            pos.row += inner.y as usize;
+            let cursor_kind = config.cursor_shape.from_mode(self.mode);
+            (Some(pos), cursor_kind)
+        } else {
+            (None, CursorKind::default())
+        }
+    }
+
+    /// Resizes the area available to views, e.g. after the terminal was resized.
+    pub fn resize(&mut self, area: Rect) {
+        if self.tree.set_area(area) {
+            // views keep their offsets, only scroll the ones whose cursor is no longer visible
+            let scrolloff = self.config().scrolloff;
+            for (view, _) in self.tree.views_mut() {
+                let doc = &self.documents[&view.doc];
+                view.ensure_cursor_in_view(doc, scrolloff);
+            }
+        }
+    }
+
+    pub fn ensure_cursor_in_view(&mut self, id: ViewId) {
+        let config = self.config();
+        let view = self.tree.get_mut(id);
+        let doc = &self.documents[&view.doc];
+        view.ensure_cursor_in_view(doc, config.scrolloff)
+    }
+
     pub fn reset_idle_timer(&mut self) {
         let config = self.config();
         self.idle_timer.as_mut().reset(Instant::now() + config.idle_timeout);
     }
 
+    /// Stops the idle timer until the next [`reset_idle_timer`](Self::reset_idle_timer).
+    pub fn clear_idle_timer(&mut self) {
+        self.idle_timer.as_mut().reset(far_future());
+    }
+
     pub fn open(&mut self, path: &Path, action: Action) -> anyhow::Result<DocumentId> {
+        self.open_with_encoding(path, action, None)
+    }
+
+    /// Opens `path` decoding it with `encoding`, or an auto-detected encoding if `None`.
+    /// If the document is already open, it is switched to as is.
+    pub fn open_with_encoding(
+        &mut self,
+        path: &Path,
+        action: Action,
+        encoding: Option<&'static encoding::Encoding>,
+    ) -> anyhow::Result<DocumentId> {
         let path = crate::core::path::get_canonicalized_path(path)?;
         let id = self.document_by_path(&path).map(|doc| doc.id);
 
         let id = if let Some(id) = id {
             id
         } else {
-            let mut doc = Document::open(
-                &path,
-                None,
-                // Some(self.syn_loader.clone())
-                self.config.clone(),
-            )?;
+            let doc = Document::open(&path, encoding, Some(&self.syn_loader), self.config.clone())?;
 
             // TODO: handle diff
 
             let id = self.new_document(doc);
-
-            // TODO: launch_language_server
-            // let _ = self.launch_language_server(id);
+            let _ = self.launch_language_server(id);
 
             id
         };
@@ -157,6 +620,18 @@ impl Editor {
         Ok(id)
     }
 
+    /// Creates a new scratch buffer without a path.
+    pub fn new_file(&mut self, action: Action) -> DocumentId {
+        self.new_file_from_document(action, Document::default(self.config.clone()))
+    }
+
+    /// Registers `doc` as a new document and shows it according to `action`.
+    pub fn new_file_from_document(&mut self, action: Action, doc: Document) -> DocumentId {
+        let id = self.new_document(doc);
+        self.switch(id, action);
+        id
+    }
+
     #[inline]
     pub fn document(&self, id: DocumentId) -> Option<&Document> {
         self.documents.get(&id)
@@ -166,11 +641,20 @@ impl Editor {
         self.documents.values()
     }
 
+    pub fn documents_mut(&mut self) -> impl Iterator<Item = &mut Document> {
+        self.documents.values_mut()
+    }
+
     pub fn document_by_path<P: AsRef<Path>>(&self, path: P) -> Option<&Document> {
         self.documents()
             .find(|doc| doc.path().map(|p| p == path.as_ref()).unwrap_or(false))
     }
 
+    pub fn document_by_path_mut<P: AsRef<Path>>(&mut self, path: P) -> Option<&mut Document> {
+        self.documents_mut()
+            .find(|doc| doc.path().map(|p| p == path.as_ref()).unwrap_or(false))
+    }
+
     /// Generate an id for a new document and register it.
     fn new_document(&mut self, mut doc: Document) -> DocumentId {
         let id = self.next_document_id;
@@ -220,29 +704,209 @@ impl Editor {
                 let view_id = view!(self).id;
                 let doc = doc_mut!(self, &id);
                 doc.ensure_view_init(view_id);
-                return;
             }
-            action => tracing::error!("action: {action:?} not implemented yet"),
+            Action::Replace => {
+                let view = view_mut!(self);
+                let previous = view.doc;
+                let view_id = view.id;
+                if previous != id {
+                    view.add_to_history(previous);
+                }
+
+                self.replace_document_in_view(view_id, id);
+
+                // An unmodified scratch buffer that is not shown anywhere else anymore is dropped.
+                let doc = doc!(self, &previous);
+                let remove_empty_scratch = previous != id
+                    && !doc.is_modified()
+                    && doc.path().is_none()
+                    && !self.tree.views().any(|(view, _)| view.doc == previous);
+                if remove_empty_scratch {
+                    // Cannot fail: the document exists and is forced closed.
+                    let _ = self.close_document(previous, true);
+                }
+            }
         }
 
         // self_refresh();
     }
 
+    fn replace_document_in_view(&mut self, view_id: ViewId, doc_id: DocumentId) {
+        let view = view_mut!(self, view_id);
+        view.doc = doc_id;
+        view.offset = ViewPosition::default();
+
+        let doc = doc_mut!(self, &doc_id);
+        doc.ensure_view_init(view_id);
+    }
+
+    pub fn focus(&mut self, view_id: ViewId) {
+        self.tree.focus = view_id;
+    }
+
+    pub fn focus_direction(&mut self, direction: tree::Direction) {
+        let current_view = self.tree.focus;
+        if let Some(id) = self.tree.find_split_in_direction(current_view, direction) {
+            self.focus(id)
+        }
+    }
+
+    pub fn swap_split_in_direction(&mut self, direction: tree::Direction) {
+        self.tree.swap_split_in_direction(direction);
+    }
+
+    pub fn transpose_view(&mut self) {
+        self.tree.transpose();
+    }
+
+    /// The editor exits once its last view has been closed.
+    pub fn should_close(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Closes a view, dropping its selections in every document.
+    pub fn close(&mut self, id: ViewId) {
+        for doc in self.documents.values_mut() {
+            doc.remove_view(id);
+        }
+        self.tree.remove(id);
+    }
+
+    /// Closes a document. Views showing it go back to the document they showed previously, or are
+    /// closed if there is none. A new view is created if this closed the last one.
+    pub fn close_document(&mut self, doc_id: DocumentId, force: bool) -> Result<(), CloseError> {
+        let doc = self.documents.get(&doc_id).ok_or(CloseError::DoesNotExist)?;
+        if !force && doc.is_modified() {
+            return Err(CloseError::BufferModified(doc.display_name().into_owned()));
+        }
+
+        if let Some(language_server) = doc.language_server() {
+            if let Err(err) = language_server.text_document_did_close(doc.identifier()) {
+                tracing::error!("failed to notify the language server of the close: {err}");
+            }
+        }
+
+        enum Action {
+            Close(ViewId),
+            ReplaceDoc(ViewId, DocumentId),
+        }
+
+        let actions: Vec<Action> = self
+            .tree
+            .views_mut()
+            .filter_map(|(view, _focus)| {
+                view.remove_document(&doc_id);
+
+                if view.doc != doc_id {
+                    return None;
+                }
+                match view.docs_access_history.pop() {
+                    Some(prev_doc) => Some(Action::ReplaceDoc(view.id, prev_doc)),
+                    None => Some(Action::Close(view.id)),
+                }
+            })
+            .collect();
+
+        for action in actions {
+            match action {
+                Action::Close(view_id) => self.close(view_id),
+                Action::ReplaceDoc(view_id, doc_id) => self.replace_document_in_view(view_id, doc_id),
+            }
+        }
+
+        self.documents.remove(&doc_id);
+
+        // Closing a buffer never closes the editor, so if it was shown in all views we need a new
+        // one with either an existing document or a new scratch buffer.
+        if self.tree.views().next().is_none() {
+            let doc_id = match self.documents.keys().next() {
+                Some(&doc_id) => doc_id,
+                None => self.new_document(Document::default(self.config.clone())),
+            };
+            let view_id = self.tree.split(View::new(doc_id), Layout::Vertical);
+            doc_mut!(self, &doc_id).ensure_view_init(view_id);
+        }
+
+        Ok(())
+    }
+
+    /// Starts the language server of the document `doc_id` if needed and opens the document in
+    /// it. Documents opened before the server is initialized are opened once it is.
     fn launch_language_server(&mut self, doc_id: DocumentId) -> Option<()> {
         if !self.config().lsp.enable {
             return None;
         };
 
+        let doc = self.documents.get_mut(&doc_id)?;
         // if doc doesn't have a URL it's a scratch buffer, ignore it
-        let (lang, path) = {
-            let doc = self.document(doc_id)?;
-            (doc.language.clone(), doc.path().cloned())
+        let doc_url = doc.url()?;
+        let language = doc.language.clone()?;
+
+        let language_server = match self.language_servers.get(&language, doc.path().map(|path| path.as_path())) {
+            Ok(language_server) => language_server?,
+            Err(err) => {
+                tracing::error!("Failed to initialize the LSP for `{}` {{ {err} }}", language.language_id);
+                return None;
+            }
         };
 
-        None
+        // only open the document if the server changed
+        if doc.language_server().map(|server| server.id()) == Some(language_server.id()) {
+            return Some(());
+        }
+        if let Some(previous) = doc.language_server() {
+            if let Err(err) = previous.text_document_did_close(doc.identifier()) {
+                tracing::error!("failed to notify the language server of the close: {err}");
+            }
+        }
+        if language_server.is_initialized() {
+            let language_id = doc.language_id().map(ToOwned::to_owned).unwrap_or_default();
+            if let Err(err) = language_server.text_document_did_open(doc_url, doc.version(), doc.text(), language_id) {
+                tracing::error!("failed to open the document in the language server: {err}");
+            }
+        }
+        doc.set_language_server(Some(language_server));
+
+        Some(())
+    }
+
+    /// Starts the language server matching the language of the document `doc_id`, e.g. after it
+    /// was written to a new path.
+    pub fn refresh_language_server(&mut self, doc_id: DocumentId) -> bool {
+        self.launch_language_server(doc_id).is_some()
+    }
+
+    /// Shuts down the language servers, giving them `timeout` (a second by default) to exit.
+    pub async fn close_language_servers(&self, timeout: Option<u64>) -> Result<(), tokio::time::error::Elapsed> {
+        let shutdowns = self.language_servers.iter_clients().map(|client| client.force_shutdown());
+        tokio::time::timeout(
+            Duration::from_millis(timeout.unwrap_or(1000)),
+            futures_util::future::join_all(shutdowns),
+        )
+        .await
+        .map(|_| ())
     }
 }
 
+#[derive(Debug)]
+pub enum CloseError {
+    /// Document doesn't exist
+    DoesNotExist,
+    /// Buffer is modified
+    BufferModified(String),
+}
+
+impl std::fmt::Display for CloseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DoesNotExist => f.write_str("document does not exist"),
+            Self::BufferModified(name) => write!(f, "buffer {name:?} is modified"),
+        }
+    }
+}
+
+impl std::error::Error for CloseError {}
+
 #[derive(Debug, Copy, Clone)]
 pub enum Action {
     Load,
@@ -252,6 +916,7 @@ pub enum Action {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WhitespaceConfig {
     pub render: WhitespaceRender,
     pub characters: WhitespaceCharacters,
@@ -267,6 +932,7 @@ impl Default for WhitespaceConfig {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged, rename_all = "kebab-case")]
 pub enum WhitespaceRender {
     Basic(WhitespaceRenderValue),
     Specific {
@@ -282,8 +948,8 @@ pub enum WhitespaceRender {
 #[serde(rename_all = "kebab-case")]
 pub enum WhitespaceRenderValue {
     None,
-    // TODO
-    // Selection,
+    /// Only render whitespace inside selections.
+    Selection,
     All,
 }
 
@@ -314,6 +980,25 @@ impl WhitespaceRender {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct IndentGuidesConfig {
+    pub render: bool,
+    pub character: char,
+    /// Number of indent levels to skip before drawing guides.
+    pub skip_levels: u8,
+}
+
+impl Default for IndentGuidesConfig {
+    fn default() -> Self {
+        Self {
+            render: false,
+            character: '│',
+            skip_levels: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct WhitespaceCharacters {
@@ -335,3 +1020,125 @@ impl Default for WhitespaceCharacters {
         }
     }
 }
+
+#[cfg(test)]
+impl Editor {
+    /// An editor of size `area` without any theme nor language, for the unit tests.
+    pub(crate) fn for_test(config: Config, area: Rect) -> Self {
+        let config: Arc<dyn DynAccess<Config>> = Arc::new(arc_swap::ArcSwap::from_pointee(config));
+        let theme_loader = Arc::new(theme::Loader::new(&[]));
+        let syn_loader = Arc::new(syntax::Loader::new(syntax::Configuration { language: Vec::new() }));
+        Editor::new(area, theme_loader, syn_loader, config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn editor() -> Editor {
+        Editor::for_test(Config::default(), Rect::new(0, 0, 80, 24))
+    }
+
+    #[test]
+    fn parse_statusline_config() {
+        let config: StatusLineConfig = toml::from_str(
+            r#"
+            left = ["mode", "file-base-name"]
+            right = ["total-line-numbers"]
+            mode.normal = "N"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.left, vec![StatusLineElement::Mode, StatusLineElement::FileBaseName]);
+        assert_eq!(config.right, vec![StatusLineElement::TotalLineNumbers]);
+        // the fields left out keep their default
+        assert_eq!(config.center, Vec::new());
+        assert_eq!(config.mode.normal, "N");
+        assert_eq!(config.mode.insert, "INS");
+        assert_eq!(config.separator, "│");
+        assert!(toml::from_str::<StatusLineConfig>("left = [\"unknown\"]").is_err());
+    }
+
+    #[tokio::test]
+    async fn message_history_is_capped() {
+        let mut editor = editor();
+        for i in 0..=MAX_MESSAGES {
+            editor.set_status(format!("message {i}"));
+        }
+        editor.set_error("failed");
+        assert_eq!(editor.messages.len(), MAX_MESSAGES);
+        assert_eq!(editor.messages.front().unwrap().0, "message 2");
+        assert_eq!(editor.messages.back().unwrap(), &(Cow::from("failed"), Severity::Error));
+        assert_eq!(editor.get_status(), Some((&Cow::from("failed"), &Severity::Error)));
+    }
+
+    #[tokio::test]
+    async fn status_times_out() {
+        let config = Config {
+            status_timeout: Duration::from_millis(10),
+            ..Config::default()
+        };
+        let mut editor = Editor::for_test(config, Rect::new(0, 0, 80, 24));
+        editor.set_status("saved");
+        assert!(editor.get_status().is_some());
+        (&mut editor.status_timer).await;
+        editor.handle_status_timeout();
+        assert_eq!(editor.get_status(), None);
+        // the timer is parked until the next message
+        assert!(editor.status_timer.deadline() > Instant::now() + Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn resize_keeps_cursors_in_view() {
+        use crate::core::{Rope, Selection};
+
+        let mut editor = editor();
+        let doc = Document::from(Rope::from("line\n".repeat(30)), None, editor.config.clone());
+        let doc_id = editor.new_file_from_document(Action::VerticalSplit, doc);
+        let view_id = editor.tree.focus;
+        // the start of line 10
+        let doc = editor.documents.get_mut(&doc_id).unwrap();
+        doc.set_selection(view_id, Selection::point(50));
+        editor.ensure_cursor_in_view(view_id);
+        assert_eq!(editor.tree.get(view_id).offset.anchor, 0);
+        let cursor = editor.cursor().0;
+        assert!(cursor.is_some());
+
+        // the same area leaves the views alone
+        editor.resize(Rect::new(0, 0, 80, 23));
+        assert_eq!(editor.tree.get(view_id).offset.anchor, 0);
+        assert_eq!(editor.cursor().0, cursor);
+
+        // the view scrolls to keep the cursor visible in the smaller area
+        editor.resize(Rect::new(0, 0, 80, 8));
+        assert_eq!(editor.tree.get(view_id).area.height, 8);
+        assert_ne!(editor.tree.get(view_id).offset.anchor, 0);
+        assert!(editor.cursor().0.is_some());
+    }
+
+    #[tokio::test]
+    async fn close_views() {
+        let mut editor = editor();
+        let doc_id = editor.new_file(Action::VerticalSplit);
+        let first = editor.tree.focus;
+        editor.switch(doc_id, Action::VerticalSplit);
+        let second = editor.tree.focus;
+        assert_ne!(first, second);
+        let mut views: Vec<_> = editor.documents[&doc_id].selections().keys().copied().collect();
+        views.sort();
+        assert_eq!(views, vec![first, second]);
+
+        // the selection of a closed view is dropped from its document
+        editor.close(second);
+        assert_eq!(editor.tree.focus, first);
+        assert_eq!(
+            editor.documents[&doc_id].selections().keys().collect::<Vec<_>>(),
+            vec![&first]
+        );
+
+        editor.close(first);
+        assert!(editor.tree.is_empty());
+        assert!(editor.documents[&doc_id].selections().is_empty());
+    }
+}
@@ -1,8 +1,10 @@
 use std::str::FromStr;
 
 use bitflags::bitflags;
+use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 /// UNSTABLE
 pub enum CursorKind {
     /// █
@@ -73,6 +75,42 @@ impl Rect {
         }
     }
 
+    /// Returns a new Rect with height reduced from the top.
+    /// This changes the y coordinate and clamps it to the bottom
+    /// edge of the original Rect.
+    pub fn clip_top(self, height: u16) -> Rect {
+        let height = std::cmp::min(height, self.height);
+        Rect {
+            y: self.y.saturating_add(height),
+            height: self.height.saturating_sub(height),
+            ..self
+        }
+    }
+
+    /// Returns a new Rect with the same position and the given width.
+    pub fn with_width(self, width: u16) -> Rect {
+        Rect { width, ..self }
+    }
+
+    /// Returns a new Rect with the same position and the given height.
+    pub fn with_height(self, height: u16) -> Rect {
+        Rect { height, ..self }
+    }
+
+    /// Returns the area covered by both rects, which is empty if they don't overlap.
+    pub fn intersection(self, other: Rect) -> Rect {
+        let x1 = std::cmp::max(self.x, other.x);
+        let y1 = std::cmp::max(self.y, other.y);
+        let x2 = std::cmp::min(self.right(), other.right());
+        let y2 = std::cmp::min(self.bottom(), other.bottom());
+        Rect {
+            x: x1,
+            y: y1,
+            width: x2.saturating_sub(x1),
+            height: y2.saturating_sub(y1),
+        }
+    }
+
     pub fn clip_bottom(self, height: u16) -> Rect {
         Rect {
             height: self.height.saturating_sub(height),
@@ -104,6 +142,106 @@ pub enum Color {
     Indexed(u8),
 }
 
+/// The colors a terminal is able to display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// The 16 named colors.
+    Ansi16,
+    /// The xterm 256-color palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    TrueColor,
+}
+
+/// The 16 named colors with their default xterm values, in palette order.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::LightGray, (229, 229, 229)),
+    (Color::Gray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Intensities of the channels of the 6x6x6 color cube in the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// The RGB value of an entry of the 256-color palette.
+fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// The closest entry of the 256-color palette, ignoring the 16 named colors as their values
+/// depend on the terminal.
+fn rgb_to_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let cube_index = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - value as i32).abs())
+            .unwrap() as u8
+    };
+    let cube = 16 + 36 * cube_index(rgb.0) + 6 * cube_index(rgb.1) + cube_index(rgb.2);
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(ansi256_to_rgb(gray), rgb) < distance(ansi256_to_rgb(cube), rgb) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, value)| distance(*value, rgb))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+impl Color {
+    /// Returns the closest color that can be displayed with the given color support.
+    pub fn downgrade(self, support: ColorSupport) -> Color {
+        match (self, support) {
+            (_, ColorSupport::TrueColor) => self,
+            (Color::Rgb(r, g, b), ColorSupport::Ansi256) => Color::Indexed(rgb_to_ansi256((r, g, b))),
+            (Color::Rgb(r, g, b), ColorSupport::Ansi16) => rgb_to_ansi16((r, g, b)),
+            (Color::Indexed(index), ColorSupport::Ansi16) if index < 16 => ANSI16[index as usize].0,
+            (Color::Indexed(index), ColorSupport::Ansi16) => rgb_to_ansi16(ansi256_to_rgb(index)),
+            _ => self,
+        }
+    }
+}
+
 impl From<Color> for crossterm::style::Color {
     fn from(color: Color) -> Self {
         use crossterm::style::Color as CColor;
@@ -142,6 +280,21 @@ pub enum UnderlineStyle {
     DoubleLine,
 }
 
+impl From<UnderlineStyle> for crossterm::style::Attribute {
+    fn from(style: UnderlineStyle) -> Self {
+        use crossterm::style::Attribute;
+
+        match style {
+            UnderlineStyle::Reset => Attribute::NoUnderline,
+            UnderlineStyle::Line => Attribute::Underlined,
+            UnderlineStyle::Curl => Attribute::Undercurled,
+            UnderlineStyle::Dotted => Attribute::Underdotted,
+            UnderlineStyle::Dashed => Attribute::Underdashed,
+            UnderlineStyle::DoubleLine => Attribute::DoubleUnderlined,
+        }
+    }
+}
+
 impl FromStr for UnderlineStyle {
     type Err = &'static str;
 
@@ -239,7 +392,7 @@ impl Style {
     ///
     /// ## Examples
     /// ```
-    /// # use helix_view::graphics::{Color, Modifier, Style};
+    /// # use toy_helix::view::graphics::{Color, Modifier, Style};
     /// let style_1 = Style::default().fg(Color::Yellow);
     /// let style_2 = Style::default().bg(Color::Red);
     /// let combined = style_1.patch(style_2);
@@ -274,3 +427,31 @@ impl Default for Style {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn downgrade_colors() {
+        let white = Color::Rgb(255, 255, 255);
+        assert_eq!(white.downgrade(ColorSupport::TrueColor), white);
+        assert_eq!(white.downgrade(ColorSupport::Ansi256), Color::Indexed(231));
+        assert_eq!(white.downgrade(ColorSupport::Ansi16), Color::White);
+
+        // grays prefer the grayscale ramp
+        assert_eq!(
+            Color::Rgb(128, 128, 128).downgrade(ColorSupport::Ansi256),
+            Color::Indexed(244)
+        );
+        assert_eq!(
+            Color::Rgb(0x5f, 0x87, 0xd7).downgrade(ColorSupport::Ansi256),
+            Color::Indexed(68)
+        );
+
+        assert_eq!(Color::Rgb(240, 10, 20).downgrade(ColorSupport::Ansi16), Color::LightRed);
+        assert_eq!(Color::Indexed(1).downgrade(ColorSupport::Ansi16), Color::Red);
+        assert_eq!(Color::Indexed(21).downgrade(ColorSupport::Ansi16), Color::Blue);
+        assert_eq!(Color::Cyan.downgrade(ColorSupport::Ansi16), Color::Cyan);
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Write;
+
+use crate::{
+    core::diagnostic::Severity,
+    view::{
+        document::{Document, Mode},
+        editor::{Editor, GutterType, LineNumber},
+        graphics::Style,
+        theme::Theme,
+        view::View,
+    },
+};
+
+/// Renders the gutter of a single document line. Called with the line index, whether a cursor
+/// is on the line and whether this is its first visual line; writes the text to draw into the
+/// buffer and returns its style, or `None` if there is nothing to draw.
+pub type GutterFn<'doc> = Box<dyn FnMut(usize, bool, bool, &mut String) -> Option<Style> + 'doc>;
+
+impl GutterType {
+    pub fn style<'doc>(
+        self,
+        editor: &'doc Editor,
+        doc: &'doc Document,
+        view: &View,
+        theme: &Theme,
+        is_focused: bool,
+    ) -> GutterFn<'doc> {
+        match self {
+            GutterType::Diagnostics => diagnostic(editor, doc, view, theme, is_focused),
+            GutterType::LineNumbers => line_numbers(editor, doc, view, theme, is_focused),
+            GutterType::Spacer => padding(editor, doc, view, theme, is_focused),
+            GutterType::Diff => diff(editor, doc, view, theme, is_focused),
+        }
+    }
+
+    pub fn width(self, view: &View, doc: &Document) -> usize {
+        match self {
+            GutterType::Diagnostics => 1,
+            GutterType::LineNumbers => line_numbers_width(view, doc),
+            GutterType::Spacer => 1,
+            GutterType::Diff => 1,
+        }
+    }
+}
+
+pub fn diagnostic<'doc>(
+    _editor: &'doc Editor,
+    doc: &'doc Document,
+    _view: &View,
+    theme: &Theme,
+    _is_focused: bool,
+) -> GutterFn<'doc> {
+    let warning = theme.get("warning");
+    let error = theme.get("error");
+    let info = theme.get("info");
+    let hint = theme.get("hint");
+    let diagnostics = doc.diagnostics();
+
+    Box::new(
+        move |line: usize, _selected: bool, first_visual_line: bool, out: &mut String| {
+            if !first_visual_line {
+                return None;
+            }
+
+            let diagnostic = diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.line == line)
+                .max_by_key(|diagnostic| diagnostic.severity())?;
+
+            write!(out, "●").unwrap();
+            Some(match diagnostic.severity() {
+                Severity::Error => error,
+                Severity::Warning => warning,
+                Severity::Info => info,
+                Severity::Hint => hint,
+            })
+        },
+    )
+}
+
+pub fn diff<'doc>(_editor: &'doc Editor, doc: &'doc Document, _view: &View, theme: &Theme, _is_focused: bool) -> GutterFn<'doc> {
+    let added = theme.get("diff.plus");
+    let deleted = theme.get("diff.minus");
+    let modified = theme.get("diff.delta");
+    let hunks = doc.diff_hunks();
+
+    Box::new(
+        move |line: usize, _selected: bool, first_visual_line: bool, out: &mut String| {
+            if !first_visual_line {
+                return None;
+            }
+
+            let line = line as u32;
+            let hunk = hunks
+                .iter()
+                .find(|hunk| hunk.after.contains(&line) || (hunk.is_pure_removal() && hunk.after.start == line))?;
+
+            let (icon, style) = if hunk.is_pure_insertion() {
+                ("▍", added)
+            } else if hunk.is_pure_removal() {
+                ("▔", deleted)
+            } else {
+                ("▍", modified)
+            };
+
+            write!(out, "{}", icon).unwrap();
+            Some(style)
+        },
+    )
+}
+
+pub fn line_numbers<'doc>(
+    editor: &'doc Editor,
+    doc: &'doc Document,
+    view: &View,
+    theme: &Theme,
+    is_focused: bool,
+) -> GutterFn<'doc> {
+    let text = doc.text().slice(..);
+    let width = line_numbers_width(view, doc);
+
+    let last_line_in_view = view.estimate_last_doc_line(doc);
+
+    // Whether to draw the line number for the last line of the
+    // document or not.  We only draw it if it's not an empty line.
+    let draw_last = text.line_to_byte(last_line_in_view) < text.len_bytes();
+
+    let linenr = theme.get("ui.linenr");
+    let linenr_select = theme.get("ui.linenr.selected");
+
+    let current_line = text.char_to_line(doc.selection(view.id).primary().cursor(text));
+
+    let line_number = editor.config().line_number;
+    let mode = editor.mode;
+
+    Box::new(
+        move |line: usize, selected: bool, first_visual_line: bool, out: &mut String| {
+            if line == last_line_in_view && !draw_last {
+                write!(out, "{:>1$}", '~', width).unwrap();
+                Some(linenr)
+            } else {
+                let relative = line_number == LineNumber::Relative && mode != Mode::Insert && is_focused && current_line != line;
+
+                let display_num = if relative { current_line.abs_diff(line) } else { line + 1 };
+
+                let style = if selected && is_focused { linenr_select } else { linenr };
+
+                if first_visual_line {
+                    write!(out, "{:>1$}", display_num, width).unwrap();
+                } else {
+                    write!(out, "{:>1$}", " ", width).unwrap();
+                }
+
+                first_visual_line.then_some(style)
+            }
+        },
+    )
+}
+
+/// The width of a "line-numbers" gutter
+///
+/// The width of the gutter depends on the number of lines in the document and whether there is
+/// content on the last line (the `~` line).
+fn line_numbers_width(_view: &View, doc: &Document) -> usize {
+    let text = doc.text();
+    let last_line = text.len_lines().saturating_sub(1);
+    let draw_last = text.line_to_byte(last_line) < text.len_bytes();
+    let last_drawn = if draw_last { last_line + 1 } else { last_line };
+    count_digits(last_drawn).max(1)
+}
+
+pub fn padding<'doc>(
+    _editor: &'doc Editor,
+    _doc: &'doc Document,
+    _view: &View,
+    _theme: &Theme,
+    _is_focused: bool,
+) -> GutterFn<'doc> {
+    Box::new(|_line: usize, _selected: bool, _first_visual_line: bool, _out: &mut String| None)
+}
+
+fn count_digits(n: usize) -> usize {
+    (usize::checked_ilog10(n).unwrap_or(0) + 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_digits() {
+        assert_eq!(count_digits(0), 1);
+        assert_eq!(count_digits(9), 1);
+        assert_eq!(count_digits(10), 2);
+        assert_eq!(count_digits(1000), 4);
+    }
+
+    #[tokio::test]
+    async fn diagnostics_without_severity_are_warnings() {
+        use crate::{
+            core::{
+                diagnostic::{self, Diagnostic},
+                Rope,
+            },
+            view::{editor::Config, graphics::Rect, theme},
+        };
+
+        let editor = Editor::for_test(Config::default(), Rect::new(0, 0, 20, 5));
+        let mut doc = Document::from(Rope::from("a\nb\nc\n"), None, editor.config.clone());
+        let diag = |line, severity| Diagnostic {
+            range: diagnostic::Range {
+                start: line * 2,
+                end: line * 2 + 1,
+            },
+            line,
+            message: String::new(),
+            severity,
+        };
+        doc.set_diagnostics(vec![
+            diag(0, None),
+            diag(1, Some(Severity::Hint)),
+            diag(1, None),
+            diag(2, None),
+            diag(2, Some(Severity::Error)),
+        ]);
+        let view = View::new(doc.id);
+
+        let theme = &*theme::DEFAULT_THEME;
+        let mut gutter = diagnostic(&editor, &doc, &view, theme, true);
+        let mut out = String::new();
+        assert_eq!(gutter(0, false, true, &mut out), Some(theme.get("warning")));
+        assert_eq!(gutter(1, false, true, &mut out), Some(theme.get("warning")));
+        assert_eq!(gutter(2, false, true, &mut out), Some(theme.get("error")));
+    }
+}
@@ -7,7 +7,7 @@ pub enum Event {
     FocusGained,
     FocusLost,
     Key(KeyEvent),
-    // Mouse(MouseEvent),
+    Mouse(MouseEvent),
     Paste(String),
     Resize(u16, u16),
     IdleTimeout,
@@ -19,6 +19,57 @@ pub struct KeyEvent {
     pub modifiers: KeyModifiers,
 }
 
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct MouseEvent {
+    /// The kind of mouse event that was caused.
+    pub kind: MouseEventKind,
+    /// The column that the event occurred on.
+    pub column: u16,
+    /// The row that the event occurred on.
+    pub row: u16,
+    /// The key modifiers active when the event occurred.
+    pub modifiers: KeyModifiers,
+}
+
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MouseEventKind {
+    /// Pressed mouse button. Contains the button that was pressed.
+    Down(MouseButton),
+    /// Released mouse button. Contains the button that was released.
+    Up(MouseButton),
+    /// Moved the mouse cursor while pressing the contained mouse button.
+    Drag(MouseButton),
+    /// Moved the mouse cursor while not pressing a mouse button.
+    Moved,
+    /// Scrolled mouse wheel downwards (towards the user).
+    ScrollDown,
+    /// Scrolled mouse wheel upwards (away from the user).
+    ScrollUp,
+}
+
+/// Represents a mouse button.
+#[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MouseButton {
+    /// Left mouse button.
+    Left,
+    /// Right mouse button.
+    Right,
+    /// Middle mouse button.
+    Middle,
+}
+
+impl KeyEvent {
+    /// If a character was pressed, return it.
+    pub fn char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(ch) => Some(ch),
+            KeyCode::Enter => Some('\n'),
+            KeyCode::Tab => Some('\t'),
+            _ => None,
+        }
+    }
+}
+
 pub(crate) mod keys {
     pub(crate) const BACKSPACE: &str = "backspace";
     pub(crate) const ENTER: &str = "ret";
@@ -151,14 +202,37 @@ impl std::str::FromStr for KeyEvent {
     }
 }
 
+/// Parses a sequence of keys, e.g. `"ihello<esc>"`. Keys with modifiers or names are written
+/// between angle brackets, as in `"<C-w>v"`, use `<lt>` and `<gt>` for the brackets themselves.
+pub fn parse_macro(keys_str: &str) -> Result<Vec<KeyEvent>, Error> {
+    let mut keys = Vec::new();
+    let mut chars = keys_str.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        let key = match c {
+            '<' => {
+                let len = keys_str[i..].find('>').ok_or_else(|| anyhow!("'>' expected"))?;
+                // skip to the closing bracket
+                chars.nth(len - 1);
+                keys_str[i + 1..i + len].parse()?
+            }
+            '>' => return Err(anyhow!("Unmatched '>'")),
+            c => KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE,
+            },
+        };
+        keys.push(key);
+    }
+
+    Ok(keys)
+}
+
 impl From<crossterm::event::Event> for Event {
     fn from(event: crossterm::event::Event) -> Self {
         match event {
             crossterm::event::Event::Key(key) => Self::Key(key.into()),
-            crossterm::event::Event::Mouse(mouse) => {
-                todo!()
-                // Self::Mouse(mouse.into()),
-            }
+            crossterm::event::Event::Mouse(mouse) => Self::Mouse(mouse.into()),
             crossterm::event::Event::Resize(w, h) => Self::Resize(w, h),
             crossterm::event::Event::FocusGained => Self::FocusGained,
             crossterm::event::Event::FocusLost => Self::FocusLost,
@@ -167,6 +241,60 @@ impl From<crossterm::event::Event> for Event {
     }
 }
 
+impl From<crossterm::event::MouseEvent> for MouseEvent {
+    fn from(
+        crossterm::event::MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers,
+        }: crossterm::event::MouseEvent,
+    ) -> Self {
+        Self {
+            kind: kind.into(),
+            column,
+            row,
+            modifiers: modifiers.into(),
+        }
+    }
+}
+
+impl From<crossterm::event::MouseEventKind> for MouseEventKind {
+    fn from(kind: crossterm::event::MouseEventKind) -> Self {
+        match kind {
+            crossterm::event::MouseEventKind::Down(button) => Self::Down(button.into()),
+            crossterm::event::MouseEventKind::Up(button) => Self::Up(button.into()),
+            crossterm::event::MouseEventKind::Drag(button) => Self::Drag(button.into()),
+            crossterm::event::MouseEventKind::Moved => Self::Moved,
+            crossterm::event::MouseEventKind::ScrollDown => Self::ScrollDown,
+            crossterm::event::MouseEventKind::ScrollUp => Self::ScrollUp,
+        }
+    }
+}
+
+impl From<crossterm::event::MouseButton> for MouseButton {
+    fn from(button: crossterm::event::MouseButton) -> Self {
+        match button {
+            crossterm::event::MouseButton::Left => MouseButton::Left,
+            crossterm::event::MouseButton::Right => MouseButton::Right,
+            crossterm::event::MouseButton::Middle => MouseButton::Middle,
+        }
+    }
+}
+
+impl From<KeyEvent> for crossterm::event::KeyEvent {
+    fn from(KeyEvent { code, modifiers }: KeyEvent) -> Self {
+        if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) {
+            // special case for Shift-Tab -> BackTab
+            let mut modifiers = modifiers;
+            modifiers.remove(KeyModifiers::SHIFT);
+            crossterm::event::KeyEvent::new(crossterm::event::KeyCode::BackTab, modifiers.into())
+        } else {
+            crossterm::event::KeyEvent::new(code.into(), modifiers.into())
+        }
+    }
+}
+
 impl From<crossterm::event::KeyEvent> for KeyEvent {
     fn from(crossterm::event::KeyEvent { code, modifiers, .. }: crossterm::event::KeyEvent) -> Self {
         if code == crossterm::event::KeyCode::BackTab {
@@ -185,3 +313,34 @@ impl From<crossterm::event::KeyEvent> for KeyEvent {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parsing_macros() {
+        let key = |code, modifiers| KeyEvent { code, modifiers };
+
+        assert_eq!(
+            parse_macro("i-<C-w><esc>").unwrap(),
+            vec![
+                key(KeyCode::Char('i'), KeyModifiers::NONE),
+                key(KeyCode::Char('-'), KeyModifiers::NONE),
+                key(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                key(KeyCode::Esc, KeyModifiers::NONE),
+            ]
+        );
+        assert_eq!(
+            parse_macro("<lt>é<A-S-ret>").unwrap(),
+            vec![
+                key(KeyCode::Char('<'), KeyModifiers::NONE),
+                key(KeyCode::Char('é'), KeyModifiers::NONE),
+                key(KeyCode::Enter, KeyModifiers::ALT | KeyModifiers::SHIFT),
+            ]
+        );
+        assert!(parse_macro("<C-w").is_err());
+        assert!(parse_macro("a>").is_err());
+        assert!(parse_macro("<invalid>").is_err());
+    }
+}
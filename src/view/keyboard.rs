@@ -30,6 +30,26 @@ impl From<crossterm::event::KeyModifiers> for KeyModifiers {
     }
 }
 
+impl From<KeyModifiers> for crossterm::event::KeyModifiers {
+    fn from(key_modifiers: KeyModifiers) -> Self {
+        use crossterm::event::KeyModifiers as CKeyModifiers;
+
+        let mut result = CKeyModifiers::NONE;
+
+        if key_modifiers.contains(KeyModifiers::SHIFT) {
+            result.insert(CKeyModifiers::SHIFT);
+        }
+        if key_modifiers.contains(KeyModifiers::CONTROL) {
+            result.insert(CKeyModifiers::CONTROL);
+        }
+        if key_modifiers.contains(KeyModifiers::ALT) {
+            result.insert(CKeyModifiers::ALT);
+        }
+
+        result
+    }
+}
+
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum KeyCode {
     /// Backspace key.
@@ -145,6 +165,63 @@ impl From<crossterm::event::ModifierKeyCode> for ModifierKeyCode {
     }
 }
 
+impl From<ModifierKeyCode> for crossterm::event::ModifierKeyCode {
+    fn from(val: ModifierKeyCode) -> Self {
+        use crossterm::event::ModifierKeyCode as CModifierKeyCode;
+
+        match val {
+            ModifierKeyCode::LeftShift => CModifierKeyCode::LeftShift,
+            ModifierKeyCode::LeftControl => CModifierKeyCode::LeftControl,
+            ModifierKeyCode::LeftAlt => CModifierKeyCode::LeftAlt,
+            ModifierKeyCode::LeftSuper => CModifierKeyCode::LeftSuper,
+            ModifierKeyCode::LeftHyper => CModifierKeyCode::LeftHyper,
+            ModifierKeyCode::LeftMeta => CModifierKeyCode::LeftMeta,
+            ModifierKeyCode::RightShift => CModifierKeyCode::RightShift,
+            ModifierKeyCode::RightControl => CModifierKeyCode::RightControl,
+            ModifierKeyCode::RightAlt => CModifierKeyCode::RightAlt,
+            ModifierKeyCode::RightSuper => CModifierKeyCode::RightSuper,
+            ModifierKeyCode::RightHyper => CModifierKeyCode::RightHyper,
+            ModifierKeyCode::RightMeta => CModifierKeyCode::RightMeta,
+            ModifierKeyCode::IsoLevel3Shift => CModifierKeyCode::IsoLevel3Shift,
+            ModifierKeyCode::IsoLevel5Shift => CModifierKeyCode::IsoLevel5Shift,
+        }
+    }
+}
+
+impl From<KeyCode> for crossterm::event::KeyCode {
+    fn from(key_code: KeyCode) -> Self {
+        use crossterm::event::KeyCode as CKeyCode;
+
+        match key_code {
+            KeyCode::Backspace => CKeyCode::Backspace,
+            KeyCode::Enter => CKeyCode::Enter,
+            KeyCode::Left => CKeyCode::Left,
+            KeyCode::Right => CKeyCode::Right,
+            KeyCode::Up => CKeyCode::Up,
+            KeyCode::Down => CKeyCode::Down,
+            KeyCode::Home => CKeyCode::Home,
+            KeyCode::End => CKeyCode::End,
+            KeyCode::PageUp => CKeyCode::PageUp,
+            KeyCode::PageDown => CKeyCode::PageDown,
+            KeyCode::Tab => CKeyCode::Tab,
+            KeyCode::Delete => CKeyCode::Delete,
+            KeyCode::Insert => CKeyCode::Insert,
+            KeyCode::F(f_number) => CKeyCode::F(f_number),
+            KeyCode::Char(character) => CKeyCode::Char(character),
+            KeyCode::Null => CKeyCode::Null,
+            KeyCode::Esc => CKeyCode::Esc,
+            KeyCode::CapsLock => CKeyCode::CapsLock,
+            KeyCode::ScrollLock => CKeyCode::ScrollLock,
+            KeyCode::NumLock => CKeyCode::NumLock,
+            KeyCode::PrintScreen => CKeyCode::PrintScreen,
+            KeyCode::Pause => CKeyCode::Pause,
+            KeyCode::Menu => CKeyCode::Menu,
+            KeyCode::KeypadBegin => CKeyCode::KeypadBegin,
+            KeyCode::Modifier(modifier_key_code) => CKeyCode::Modifier(modifier_key_code.into()),
+        }
+    }
+}
+
 impl From<crossterm::event::KeyCode> for KeyCode {
     fn from(val: crossterm::event::KeyCode) -> Self {
         use crossterm::event::KeyCode as CKeyCode;
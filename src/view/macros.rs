@@ -39,3 +39,15 @@ macro_rules! view {
         $editor.tree.get($editor.tree.focus)
     }};
 }
+
+/// Get the current document immutably
+/// Returns `&Document`
+#[macro_export]
+macro_rules! doc {
+    ($editor:expr, $id:expr) => {{
+        &$editor.documents[$id]
+    }};
+    ($editor:expr) => {{
+        $crate::doc!($editor, &$crate::view!($editor).doc)
+    }};
+}
@@ -1,16 +1,208 @@
-use crate::core::macros::hashmap;
+use crate::{core::macros::hashmap, loader::merge_toml_values};
+use anyhow::{anyhow, Context, Result};
 use once_cell::sync::Lazy;
 use serde::{de::Deserializer, Deserialize};
-use std::{collections::HashMap, str};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str,
+};
 use toml::{map::Map, Value};
 
-use super::graphics::{Color, Modifier, Style, UnderlineStyle};
+use super::graphics::{Color, ColorSupport, Modifier, Style, UnderlineStyle};
 
-pub static DEFAULT_THEME: Lazy<Theme> = Lazy::new(|| {
+/// Name of the embedded theme, which can't be overridden by a theme file.
+pub const DEFAULT_THEME_NAME: &str = "default";
+
+pub static DEFAULT_THEME_DATA: Lazy<Value> = Lazy::new(|| {
     let bytes = include_bytes!("../../theme.toml");
     toml::from_str(str::from_utf8(bytes).unwrap()).expect("Failed to parse base default theme")
 });
 
+pub static DEFAULT_THEME: Lazy<Theme> = Lazy::new(|| Theme {
+    name: DEFAULT_THEME_NAME.into(),
+    ..Theme::from(DEFAULT_THEME_DATA.clone())
+});
+
+/// Name of the embedded theme limited to the 16 named colors, used by default in terminals
+/// without 256-color support.
+pub const BASE16_DEFAULT_THEME_NAME: &str = "base16_default";
+
+pub static BASE16_DEFAULT_THEME_DATA: Lazy<Value> = Lazy::new(|| {
+    let bytes = include_bytes!("../../base16_theme.toml");
+    toml::from_str(str::from_utf8(bytes).unwrap()).expect("Failed to parse base 16 default theme")
+});
+
+pub static BASE16_DEFAULT_THEME: Lazy<Theme> = Lazy::new(|| Theme {
+    name: BASE16_DEFAULT_THEME_NAME.into(),
+    ..Theme::from(BASE16_DEFAULT_THEME_DATA.clone())
+});
+
+/// Finds themes by name in the `themes` directories of the configuration and runtime
+/// directories, resolving the themes they inherit from.
+#[derive(Clone, Debug)]
+pub struct Loader {
+    /// Directories the themes are searched in, in order of priority.
+    theme_dirs: Vec<PathBuf>,
+    /// The colors of the loaded themes are downgraded to the ones the terminal can display.
+    color_support: ColorSupport,
+}
+
+impl Loader {
+    /// Creates a loader searching the `themes` subdirectory of each of `dirs`.
+    pub fn new(dirs: &[PathBuf]) -> Self {
+        Self {
+            theme_dirs: dirs.iter().map(|dir| dir.join("themes")).collect(),
+            color_support: ColorSupport::TrueColor,
+        }
+    }
+
+    /// Downgrades the colors of the loaded themes to the closest ones in `color_support`.
+    pub fn with_color_support(mut self, color_support: ColorSupport) -> Self {
+        self.color_support = color_support;
+        self
+    }
+
+    /// Loads the theme `name`, logging the unknown keys and colors found in it.
+    pub fn load(&self, name: &str) -> Result<Theme> {
+        let (theme, warnings) = self.load_with_warnings(name)?;
+        for warning in warnings {
+            tracing::warn!("Theme '{name}': {warning}");
+        }
+        Ok(theme)
+    }
+
+    /// Loads the theme `name`, returning the unknown keys and colors found in it.
+    pub fn load_with_warnings(&self, name: &str) -> Result<(Theme, Vec<String>)> {
+        let value = match name {
+            DEFAULT_THEME_NAME if self.color_support == ColorSupport::TrueColor => {
+                return Ok((DEFAULT_THEME.clone(), Vec::new()))
+            }
+            DEFAULT_THEME_NAME => DEFAULT_THEME_DATA.clone(),
+            BASE16_DEFAULT_THEME_NAME => return Ok((BASE16_DEFAULT_THEME.clone(), Vec::new())),
+            _ => self.load_theme(name, &mut HashSet::new())?,
+        };
+        let (theme, warnings) = Theme::from_toml_with_color_support(value, self.color_support);
+        Ok((
+            Theme {
+                name: name.into(),
+                ..theme
+            },
+            warnings,
+        ))
+    }
+
+    /// Reads the theme `name` and recursively merges it into the themes it inherits from.
+    fn load_theme(&self, name: &str, visited_paths: &mut HashSet<PathBuf>) -> Result<Value> {
+        let path = self.path(name, visited_paths)?;
+        let theme_toml = Self::load_toml(&path)?;
+
+        let Some(parent) = theme_toml.get("inherits") else {
+            return Ok(theme_toml);
+        };
+        let parent_name = parent
+            .as_str()
+            .ok_or_else(|| anyhow!("Theme: expected 'inherits' to be a string: {parent}"))?;
+        let parent_toml = match parent_name {
+            DEFAULT_THEME_NAME => DEFAULT_THEME_DATA.clone(),
+            BASE16_DEFAULT_THEME_NAME => BASE16_DEFAULT_THEME_DATA.clone(),
+            _ => self.load_theme(parent_name, visited_paths)?,
+        };
+
+        Ok(Self::merge_themes(parent_toml, theme_toml))
+    }
+
+    /// Overrides the styles of `parent` with the ones of `child`. The palettes are merged, so
+    /// that the colors of `child` are also used by the styles it inherits.
+    fn merge_themes(parent: Value, child: Value) -> Value {
+        let parent_palette = parent.get("palette").cloned();
+        let child_palette = child.get("palette").cloned();
+        let palette = match (parent_palette, child_palette) {
+            (Some(parent_palette), Some(child_palette)) => merge_toml_values(parent_palette, child_palette, usize::MAX),
+            (parent_palette, child_palette) => child_palette.or(parent_palette).unwrap_or(Value::Table(Map::new())),
+        };
+
+        // a style of the child replaces the parent's style for the scope instead of being merged
+        let mut theme = merge_toml_values(parent, child, 1);
+        if let Value::Table(table) = &mut theme {
+            table.insert("palette".into(), palette);
+            table.remove("inherits");
+        }
+        theme
+    }
+
+    /// The path of the file of the theme `name` with the highest priority.
+    fn path(&self, name: &str, visited_paths: &mut HashSet<PathBuf>) -> Result<PathBuf> {
+        anyhow::ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']),
+            "Invalid theme name: '{name}'"
+        );
+        let filename = format!("{name}.toml");
+
+        // a user theme may inherit the runtime theme of the same name, the files that were
+        // already visited are skipped in favour of the ones of the lower priority directories
+        let mut cycle_found = false;
+        self.theme_dirs
+            .iter()
+            .map(|dir| dir.join(&filename))
+            .filter(|path| path.is_file())
+            .find(|path| {
+                let unvisited = visited_paths.insert(path.clone());
+                cycle_found |= !unvisited;
+                unvisited
+            })
+            .ok_or_else(|| {
+                if cycle_found {
+                    anyhow!("Theme: cycle found in inheriting theme: {name}")
+                } else {
+                    anyhow!("Theme: file not found for: {name}")
+                }
+            })
+    }
+
+    fn load_toml(path: &Path) -> Result<Value> {
+        let data = std::fs::read_to_string(path).with_context(|| format!("Theme: failed to read {}", path.display()))?;
+        toml::from_str(&data).with_context(|| format!("Theme: failed to parse {}", path.display()))
+    }
+
+    /// The names of the themes in the directory `path`.
+    pub fn read_names(path: &Path) -> Vec<String> {
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| {
+                        let path = entry.ok()?.path();
+                        if path.extension()? != "toml" {
+                            return None;
+                        }
+                        Some(path.file_stem()?.to_string_lossy().into_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The names of all the themes that can be loaded, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.theme_dirs.iter().flat_map(|dir| Self::read_names(dir)).collect();
+        names.push(DEFAULT_THEME_NAME.into());
+        names.push(BASE16_DEFAULT_THEME_NAME.into());
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// The embedded theme, or the one limited to the 16 named colors if the terminal can't display
+    /// more.
+    pub fn default(&self) -> Theme {
+        let name = match self.color_support {
+            ColorSupport::Ansi16 => BASE16_DEFAULT_THEME_NAME,
+            _ => DEFAULT_THEME_NAME,
+        };
+        self.load(name).expect("the embedded themes always load")
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Theme {
     name: String,
@@ -20,6 +212,33 @@ pub struct Theme {
 }
 
 impl Theme {
+    /// Builds a theme from its toml representation, returning the problems found in it.
+    /// Invalid styles and colors are skipped.
+    pub fn from_toml(value: Value) -> (Self, Vec<String>) {
+        Self::from_toml_with_color_support(value, ColorSupport::TrueColor)
+    }
+
+    /// Like `from_toml`, but replaces the colors with the closest ones in `color_support`.
+    pub fn from_toml_with_color_support(value: Value, color_support: ColorSupport) -> (Self, Vec<String>) {
+        let values = match value {
+            Value::Table(values) => values,
+            value => return (Self::default(), vec![format!("Theme: expected a table: {value}")]),
+        };
+
+        let (styles, scopes, highlights, warnings) = build_theme_values(values, color_support);
+        let theme = Self {
+            styles,
+            scopes,
+            highlights,
+            ..Default::default()
+        };
+        (theme, warnings)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn highlight(&self, index: usize) -> Style {
         self.highlights[index]
     }
@@ -32,6 +251,11 @@ impl Theme {
         std::iter::successors(Some(scope), |s| Some(s.rsplit_once('.')?.0)).find_map(|s| self.styles.get(s).copied())
     }
 
+    /// Like `try_get`, but without falling back to parent scopes.
+    pub fn try_get_exact(&self, scope: &str) -> Option<Style> {
+        self.styles.get(scope).copied()
+    }
+
     #[inline]
     pub fn scopes(&self) -> &[String] {
         &self.scopes
@@ -40,6 +264,12 @@ impl Theme {
     pub fn find_scope_index_exact(&self, scope: &str) -> Option<usize> {
         self.scopes().iter().position(|s| s == scope)
     }
+
+    /// Like `find_scope_index_exact`, but falls back to the closest parent scope, e.g. `ui.cursor`
+    /// for `ui.cursor.primary`.
+    pub fn find_scope_index(&self, scope: &str) -> Option<usize> {
+        std::iter::successors(Some(scope), |s| Some(s.rsplit_once('.')?.0)).find_map(|s| self.find_scope_index_exact(s))
+    }
 }
 
 impl<'de> Deserialize<'de> for Theme {
@@ -48,32 +278,40 @@ impl<'de> Deserialize<'de> for Theme {
         D: Deserializer<'de>,
     {
         let values = Map::<String, Value>::deserialize(deserializer)?;
+        Ok(Self::from(Value::Table(values)))
+    }
+}
 
-        let (styles, scopes, highlights) = build_theme_values(values);
-
-        Ok(Self {
-            styles,
-            scopes,
-            highlights,
-            ..Default::default()
-        })
+impl From<Value> for Theme {
+    /// Builds a theme from its toml representation, logging the problems found in it.
+    fn from(value: Value) -> Self {
+        let (theme, warnings) = Theme::from_toml(value);
+        for warning in warnings {
+            tracing::warn!("{}", warning);
+        }
+        theme
     }
 }
 
-fn build_theme_values(mut values: Map<String, Value>) -> (HashMap<String, Style>, Vec<String>, Vec<Style>) {
+type ThemeValues = (HashMap<String, Style>, Vec<String>, Vec<Style>, Vec<String>);
+
+/// Parses the styles of a theme, returning them along with the problems found.
+fn build_theme_values(mut values: Map<String, Value>, color_support: ColorSupport) -> ThemeValues {
     let mut styles = HashMap::new();
     let mut scopes = Vec::new();
     let mut highlights = Vec::new();
+    let mut warnings = Vec::new();
 
     let palette = values
         .remove("palette")
         .map(|value| {
             ThemePalette::try_from(value).unwrap_or_else(|err| {
-                tracing::warn!("{}", err);
+                warnings.push(err);
                 ThemePalette::default()
             })
         })
-        .unwrap_or_default();
+        .unwrap_or_default()
+        .with_color_support(color_support);
 
     let _ = values.remove("inherits");
     styles.reserve(values.len());
@@ -82,7 +320,7 @@ fn build_theme_values(mut values: Map<String, Value>) -> (HashMap<String, Style>
     for (name, style_value) in values {
         let mut style = Style::default();
         if let Err(err) = palette.parse_style(&mut style, style_value) {
-            tracing::warn!("{}", err);
+            warnings.push(format!("{err} (in '{name}')"));
         }
 
         styles.insert(name.clone(), style);
@@ -90,19 +328,30 @@ fn build_theme_values(mut values: Map<String, Value>) -> (HashMap<String, Style>
         highlights.push(style);
     }
 
-    (styles, scopes, highlights)
+    (styles, scopes, highlights, warnings)
 }
 
 struct ThemePalette {
     palette: HashMap<String, Color>,
+    color_support: ColorSupport,
 }
 
 impl ThemePalette {
     pub fn new(palette: HashMap<String, Color>) -> Self {
-        let ThemePalette { palette: mut default } = ThemePalette::default();
+        let mut default = ThemePalette::default();
+
+        default.palette.extend(palette);
+        default
+    }
 
-        default.extend(palette);
-        Self { palette: default }
+    /// Replaces the colors of the palette, and the ones found in styles, with the closest ones
+    /// in `color_support`.
+    pub fn with_color_support(mut self, color_support: ColorSupport) -> Self {
+        for color in self.palette.values_mut() {
+            *color = color.downgrade(color_support);
+        }
+        self.color_support = color_support;
+        self
     }
 
     pub fn hex_string_to_rgb(s: &str) -> Result<Color, String> {
@@ -126,11 +375,11 @@ impl ThemePalette {
     pub fn parse_color(&self, value: Value) -> Result<Color, String> {
         let value = Self::parse_value_as_str(&value)?;
 
-        self.palette
-            .get(value)
-            .copied()
-            .ok_or("")
-            .or_else(|_| Self::hex_string_to_rgb(value))
+        match self.palette.get(value) {
+            Some(&color) => Ok(color),
+            None if value.starts_with('#') => Self::hex_string_to_rgb(value).map(|color| color.downgrade(self.color_support)),
+            None => Err(format!("Theme: unknown color: {}", value)),
+        }
     }
 
     pub fn parse_modifier(value: &Value) -> Result<Modifier, String> {
@@ -208,6 +457,7 @@ impl Default for ThemePalette {
                 "light-gray".to_string() => Color::LightGray,
                 "white".to_string() => Color::White,
             },
+            color_support: ColorSupport::TrueColor,
         }
     }
 }
@@ -231,3 +481,125 @@ impl TryFrom<Value> for ThemePalette {
         Ok(Self::new(palette))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Whether the theme only uses the 16 named colors.
+    fn is_16_color(theme: &Theme) -> bool {
+        theme.styles.values().all(|style| {
+            [style.fg, style.bg, style.underline_color]
+                .into_iter()
+                .flatten()
+                .all(|color| !matches!(color, Color::Rgb(..) | Color::Indexed(_)))
+        })
+    }
+
+    #[test]
+    fn load_inherited_theme() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().to_path_buf();
+        let themes = dir.join("themes");
+        std::fs::create_dir_all(&themes).unwrap();
+        for (name, contents) in [
+            (
+                "base",
+                r##"
+                "ui.text" = "fg"
+                "ui.popup" = { bg = "bg", modifiers = ["bold"] }
+                [palette]
+                fg = "#000001"
+                bg = "#000002"
+                "##,
+            ),
+            (
+                "child",
+                r##"
+                inherits = "base"
+                "ui.popup" = { fg = "fg" }
+                "ui.menu" = "unknown"
+                [palette]
+                fg = "#000003"
+                "##,
+            ),
+            ("cycle", r#"inherits = "cycle""#),
+        ] {
+            std::fs::write(themes.join(format!("{name}.toml")), contents).unwrap();
+        }
+
+        let loader = Loader::new(std::slice::from_ref(&dir));
+        let (theme, warnings) = loader.load_with_warnings("child").unwrap();
+        assert_eq!(theme.name(), "child");
+        // the palette of the child applies to the inherited styles
+        assert_eq!(theme.get("ui.text"), Style::default().fg(Color::Rgb(0, 0, 3)));
+        // styles are replaced, not merged
+        assert_eq!(theme.get("ui.popup"), Style::default().fg(Color::Rgb(0, 0, 3)));
+        assert_eq!(warnings, ["Theme: unknown color: unknown (in 'ui.menu')"]);
+
+        assert!(loader.load("cycle").is_err());
+        assert!(loader.load("missing").is_err());
+        assert_eq!(loader.load("default").unwrap().name(), DEFAULT_THEME_NAME);
+        assert_eq!(loader.names(), ["base", "base16_default", "child", "cycle", "default"]);
+    }
+
+    #[test]
+    fn inherit_theme_of_the_same_name() {
+        let user = tempfile::tempdir().unwrap();
+        let runtime = tempfile::tempdir().unwrap();
+        let dirs = [user.path().to_path_buf(), runtime.path().to_path_buf()];
+        for dir in &dirs {
+            std::fs::create_dir_all(dir.join("themes")).unwrap();
+        }
+        let write = |dir: &Path, contents: &str| std::fs::write(dir.join("themes/shared.toml"), contents).unwrap();
+        write(
+            &dirs[0],
+            r##"
+            inherits = "shared"
+            "ui.menu" = "#000001"
+            "##,
+        );
+        write(&dirs[1], r##""ui.text" = "#000002""##);
+
+        // the user theme extends the runtime theme it shadows
+        let loader = Loader::new(&dirs);
+        let theme = loader.load("shared").unwrap();
+        assert_eq!(theme.get("ui.menu"), Style::default().fg(Color::Rgb(0, 0, 1)));
+        assert_eq!(theme.get("ui.text"), Style::default().fg(Color::Rgb(0, 0, 2)));
+
+        // a cycle is still found once every file of the name was visited
+        write(&dirs[1], r#"inherits = "shared""#);
+        let err = loader.load("shared").unwrap_err();
+        assert_eq!(err.to_string(), "Theme: cycle found in inheriting theme: shared");
+    }
+
+    #[test]
+    fn downgrade_theme_colors() {
+        let theme = r##"
+            "ui.text" = "fg"
+            "ui.popup" = { fg = "#cd0000", bg = "red", underline = { color = "fg" } }
+            [palette]
+            fg = "#ffffff"
+        "##;
+        let (theme, warnings) = Theme::from_toml_with_color_support(toml::from_str(theme).unwrap(), ColorSupport::Ansi256);
+        assert!(warnings.is_empty());
+        assert_eq!(theme.get("ui.text"), Style::default().fg(Color::Indexed(231)));
+        assert_eq!(
+            theme.get("ui.popup"),
+            Style::default()
+                .fg(Color::Indexed(160))
+                .bg(Color::Red)
+                .underline_color(Color::Indexed(231))
+        );
+        assert!(!is_16_color(&theme));
+
+        let loader = Loader::new(&[]).with_color_support(ColorSupport::Ansi16);
+        let theme = loader.default();
+        assert_eq!(theme.name(), BASE16_DEFAULT_THEME_NAME);
+        assert!(is_16_color(&theme));
+        let theme = loader.load(DEFAULT_THEME_NAME).unwrap();
+        assert_eq!(theme.name(), DEFAULT_THEME_NAME);
+        assert!(is_16_color(&theme));
+        assert!(!is_16_color(&DEFAULT_THEME));
+    }
+}
@@ -16,10 +16,15 @@ pub struct Tree {
     stack: Vec<(ViewId, Rect)>,
 }
 
+/// Weight given to new nodes, see [`Node::weight`].
+const DEFAULT_WEIGHT: u16 = 4;
+
 #[derive(Debug)]
 pub struct Node {
     parent: ViewId,
     content: Content,
+    /// Size of the node relative to its siblings in the parent container.
+    weight: u16,
 }
 
 #[derive(Debug)]
@@ -33,6 +38,7 @@ impl Node {
         Self {
             parent: ViewId::default(),
             content: Content::Container(Box::new(Container::new(layout))),
+            weight: DEFAULT_WEIGHT,
         }
     }
 
@@ -40,6 +46,7 @@ impl Node {
         Self {
             parent: ViewId::default(),
             content: Content::View(Box::new(view)),
+            weight: DEFAULT_WEIGHT,
         }
     }
 }
@@ -111,6 +118,17 @@ impl Tree {
         })
     }
 
+    pub fn views_mut(&mut self) -> impl Iterator<Item = (&mut View, bool)> {
+        let focus = self.focus;
+        self.nodes.iter_mut().filter_map(move |(key, node)| match node {
+            Node {
+                content: Content::View(view),
+                ..
+            } => Some((view.as_mut(), focus == key)),
+            _ => None,
+        })
+    }
+
     /// Get reference to a View by index.
     /// # Panics
     ///
@@ -148,33 +166,61 @@ impl Tree {
         self.nodes.contains_key(index)
     }
 
+    /// Inserts a view next to the focused one in its container, or as the first view of an empty tree.
+    pub fn insert(&mut self, view: View) -> ViewId {
+        let focus = self.focus;
+        let parent = self.nodes[focus].parent;
+        let mut node = Node::view(view);
+        node.parent = parent;
+        let node = self.nodes.insert(node);
+        self.get_mut(node).id = node;
+
+        let container = self.container_mut(parent);
+        // insert node after the current item if there is children already
+        let pos = if container.children.is_empty() {
+            0
+        } else {
+            let pos = container.children.iter().position(|&child| child == focus).unwrap();
+            pos + 1
+        };
+        container.children.insert(pos, node);
+
+        // focus the new node
+        self.focus = node;
+
+        self.recalculate();
+
+        node
+    }
+
     pub fn split(&mut self, view: View, layout: Layout) -> ViewId {
         let focus = self.focus;
         let parent = self.nodes[focus].parent;
 
+        if self.is_empty() || self.container_mut(parent).layout == layout {
+            return self.insert(view);
+        }
+
+        // The focused view is replaced by a new container holding it and the new view.
         let node = Node::view(view);
         let node = self.nodes.insert(node);
         self.get_mut(node).id = node;
 
-        let container = match &mut self.nodes[parent] {
-            Node {
-                content: Content::Container(container),
-                ..
-            } => container,
-            _ => unreachable!(),
-        };
-        if container.layout == layout {
-            let pos = if container.children.is_empty() {
-                0
-            } else {
-                let pos = container.children.iter().position(|&child| child == focus).unwrap();
-                pos + 1
-            };
-            container.children.insert(pos, node);
-            self.nodes[node].parent = parent;
-        } else {
-            panic!("layout that does not match container is not implemented yet");
-        };
+        let mut split = Node::container(layout);
+        split.parent = parent;
+        split.weight = self.nodes[focus].weight;
+        let split = self.nodes.insert(split);
+
+        let container = self.container_mut(split);
+        container.children.push(focus);
+        container.children.push(node);
+        self.nodes[focus].parent = split;
+        self.nodes[focus].weight = DEFAULT_WEIGHT;
+        self.nodes[node].parent = split;
+
+        let container = self.container_mut(parent);
+        let pos = container.children.iter().position(|&child| child == focus).unwrap();
+        container.children[pos] = split;
 
         // focus the new node
         self.focus = node;
@@ -184,6 +230,71 @@ impl Tree {
         node
     }
 
+    pub fn remove(&mut self, index: ViewId) {
+        if self.focus == index {
+            // focus on something else
+            self.focus = self.prev();
+        }
+
+        let parent = self.nodes[index].parent;
+        let parent_is_root = parent == self.root;
+
+        self.remove_or_replace(index, None);
+
+        let parent_container = self.container_mut(parent);
+        if parent_container.children.len() == 1 && !parent_is_root {
+            // Merge the only child back into its grandparent so that views are equally spaced.
+            let sibling = parent_container.children.pop().unwrap();
+            self.nodes[sibling].weight = self.nodes[parent].weight;
+            self.remove_or_replace(parent, Some(sibling));
+        }
+
+        self.recalculate()
+    }
+
+    fn remove_or_replace(&mut self, child: ViewId, replacement: Option<ViewId>) {
+        let parent = self.nodes[child].parent;
+
+        self.nodes.remove(child);
+
+        let container = self.container_mut(parent);
+        let pos = container.children.iter().position(|&item| item == child).unwrap();
+
+        if let Some(new) = replacement {
+            container.children[pos] = new;
+            self.nodes[new].parent = parent;
+        } else {
+            container.children.remove(pos);
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `index` is not a container.
+    fn container_mut(&mut self, index: ViewId) -> &mut Container {
+        match &mut self.nodes[index] {
+            Node {
+                content: Content::Container(container),
+                ..
+            } => container,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Iterates over the views in the order they are laid out.
+    pub fn traverse(&self) -> Traverse<'_> {
+        Traverse::new(self)
+    }
+
+    /// The view laid out before the focused one, wrapping around to the last view.
+    pub fn prev(&self) -> ViewId {
+        let views: Vec<ViewId> = self.traverse().map(|(key, _)| key).collect();
+        match views.iter().position(|&key| key == self.focus) {
+            Some(0) | None => views.last().copied().unwrap_or(self.root),
+            Some(pos) => views[pos - 1],
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         match &self.nodes[self.root] {
             Node {
@@ -219,40 +330,44 @@ impl Tree {
                 Content::Container(container) => {
                     container.area = area;
 
+                    let container = match &self.nodes[key].content {
+                        Content::Container(container) => container,
+                        Content::View(_) => unreachable!(),
+                    };
+                    let total: u32 = container.children.iter().map(|&child| self.nodes[child].weight as u32).sum();
+                    let len = container.children.len();
+
                     match container.layout {
                         Layout::Horizontal => {
-                            let len = container.children.len();
-                            let height = area.height / len as u16;
                             let mut child_y = area.y;
-                            for (i, child) in container.children.iter().enumerate() {
-                                let mut area = Rect::new(container.area.x, child_y, container.area.width, height);
+                            for (i, &child) in container.children.iter().enumerate() {
+                                let weight = self.nodes[child].weight as u32;
+                                let height = if i == len - 1 {
+                                    area.bottom().saturating_sub(child_y)
+                                } else {
+                                    (area.height as u32 * weight / total) as u16
+                                };
+
+                                self.stack.push((child, Rect::new(area.x, child_y, area.width, height)));
                                 child_y += height;
-
-                                if i == len - 1 {
-                                    area.height = container.area.y + container.area.height - area.y;
-                                }
-
-                                self.stack.push((*child, area));
                             }
                         }
                         Layout::Vertical => {
-                            let len = container.children.len();
-
-                            let width = area.width / len as u16;
-
+                            // one column between views for the separator
                             let inner_gap = 1u16;
+                            let available = area.width.saturating_sub(inner_gap * (len as u16 - 1));
 
                             let mut child_x = area.x;
-
-                            for (i, child) in container.children.iter().enumerate() {
-                                let mut area = Rect::new(child_x, container.area.y, width, container.area.height);
-                                child_x += width + inner_gap;
-
-                                if i == len - 1 {
-                                    area.width = container.area.x + container.area.width - area.x;
-                                }
-
-                                self.stack.push((*child, area));
+                            for (i, &child) in container.children.iter().enumerate() {
+                                let weight = self.nodes[child].weight as u32;
+                                let width = if i == len - 1 {
+                                    area.right().saturating_sub(child_x)
+                                } else {
+                                    (available as u32 * weight / total) as u16
+                                };
+
+                                self.stack.push((child, Rect::new(child_x, area.y, width, area.height)));
+                                child_x = (child_x + width + inner_gap).min(area.right());
                             }
                         }
                     }
@@ -260,4 +375,249 @@ impl Tree {
             }
         }
     }
+
+    /// Finds the view next to `id` in the given direction.
+    pub fn find_split_in_direction(&self, id: ViewId, direction: Direction) -> Option<ViewId> {
+        let parent = self.nodes[id].parent;
+        // Base case, we found the root of the tree
+        if parent == id {
+            return None;
+        }
+        // Parent must always be a container
+        let parent_container = match &self.nodes[parent].content {
+            Content::Container(container) => container,
+            Content::View(_) => unreachable!(),
+        };
+
+        match (direction, parent_container.layout) {
+            (Direction::Up, Layout::Vertical)
+            | (Direction::Left, Layout::Horizontal)
+            | (Direction::Right, Layout::Horizontal)
+            | (Direction::Down, Layout::Vertical) => {
+                // The desired direction of movement is not possible within
+                // the parent container so the search must continue closer to
+                // the root of the split tree.
+                self.find_split_in_direction(parent, direction)
+            }
+            (Direction::Up, Layout::Horizontal)
+            | (Direction::Down, Layout::Horizontal)
+            | (Direction::Left, Layout::Vertical)
+            | (Direction::Right, Layout::Vertical) => {
+                // It's possible to move in the desired direction within
+                // the parent container so an attempt is made to find the
+                // correct child.
+                match self.find_child(id, &parent_container.children, direction) {
+                    // Child is found, search is ended
+                    Some(id) => Some(id),
+                    // A child is not found. This could be because of either two scenarios
+                    // 1. Its not possible to move in the desired direction, and search should end
+                    // 2. A child was found, but the search should continue closer to the root of the split tree
+                    None => self.find_split_in_direction(parent, direction),
+                }
+            }
+        }
+    }
+
+    fn find_child(&self, id: ViewId, children: &[ViewId], direction: Direction) -> Option<ViewId> {
+        let mut child_id = match direction {
+            // index wise in the child list the Up and Left represents a -1
+            // thus reversed iterator.
+            Direction::Up | Direction::Left => children.iter().rev().skip_while(|i| **i != id).copied().nth(1)?,
+            // Down and Right => +1 index wise in the child list
+            Direction::Down | Direction::Right => children.iter().skip_while(|i| **i != id).copied().nth(1)?,
+        };
+        let (current_x, current_y) = match &self.nodes[self.focus].content {
+            Content::View(current_view) => (current_view.area.left(), current_view.area.top()),
+            Content::Container(_) => unreachable!(),
+        };
+
+        // If the child is a container the search finds the closest container child
+        // visually based on screen location.
+        while let Content::Container(container) = &self.nodes[child_id].content {
+            child_id = *container.children.iter().min_by_key(|id| {
+                let area = match &self.nodes[**id].content {
+                    Content::View(view) => view.area,
+                    Content::Container(container) => container.area,
+                };
+                match container.layout {
+                    // find closest split based on x because y is irrelevant
+                    // in a vertical container (and already correct based on previous search)
+                    Layout::Vertical => (current_x as i32 - area.left() as i32).abs(),
+                    // find closest split based on y because x is irrelevant
+                    // in a horizontal container (and already correct based on previous search)
+                    Layout::Horizontal => (current_y as i32 - area.top() as i32).abs(),
+                }
+            })?;
+        }
+        Some(child_id)
+    }
+
+    /// Swaps the focused view with the view next to it in the given direction.
+    pub fn swap_split_in_direction(&mut self, direction: Direction) -> Option<()> {
+        let focus = self.focus;
+        let target = self.find_split_in_direction(focus, direction)?;
+        let focus_parent = self.nodes[focus].parent;
+        let target_parent = self.nodes[target].parent;
+
+        // swap node positions so that traversal order is kept
+        let focus_pos = self.container_mut(focus_parent).children.iter().position(|&id| id == focus)?;
+        let target_pos = self
+            .container_mut(target_parent)
+            .children
+            .iter()
+            .position(|&id| id == target)?;
+        self.container_mut(focus_parent).children[focus_pos] = target;
+        self.container_mut(target_parent).children[target_pos] = focus;
+        self.nodes[focus].parent = target_parent;
+        self.nodes[target].parent = focus_parent;
+
+        // the sizes stay in place, only the views move
+        let [focus, target] = self.nodes.get_disjoint_mut([focus, target])?;
+        std::mem::swap(&mut focus.weight, &mut target.weight);
+
+        self.recalculate();
+        Some(())
+    }
+
+    /// Flips the layout of the container holding the focused view.
+    pub fn transpose(&mut self) {
+        let parent = self.nodes[self.focus].parent;
+        let container = self.container_mut(parent);
+        container.layout = match container.layout {
+            Layout::Vertical => Layout::Horizontal,
+            Layout::Horizontal => Layout::Vertical,
+        };
+        self.recalculate();
+    }
+
+    /// Sets the area available to the tree, recalculating the layout if it changed.
+    /// Returns whether the area changed.
+    pub fn set_area(&mut self, area: Rect) -> bool {
+        if self.area != area {
+            self.area = area;
+            self.recalculate();
+            return true;
+        }
+        false
+    }
+
+    /// Grows (or shrinks for a negative `amount`) the focused view relative to its siblings.
+    pub fn resize(&mut self, amount: i16) {
+        let node = &mut self.nodes[self.focus];
+        node.weight = node.weight.saturating_add_signed(amount).max(1);
+        self.recalculate();
+    }
+}
+
+#[derive(Debug)]
+pub struct Traverse<'a> {
+    tree: &'a Tree,
+    stack: Vec<ViewId>,
+}
+
+impl<'a> Traverse<'a> {
+    fn new(tree: &'a Tree) -> Self {
+        Self {
+            tree,
+            stack: vec![tree.root],
+        }
+    }
+}
+
+impl<'a> Iterator for Traverse<'a> {
+    type Item = (ViewId, &'a View);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key = self.stack.pop()?;
+
+            match &self.tree.nodes[key].content {
+                Content::View(view) => return Some((key, view)),
+                Content::Container(container) => {
+                    self.stack.extend(container.children.iter().rev());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::view::DocumentId;
+
+    #[test]
+    fn find_split_in_direction() {
+        let mut tree = Tree::new(Rect {
+            x: 0,
+            y: 0,
+            width: 180,
+            height: 80,
+        });
+        let l0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        let r0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        let r1 = tree.split(View::new(DocumentId::default()), Layout::Horizontal);
+
+        tree.focus = l0;
+        assert_eq!(tree.find_split_in_direction(l0, Direction::Right), Some(r0));
+        assert_eq!(tree.find_split_in_direction(l0, Direction::Left), None);
+        assert_eq!(tree.find_split_in_direction(l0, Direction::Down), None);
+
+        tree.focus = r1;
+        assert_eq!(tree.find_split_in_direction(r1, Direction::Up), Some(r0));
+        assert_eq!(tree.find_split_in_direction(r1, Direction::Left), Some(l0));
+
+        // removing r1 merges r0 back into the root container
+        tree.remove(r1);
+        assert_eq!(tree.focus, r0);
+        assert_eq!(tree.nodes[r0].parent, tree.root);
+        assert_eq!(tree.get(r0).area, Rect::new(90, 0, 90, 80));
+    }
+
+    #[test]
+    fn remove_views() {
+        let mut tree = Tree::new(Rect::new(0, 0, 80, 80));
+        let l0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        let m0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        let r0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        assert_eq!(tree.focus, r0);
+
+        // the view laid out before the focused one gets the focus
+        tree.focus = m0;
+        tree.remove(m0);
+        assert_eq!(tree.focus, l0);
+        assert!(!tree.contains(m0));
+        assert_eq!(tree.get(l0).area, Rect::new(0, 0, 39, 80));
+        assert_eq!(tree.get(r0).area, Rect::new(40, 0, 40, 80));
+
+        // removing a view that isn't focused keeps the focus
+        tree.remove(r0);
+        assert_eq!(tree.focus, l0);
+        assert_eq!(tree.get(l0).area, Rect::new(0, 0, 80, 80));
+
+        tree.remove(l0);
+        assert!(tree.is_empty());
+        assert_eq!(tree.views().count(), 0);
+    }
+
+    #[test]
+    fn swap_transpose_and_resize() {
+        let mut tree = Tree::new(Rect::new(0, 0, 81, 80));
+        let l0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        let r0 = tree.split(View::new(DocumentId::default()), Layout::Vertical);
+        assert_eq!(tree.get(l0).area, Rect::new(0, 0, 40, 80));
+        assert_eq!(tree.get(r0).area, Rect::new(41, 0, 40, 80));
+
+        tree.swap_split_in_direction(Direction::Left);
+        assert_eq!(tree.get(r0).area, Rect::new(0, 0, 40, 80));
+        assert_eq!(tree.traverse().map(|(id, _)| id).collect::<Vec<_>>(), vec![r0, l0]);
+
+        tree.resize(4);
+        assert_eq!(tree.get(r0).area, Rect::new(0, 0, 53, 80));
+        assert_eq!(tree.get(l0).area, Rect::new(54, 0, 27, 80));
+
+        tree.transpose();
+        assert_eq!(tree.get(r0).area, Rect::new(0, 0, 81, 53));
+        assert_eq!(tree.get(l0).area, Rect::new(0, 53, 81, 27));
+    }
 }
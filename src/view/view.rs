@@ -1,10 +1,13 @@
 use std::fmt;
 
-use super::{document::Document, graphics::Rect, theme::Theme, DocumentId};
+use super::{document::Document, editor::GutterType, graphics::Rect, theme::Theme, DocumentId};
 use crate::{
     core::{
+        line_ending::line_end_char_index,
         position::{char_idx_at_visual_offset, visual_offset_from_anchor, visual_offset_from_block},
-        text_annotations::TextAnnotations,
+        syntax::Highlight,
+        text_annotations::{InlineAnnotation, TextAnnotations},
+        Position, RopeSlice,
         VisualOffsetError::{PosAfterMaxRow, PosBeforeAnchorRow},
     },
     view::ViewId,
@@ -23,6 +26,8 @@ pub struct View {
     pub offset: ViewPosition,
     pub area: Rect,
     pub doc: DocumentId,
+    /// The documents previously shown in this view, the most recently accessed one last.
+    pub docs_access_history: Vec<DocumentId>,
 }
 
 impl fmt::Debug for View {
@@ -46,6 +51,101 @@ impl View {
                 vertical_offset: 0,
             },
             area: Rect::default(), // will get calculated upon inserting into tree
+            docs_access_history: Vec::new(),
+        }
+    }
+
+    pub fn add_to_history(&mut self, id: DocumentId) {
+        if let Some(pos) = self.docs_access_history.iter().position(|&doc| doc == id) {
+            self.docs_access_history.remove(pos);
+        }
+        self.docs_access_history.push(id);
+    }
+
+    /// Forgets a document that is being closed.
+    pub fn remove_document(&mut self, doc_id: &DocumentId) {
+        self.docs_access_history.retain(|doc| doc != doc_id);
+    }
+
+    /// Translates a document position to an absolute position in the terminal.
+    /// Returns a (0-indexed) position relative to the inner area, or `None` if the position is
+    /// not visible on screen.
+    pub fn screen_coords_at_pos(&self, doc: &Document, text: RopeSlice, pos: usize) -> Option<Position> {
+        if pos < self.offset.anchor {
+            // Line is not visible on screen
+            return None;
+        }
+
+        let viewport = self.inner_area(doc);
+        let text_fmt = doc.text_format(viewport.width, None);
+        let annotations = self.text_annotations(doc, None);
+
+        let mut pos = visual_offset_from_anchor(
+            text,
+            self.offset.anchor,
+            pos,
+            &text_fmt,
+            &annotations,
+            viewport.height as usize,
+        )
+        .ok()?
+        .0;
+        if pos.row < self.offset.vertical_offset {
+            return None;
+        }
+        pos.row -= self.offset.vertical_offset;
+        if pos.row >= viewport.height as usize {
+            return None;
+        }
+        if pos.col < self.offset.horizontal_offset {
+            return None;
+        }
+        pos.col -= self.offset.horizontal_offset;
+        if pos.col >= viewport.width as usize {
+            return None;
+        }
+
+        Some(pos)
+    }
+
+    /// Translates a screen position to the document position under it.
+    /// Returns `None` if the position lies outside the text area of the view. With
+    /// `ignore_virtual_text` set, positions on lines made up only of virtual text return `None`.
+    pub fn pos_at_screen_coords(&self, doc: &Document, row: u16, column: u16, ignore_virtual_text: bool) -> Option<usize> {
+        let inner = self.inner_area(doc);
+        if row < inner.top() || row >= inner.bottom() {
+            return None;
+        }
+
+        if column < inner.left() || column >= inner.right() {
+            return None;
+        }
+
+        self.text_pos_at_visual_coords(doc, row - inner.y, column - inner.x, ignore_virtual_text)
+    }
+
+    /// Translates a position relative to the top left corner of the text area to a document
+    /// position, taking the view offset into account.
+    pub fn text_pos_at_visual_coords(&self, doc: &Document, row: u16, column: u16, ignore_virtual_text: bool) -> Option<usize> {
+        let text = doc.text().slice(..);
+        let viewport = self.inner_area(doc);
+        let text_fmt = doc.text_format(viewport.width, None);
+        let annotations = self.text_annotations(doc, None);
+
+        let (char_idx, virt_lines) = char_idx_at_visual_offset(
+            text,
+            self.offset.anchor,
+            (row as usize + self.offset.vertical_offset) as isize,
+            column as usize + self.offset.horizontal_offset,
+            &text_fmt,
+            &annotations,
+        );
+
+        // if the cursor is on a line with only virtual text return None
+        if virt_lines != 0 && ignore_virtual_text {
+            None
+        } else {
+            Some(char_idx)
         }
     }
 
@@ -53,14 +153,67 @@ impl View {
         self.area.clip_left(self.gutter_offset(doc)).clip_bottom(1) // -1 for statusline
     }
 
-    pub fn gutter_offset(&self, _doc: &Document) -> u16 {
-        // TODO: impl
-        2
+    pub fn gutters(&self, doc: &Document) -> Vec<GutterType> {
+        doc.config.load().gutters.clone()
+    }
+
+    /// The total width of the gutters, or 0 if they would not leave room for any text.
+    pub fn gutter_offset(&self, doc: &Document) -> u16 {
+        let total_width = self.gutters(doc).iter().map(|gutter| gutter.width(self, doc) as u16).sum();
+        if total_width < self.area.width {
+            total_width
+        } else {
+            0
+        }
+    }
+
+    /// The last document line visible in the view, assuming no soft wrapping.
+    pub fn estimate_last_doc_line(&self, doc: &Document) -> usize {
+        let doc_text = doc.text().slice(..);
+        let line = doc_text.char_to_line(self.offset.anchor.min(doc_text.len_chars()));
+        // Saturating subs to make it inclusive zero indexing.
+        (line + self.inner_area(doc).height as usize)
+            .min(doc_text.len_lines())
+            .saturating_sub(1)
     }
 
+    /// The virtual text of the document in this view. The message of the most severe diagnostic
+    /// of a line is shown at its end, unless a cursor is on the line so that it doesn't get in
+    /// the way of editing.
     pub fn text_annotations(&self, doc: &Document, theme: Option<&Theme>) -> TextAnnotations {
-        doc.text_annotations(theme)
-        // TODO
+        let mut text_annotations = doc.text_annotations(theme);
+
+        let text = doc.text().slice(..);
+        let cursor_lines: Vec<_> = doc.selection(self.id).iter().map(|range| range.cursor_line(text)).collect();
+
+        // a layer per severity, from hint to error
+        let mut layers: [Vec<InlineAnnotation>; 4] = Default::default();
+        for diagnostics in doc.diagnostics().chunk_by(|a, b| a.line == b.line) {
+            let line = diagnostics[0].line;
+            if cursor_lines.contains(&line) || line >= text.len_lines() {
+                continue;
+            }
+            // the first of the most severe ones
+            let diagnostic = diagnostics
+                .iter()
+                .rev()
+                .max_by_key(|diagnostic| diagnostic.severity())
+                .unwrap();
+            let message = diagnostic.message.lines().next().unwrap_or_default();
+            layers[diagnostic.severity() as usize].push(InlineAnnotation {
+                text: format!("  {message}").into(),
+                char_idx: line_end_char_index(&text, line),
+            });
+        }
+
+        for (layer, scope) in layers.into_iter().zip(["hint", "info", "warning", "error"]) {
+            if !layer.is_empty() {
+                let highlight = theme.and_then(|theme| theme.find_scope_index(scope)).map(Highlight);
+                text_annotations.add_inline_annotations(layer.into(), highlight);
+            }
+        }
+
+        text_annotations
     }
 
     pub fn offset_coords_to_in_view_center<const CENTERING: bool>(
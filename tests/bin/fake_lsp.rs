@@ -0,0 +1,381 @@
+//! A minimal language server used by the integration tests.
+//!
+//! It keeps the text of the open documents up to date with incremental changes, and answers the
+//! custom `fake/state` request with what it knows, so that tests can check what the editor sent.
+//! Every `TODO` in a document is reported as a warning and every `FIXME` as an error, and words
+//! are completed with a few fruits.
+//!
+//! Names are navigated by their text in the open documents: `struct Name` and `fn name` define
+//! them, and `impl Name` implements them. An indented `fn` is a method of the unindented symbol
+//! before it. Types aren't known, so there are no type definitions.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+#[derive(Default)]
+struct State {
+    root_uri: Value,
+    /// The text of the open documents, by uri.
+    documents: BTreeMap<String, String>,
+    /// The text sent with the last save of each document, by uri.
+    saved: BTreeMap<String, Value>,
+    /// The methods of the notifications received, in order.
+    notifications: Vec<String>,
+    /// The context of each completion request, in order.
+    completions: Vec<Value>,
+    /// The query of each workspace symbols request, in order.
+    symbol_queries: Vec<String>,
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut stdout = io::stdout().lock();
+    let mut state = State::default();
+
+    while let Some(message) = read_message(&mut reader)? {
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = &message["params"];
+
+        let Some(id) = message.get("id") else {
+            state.notifications.push(method.clone());
+            match method.as_str() {
+                "exit" => return Ok(()),
+                "textDocument/didOpen" => {
+                    let document = &params["textDocument"];
+                    let text = document["text"].as_str().unwrap().to_string();
+                    publish_diagnostics(&mut stdout, document, &text)?;
+                    state.documents.insert(uri(document), text);
+                }
+                "textDocument/didChange" => {
+                    let text = state.documents.get_mut(&uri(&params["textDocument"])).unwrap();
+                    for change in params["contentChanges"].as_array().unwrap() {
+                        apply_change(text, change);
+                    }
+                    publish_diagnostics(&mut stdout, &params["textDocument"], text)?;
+                }
+                "textDocument/didSave" => {
+                    state.saved.insert(uri(&params["textDocument"]), params["text"].clone());
+                }
+                "textDocument/didClose" => {
+                    state.documents.remove(&uri(&params["textDocument"]));
+                }
+                _ => {}
+            }
+            continue;
+        };
+
+        let result = match method.as_str() {
+            "initialize" => {
+                state.root_uri = params["rootUri"].clone();
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": {
+                            "openClose": true,
+                            "change": 2,
+                            "save": { "includeText": true },
+                        },
+                        "completionProvider": {
+                            "triggerCharacters": ["."],
+                            "resolveProvider": true,
+                        },
+                        "definitionProvider": true,
+                        "declarationProvider": true,
+                        "typeDefinitionProvider": true,
+                        "implementationProvider": true,
+                        "referencesProvider": true,
+                        "documentSymbolProvider": true,
+                        "workspaceSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "fake-lsp" },
+                })
+            }
+            "shutdown" => Value::Null,
+            "textDocument/completion" => {
+                state.completions.push(params["context"].clone());
+                completion_list(&state.documents[&uri(&params["textDocument"])], &params["position"])
+            }
+            "completionItem/resolve" => {
+                let mut item = params.clone();
+                if item["label"] == "apple" {
+                    item["documentation"] = json!("Picks an apple from the tree.");
+                }
+                item
+            }
+            "textDocument/definition" => {
+                let word = word_at(&state.documents[&uri(&params["textDocument"])], &params["position"]);
+                definitions(&state.documents, word).into_iter().next().unwrap_or(Value::Null)
+            }
+            "textDocument/declaration" => {
+                // answered with links, to the definitions
+                let word = word_at(&state.documents[&uri(&params["textDocument"])], &params["position"]);
+                let links = definitions(&state.documents, word).into_iter().map(|location| {
+                    json!({
+                        "targetUri": location["uri"],
+                        "targetRange": location["range"],
+                        "targetSelectionRange": location["range"],
+                    })
+                });
+                Value::Array(links.collect())
+            }
+            "textDocument/typeDefinition" => Value::Null,
+            "textDocument/implementation" => {
+                let word = word_at(&state.documents[&uri(&params["textDocument"])], &params["position"]);
+                json!(find_words(&state.documents, &format!("impl {word}"), word.len()))
+            }
+            "textDocument/references" => {
+                let word = word_at(&state.documents[&uri(&params["textDocument"])], &params["position"]);
+                json!(find_words(&state.documents, word, word.len()))
+            }
+            "textDocument/documentSymbol" => document_symbols(&state.documents[&uri(&params["textDocument"])]),
+            "workspace/symbol" => {
+                let query = params["query"].as_str().unwrap().to_string();
+                let mut symbols = Vec::new();
+                for (uri, text) in &state.documents {
+                    flatten_symbols(uri, &document_symbols(text), &mut symbols);
+                }
+                symbols.retain(|symbol| {
+                    let name = symbol["name"].as_str().unwrap().to_lowercase();
+                    name.contains(&query.to_lowercase())
+                });
+                state.symbol_queries.push(query);
+                json!(symbols)
+            }
+            "fake/state" => json!({
+                "rootUri": state.root_uri,
+                "documents": state.documents,
+                "saved": state.saved,
+                "notifications": state.notifications,
+                "completions": state.completions,
+                "symbolQueries": state.symbol_queries,
+            }),
+            _ => {
+                let error = json!({ "code": -32601, "message": format!("Method not found: {method}") });
+                write_message(&mut stdout, &json!({ "jsonrpc": "2.0", "error": error, "id": id }))?;
+                continue;
+            }
+        };
+        write_message(&mut stdout, &json!({ "jsonrpc": "2.0", "result": result, "id": id }))?;
+    }
+
+    Ok(())
+}
+
+fn uri(text_document: &Value) -> String {
+    text_document["uri"].as_str().unwrap().to_string()
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        match header.trim_end().split_once(": ") {
+            Some(("Content-Length", length)) => content_length = length.parse().unwrap(),
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
+
+/// Reports the `TODO`s and `FIXME`s of the version of `text_document` whose text is `text`.
+fn publish_diagnostics(writer: &mut impl Write, text_document: &Value, text: &str) -> io::Result<()> {
+    let mut diagnostics = Vec::new();
+    for (line, content) in text.lines().enumerate() {
+        for (word, severity, message) in [("TODO", 2, "found a TODO"), ("FIXME", 1, "found a FIXME")] {
+            for (offset, _) in content.match_indices(word) {
+                // positions are counted in UTF-16 code units
+                let start = content[..offset].encode_utf16().count();
+                let range = json!({
+                    "start": { "line": line, "character": start },
+                    "end": { "line": line, "character": start + word.len() },
+                });
+                diagnostics.push(json!({ "range": range, "severity": severity, "message": message }));
+            }
+        }
+    }
+
+    let params = json!({
+        "uri": text_document["uri"],
+        "version": text_document["version"],
+        "diagnostics": diagnostics,
+    });
+    write_message(
+        writer,
+        &json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": params }),
+    )
+}
+
+/// Completes the word before `position` with fruits: a function replacing the word, a variable
+/// imported by an additional edit and a snippet with placeholders. The documentation of the
+/// function is only sent when it is resolved.
+fn completion_list(text: &str, position: &Value) -> Value {
+    let end = offset(text, position);
+    let start = text[..end]
+        .trim_end_matches(|ch: char| ch.is_alphanumeric() || ch == '_')
+        .len();
+    let character = position["character"].as_u64().unwrap() as usize;
+    let word = json!({
+        "start": { "line": position["line"], "character": character - text[start..end].encode_utf16().count() },
+        "end": position,
+    });
+    let file_start = json!({ "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } });
+
+    json!({
+        "isIncomplete": false,
+        "items": [
+            {
+                "label": "apple",
+                "kind": 3,
+                "detail": "fn apple() -> Fruit",
+                "textEdit": { "range": word, "newText": "apple()" },
+            },
+            {
+                "label": "apricot",
+                "kind": 6,
+                "insertText": "apricot",
+                "additionalTextEdits": [{ "range": file_start, "newText": "use fruits::apricot;\n" }],
+            },
+            {
+                "label": "banana",
+                "kind": 3,
+                "insertTextFormat": 2,
+                "textEdit": { "range": word, "newText": "banana(${1:count}, ${2:ripe})$0" },
+            },
+        ],
+    })
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// The word around `position`.
+fn word_at<'a>(text: &'a str, position: &Value) -> &'a str {
+    let offset = offset(text, position);
+    let start = text[..offset].trim_end_matches(is_word).len();
+    let end = text[offset..].find(|ch| !is_word(ch)).map_or(text.len(), |len| offset + len);
+    &text[start..end]
+}
+
+/// The locations of the matches of `pattern` in the documents that aren't part of a longer
+/// word. The range of each is its last `word_len` bytes.
+fn find_words(documents: &BTreeMap<String, String>, pattern: &str, word_len: usize) -> Vec<Value> {
+    let mut locations = Vec::new();
+    if pattern.is_empty() {
+        return locations;
+    }
+    for (uri, text) in documents {
+        for (start, _) in text.match_indices(pattern) {
+            let end = start + pattern.len();
+            let before = text[..start].chars().next_back();
+            let after = text[end..].chars().next();
+            if before.is_some_and(is_word) || after.is_some_and(is_word) {
+                continue;
+            }
+            let range = json!({ "start": position(text, end - word_len), "end": position(text, end) });
+            locations.push(json!({ "uri": uri, "range": range }));
+        }
+    }
+    locations
+}
+
+/// The locations of the definitions of `word`.
+fn definitions(documents: &BTreeMap<String, String>, word: &str) -> Vec<Value> {
+    let mut locations = find_words(documents, &format!("struct {word}"), word.len());
+    locations.extend(find_words(documents, &format!("fn {word}"), word.len()));
+    locations
+}
+
+/// The `struct`s and `fn`s of `text`, with the methods as children.
+fn document_symbols(text: &str) -> Value {
+    let mut symbols: Vec<Value> = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_start();
+        let indented = content.len() < line.len();
+        let fn_kind = if indented { 6 } else { 12 };
+        let symbol = [("struct ", 23), ("fn ", fn_kind)].into_iter().find_map(|(keyword, kind)| {
+            let rest = content.strip_prefix(keyword)?;
+            let name = &rest[..rest.find(|ch| !is_word(ch)).unwrap_or(rest.len())];
+            let start = line_start + line.len() - rest.len();
+            let range = json!({ "start": position(text, start), "end": position(text, start + name.len()) });
+            Some(json!({ "name": name, "kind": kind, "range": range, "selectionRange": range, "children": [] }))
+        });
+        line_start += line.len();
+
+        match (symbol, symbols.last_mut()) {
+            (Some(symbol), Some(parent)) if indented => parent["children"].as_array_mut().unwrap().push(symbol),
+            (Some(symbol), _) => symbols.push(symbol),
+            (None, _) => {}
+        }
+    }
+    json!(symbols)
+}
+
+/// Adds the symbols of the document at `uri` and their children to `flat`, as symbol
+/// informations.
+fn flatten_symbols(uri: &str, symbols: &Value, flat: &mut Vec<Value>) {
+    for symbol in symbols.as_array().unwrap() {
+        flat.push(json!({
+            "name": symbol["name"],
+            "kind": symbol["kind"],
+            "location": { "uri": uri, "range": symbol["selectionRange"] },
+        }));
+        flatten_symbols(uri, &symbol["children"], flat);
+    }
+}
+
+/// The position of a byte offset, whose character is counted in UTF-16 code units.
+fn position(text: &str, offset: usize) -> Value {
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    json!({
+        "line": text[..offset].matches('\n').count(),
+        "character": text[line_start..offset].encode_utf16().count(),
+    })
+}
+
+/// Replaces the range of `change` in `text`, or the whole text if it has no range.
+fn apply_change(text: &mut String, change: &Value) {
+    let new_text = change["text"].as_str().unwrap();
+    if change["range"].is_null() {
+        *text = new_text.to_string();
+        return;
+    }
+
+    let start = offset(text, &change["range"]["start"]);
+    let end = offset(text, &change["range"]["end"]);
+    text.replace_range(start..end, new_text);
+}
+
+/// The byte offset of a position, whose character is counted in UTF-16 code units.
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap() as usize;
+    let character = position["character"].as_u64().unwrap() as usize;
+
+    let line_start: usize = text.split_inclusive('\n').take(line).map(str::len).sum();
+    let mut units = 0;
+    for (offset, ch) in text[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return line_start + offset;
+        }
+        units += ch.len_utf16();
+    }
+    text.len()
+}
@@ -0,0 +1,308 @@
+mod test {
+    mod helpers;
+    // needs the `fake-lsp` binary, built with `--features test-lsp`
+    #[cfg(feature = "test-lsp")]
+    mod lsp;
+
+    use crossterm::event::{Event as CrosstermEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use toy_helix::{
+        config::Config,
+        core::{line_ending::LineEnding, Range, Selection},
+        doc,
+        view::{
+            editor::{BufferLine, StatusLineElement as E},
+            graphics::CursorKind,
+        },
+    };
+
+    use self::helpers::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn insert_text() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new().with_input_text("world\n").build()?;
+
+        test_key_sequence(&mut app, "ihello <esc>", |app| {
+            assert_eq!(doc_text(app), "hello world\n");
+            assert_eq!(selection(app).primary(), Range::new(6, 7));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn convert_line_endings() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new().with_input_text("one\ntwo\r\nthree\n").build()?;
+
+        // every line ending is converted, the ones that already match are kept
+        test_key_sequence(&mut app, ":line-ending crlf<ret>", |app| {
+            assert_eq!(doc_text(app), "one\r\ntwo\r\nthree\r\n");
+            assert_eq!(doc!(app.editor).line_ending, LineEnding::Crlf);
+        })
+        .await?;
+
+        test_key_sequence(&mut app, ":line-ending lf<ret>:line-ending<ret>", |app| {
+            assert_eq!(doc_text(app), "one\ntwo\nthree\n");
+            assert_eq!(screen(app).lines().last(), Some("LF"));
+        })
+        .await?;
+
+        // an unknown line ending leaves the document as is
+        test_key_sequence(&mut app, ":line-ending nope<ret>", |app| {
+            assert_eq!(doc_text(app), "one\ntwo\nthree\n");
+            assert!(screen(app).contains("invalid line ending 'nope'"));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn render_screen() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new()
+            .with_input_text("one\ntwo\nthree\n")
+            .with_size(40, 6)
+            .build()?;
+
+        // split the view, the new one is focused and matches the cursor position of the old one
+        test_key_sequence(&mut app, "jl<C-w>v:nonexistent<ret>", |app| {
+            let expected = concat!(
+                "  1  one           │  1  one\n",
+                "  2  two           │  2  two\n",
+                "  3  three         │  3  three\n",
+                "  ~                │  ~\n",
+                "    1 sel  2:2  LF │ NOR 1 sel  2:2  LF\n",
+                "no such command: 'nonexistent'\n",
+            );
+            assert_eq!(screen(app), expected);
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn custom_statusline() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let statusline = &mut config.editor.statusline;
+        statusline.left = vec![E::Mode];
+        statusline.center = vec![E::FileType];
+        statusline.right = vec![E::TotalLineNumbers, E::Separator, E::Position];
+        statusline.mode.insert = "I".into();
+        let mut app = AppBuilder::new()
+            .with_input_text("one\ntwo\n")
+            .with_config(config)
+            .with_size(30, 4)
+            .build()?;
+
+        // the center is centered in the view, the right side is aligned to its right edge
+        test_key_sequence(&mut app, "i", |app| {
+            let screen = screen(app);
+            let statusline = screen.lines().nth(2).unwrap();
+            assert_eq!(statusline, format!(" I {:9} text {:3} 3 │ 1:1", "", ""));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn terminal_cursor() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.editor.cursor_shape = toml::from_str("insert = \"bar\"")?;
+        let mut app = AppBuilder::new()
+            .with_input_text("hello\n")
+            .with_config(config)
+            .with_size(20, 4)
+            .build()?;
+
+        // block cursors are drawn by the editor, the terminal cursor stays hidden
+        test_key_sequence(&mut app, "", |app| {
+            assert_eq!(app.backend().cursor(), None);
+        })
+        .await?;
+
+        test_key_sequence(&mut app, "i", |app| {
+            let col = screen(app).lines().next().unwrap().find('h').unwrap() as u16;
+            assert_eq!(app.backend().cursor(), Some(CursorKind::Bar));
+            assert_eq!(app.backend().cursor_position(), (col, 0));
+        })
+        .await?;
+
+        // the prompt on top of the editor gets the cursor, after the `:`
+        test_key_sequence(&mut app, "<esc>:w", |app| {
+            assert_eq!(app.backend().cursor(), Some(CursorKind::Bar));
+            assert_eq!(app.backend().cursor_position(), (2, 3));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn narrow_view_hides_gutters() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new().with_input_text("a\n").with_size(5, 3).build()?;
+
+        // the gutters would take the whole width, the text is drawn without them
+        test_key_sequence(&mut app, "", |app| {
+            assert_eq!(screen(app).lines().next(), Some("a"));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn soft_wrap_gutters() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.editor.soft_wrap.enable = Some(true);
+        let mut app = AppBuilder::new()
+            .with_input_text("one two three four five\nsix\n")
+            .with_config(config)
+            .with_size(20, 5)
+            .build()?;
+
+        // the gutters are only drawn on the first visual line of a wrapped line, the cursor moves
+        // by visual lines
+        test_key_sequence(&mut app, "j", |app| {
+            let screen = screen(app);
+            let lines: Vec<_> = screen.lines().take(3).collect();
+            assert_eq!(lines, ["  1  one two three", "     ↪ four five", "  2  six"]);
+            let range = selection(app).primary();
+            assert_eq!((range.anchor, range.head), (14, 15));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bufferline_with_multiple_documents() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.editor.bufferline = BufferLine::Multiple;
+        let mut app = AppBuilder::new()
+            .with_input_text("text\n")
+            .with_config(config)
+            .with_size(30, 4)
+            .build()?;
+
+        test_key_sequence(&mut app, "", |app| {
+            assert!(screen(app).lines().next().unwrap().ends_with("text"));
+        })
+        .await?;
+
+        // a second document shows the bufferline above the view
+        test_key_sequence(&mut app, ":new<ret>", |app| {
+            assert_eq!(screen(app).lines().next(), Some(" [scratch][+]  [scratch]"));
+        })
+        .await
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16, modifiers: KeyModifiers) -> CrosstermEvent {
+        CrosstermEvent::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers,
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mouse_selection() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new().with_input_text("hello world\n").with_size(30, 6).build()?;
+        let mut column = 0;
+        test_key_sequence(&mut app, "", |app| {
+            column = screen(app).lines().next().unwrap().find('h').unwrap() as u16;
+        })
+        .await?;
+
+        let left = MouseEventKind::Down(MouseButton::Left);
+        let none = KeyModifiers::NONE;
+        test_event_sequence(&mut app, [mouse(left, column + 6, 0, none)], |app| {
+            assert_eq!(selection(app), Selection::single(6, 7));
+        })
+        .await?;
+
+        // dragging extends the selection from the clicked cell
+        let drag = MouseEventKind::Drag(MouseButton::Left);
+        let events = [mouse(left, column + 1, 0, none), mouse(drag, column + 4, 0, none)];
+        test_event_sequence(&mut app, events, |app| {
+            assert_eq!(selection(app), Selection::single(1, 5));
+        })
+        .await?;
+
+        // alt-click adds a cursor which becomes the primary one
+        test_event_sequence(&mut app, [mouse(left, column + 8, 0, KeyModifiers::ALT)], |app| {
+            let selection = selection(app);
+            assert_eq!(selection.ranges(), [Range::new(1, 5), Range::new(8, 9)]);
+            assert_eq!(selection.primary_index(), 1);
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn picker_consumes_mouse_events() -> anyhow::Result<()> {
+        let mut app = AppBuilder::new().with_input_text("hello world\n").with_size(30, 6).build()?;
+        let mut column = 0;
+        let mut initial = Selection::point(0);
+        test_key_sequence(&mut app, "", |app| {
+            column = screen(app).lines().next().unwrap().find('h').unwrap() as u16;
+            initial = selection(app);
+        })
+        .await?;
+
+        // the first row is still showing the text above the picker
+        test_key_sequence(&mut app, "<space>b", |_| {}).await?;
+        let click = mouse(MouseEventKind::Down(MouseButton::Left), column + 6, 0, KeyModifiers::NONE);
+        test_event_sequence(&mut app, [click], |app| {
+            assert_eq!(selection(app), initial);
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn open_file() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("open-file.txt");
+        std::fs::write(&path, "from disk\n")?;
+
+        let mut config = Config::default();
+        config.editor.bufferline = BufferLine::Always;
+        let mut app = AppBuilder::new()
+            .with_file(&path)
+            .with_config(config)
+            .with_size(40, 6)
+            .build()?;
+
+        test_key_sequence(&mut app, "l", |app| {
+            assert_eq!(doc_text(app), "from disk\n");
+            assert_eq!(selection(app).primary(), Range::new(1, 2));
+            assert_eq!(screen(app).lines().next(), Some(" open-file.txt"));
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn save_stateful_encoding() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("iso-2022-jp.txt");
+        std::fs::write(&path, "")?;
+
+        let mut app = AppBuilder::new().with_file(&path).build()?;
+
+        // the encoder switches back to ASCII at the end of the text
+        test_key_sequence(&mut app, ":encoding iso-2022-jp<ret>iあ<esc>:w<ret>", |_| {}).await?;
+        assert_eq!(std::fs::read(&path)?, b"\x1b$B$\"\x1b(B");
+
+        test_key_sequence(&mut app, ":encoding iso-2022-jp<ret>", |app| {
+            assert_eq!(doc_text(app), "あ");
+            assert_eq!(doc!(app.editor).encoding().name(), "ISO-2022-JP");
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn theme_from_config() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "theme = \"base16_default\"\n")?;
+
+        let app = AppBuilder::new().with_config(Config::load(&path)?).build()?;
+        assert_eq!(app.editor.theme.name(), "base16_default");
+
+        // an unknown theme keeps the default one
+        let config = Config {
+            theme: Some(String::from("unknown")),
+            ..Config::default()
+        };
+        let app = AppBuilder::new().with_config(config).build()?;
+        assert_eq!(app.editor.theme.name(), "default");
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use crossterm::event::Event as CrosstermEvent;
+use toy_helix::{
+    application::Application,
+    config::Config,
+    core::{syntax, Selection, Transaction},
+    current, doc, loader,
+    term::args::Args,
+    tui::backend::test::TestBackend,
+    view::input::parse_macro,
+};
+
+pub type TestApplication = Application<TestBackend>;
+
+/// Builds an application rendering to a [`TestBackend`].
+pub struct AppBuilder {
+    args: Args,
+    config: Config,
+    syn_loader_conf: syntax::Configuration,
+    input: Option<String>,
+    size: (u16, u16),
+}
+
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self {
+            args: Args::default(),
+            config: Config::default(),
+            syn_loader_conf: loader::default_lang_config()
+                .try_into()
+                .expect("Could not serialize built-in languages.toml"),
+            input: None,
+            size: (80, 24),
+        }
+    }
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.args.files.push((path.into(), Default::default()));
+        self
+    }
+
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Replaces the built-in languages, e.g. with one served by the `fake-lsp` binary.
+    #[cfg(feature = "test-lsp")]
+    pub fn with_lang_config(mut self, syn_loader_conf: syntax::Configuration) -> Self {
+        self.syn_loader_conf = syn_loader_conf;
+        self
+    }
+
+    /// Replaces the text of the initial document, the cursor is placed at the start.
+    pub fn with_input_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.input = Some(text.into());
+        self
+    }
+
+    pub fn with_size(mut self, width: u16, height: u16) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn build(self) -> Result<TestApplication> {
+        let backend = TestBackend::new(self.size.0, self.size.1);
+        let mut app = Application::with_backend(self.args, self.config, self.syn_loader_conf, backend)?;
+
+        if let Some(text) = self.input {
+            let (view, doc) = current!(app.editor);
+            let len = doc.text().len_chars();
+            let transaction = Transaction::change(doc.text(), std::iter::once((0, len, Some(text.as_str().into()))));
+            doc.apply(&transaction, view.id);
+            doc.set_selection(view.id, Selection::point(0));
+        }
+
+        Ok(app)
+    }
+}
+
+/// Feeds `keys`, parsed with [`parse_macro`], through the event loop of `app` and then calls
+/// `test_fn` to check the resulting state.
+pub async fn test_key_sequence<F>(app: &mut TestApplication, keys: &str, test_fn: F) -> Result<()>
+where
+    F: FnOnce(&TestApplication),
+{
+    let events = parse_macro(keys)?.into_iter().map(|key| CrosstermEvent::Key(key.into()));
+    test_event_sequence(app, events, test_fn).await
+}
+
+/// Feeds terminal `events`, e.g. mouse events, through the event loop of `app` and then calls
+/// `test_fn` to check the resulting state.
+pub async fn test_event_sequence<I, F>(app: &mut TestApplication, events: I, test_fn: F) -> Result<()>
+where
+    I: IntoIterator<Item = CrosstermEvent>,
+    F: FnOnce(&TestApplication),
+{
+    let mut input_stream = futures_util::stream::iter(events.into_iter().map(Ok));
+
+    app.event_loop(&mut input_stream).await;
+    test_fn(app);
+
+    Ok(())
+}
+
+/// The text of the document in the focused view.
+pub fn doc_text(app: &TestApplication) -> String {
+    doc!(app.editor).text().to_string()
+}
+
+/// The selection of the document in the focused view.
+pub fn selection(app: &TestApplication) -> Selection {
+    let view = app.editor.tree.get(app.editor.tree.focus);
+    doc!(app.editor).selection(view.id).clone()
+}
+
+/// The last rendered screen as text, see [`TestBackend::screen`].
+pub fn screen(app: &TestApplication) -> String {
+    app.backend().screen()
+}
@@ -0,0 +1,392 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use arc_swap::{access::DynAccess, ArcSwap};
+
+use super::helpers::*;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use toy_helix::{
+    core::{
+        syntax::{Configuration, LanguageConfiguration, LanguageServerConfiguration},
+        Range, Selection, Transaction,
+    },
+    current, doc,
+    lsp::{self, lsp as lsp_types, Registry},
+    view::{document::Document, editor::Config, ViewId},
+};
+
+/// Handles the messages of the language servers and the responses to the requests of the editor
+/// until `condition` holds, failing if it doesn't within a few seconds.
+async fn wait_for_language_servers<F>(app: &mut TestApplication, condition: F) -> anyhow::Result<()>
+where
+    F: Fn(&TestApplication) -> bool,
+{
+    let wait = async {
+        while !condition(app) {
+            tokio::select! {
+                Some((id, call)) = app.editor.language_servers.incoming.next() => {
+                    app.handle_language_server_message(call, id).await;
+                }
+                Some(callback) = app.jobs.futures.next() => app.handle_job_callback(callback).await,
+                else => anyhow::bail!("the language servers exited"),
+            }
+        }
+        Ok(())
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .context("timed out waiting for the language servers")?
+}
+
+/// Returns what the fake language server knows about the documents.
+enum FakeState {}
+
+impl lsp_types::request::Request for FakeState {
+    type Params = ();
+    type Result = Value;
+    const METHOD: &'static str = "fake/state";
+}
+
+fn fake_language() -> LanguageConfiguration {
+    LanguageConfiguration {
+        language_id: "fake".into(),
+        file_types: vec!["fake".into()],
+        roots: vec!["fake.toml".into()],
+        rulers: None,
+        language_server: Some(LanguageServerConfiguration {
+            command: env!("CARGO_BIN_EXE_fake-lsp").into(),
+            args: Vec::new(),
+            environment: Default::default(),
+            timeout: 5,
+        }),
+        config: None,
+    }
+}
+
+/// The method of the next call of a server.
+async fn next_method(registry: &mut Registry) -> Option<String> {
+    match registry.incoming.next().await?.1 {
+        lsp::Call::Notification(notification) => Some(notification.method),
+        lsp::Call::MethodCall(call) => Some(call.method),
+        lsp::Call::Invalid { .. } => None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn synchronize_document() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let dir = tmp.path();
+    std::fs::create_dir_all(dir.join("src"))?;
+    std::fs::write(dir.join("fake.toml"), "")?;
+    let path = dir.join("src/main.fake");
+    std::fs::write(&path, "hello\nworld\n")?;
+
+    let language = Arc::new(fake_language());
+    let mut registry = Registry::new();
+    let client = registry.get(&language, Some(&path))?.expect("the language has a server");
+    // the same server is used for every document of the language
+    let same_client = registry.get(&language, Some(&path))?.unwrap();
+    assert_eq!(client.id(), same_client.id());
+
+    // the transport reports when the server is initialized
+    assert_eq!(next_method(&mut registry).await.as_deref(), Some("initialized"));
+    assert!(client.is_initialized());
+
+    let config: Arc<dyn DynAccess<Config>> = Arc::new(ArcSwap::from_pointee(Config::default()));
+    let mut doc = Document::open(&path, None, None, config)?;
+    doc.language = Some(language);
+    doc.set_language_server(Some(client.clone()));
+    client.text_document_did_open(doc.url().unwrap(), doc.version(), doc.text(), "fake".into())?;
+
+    // the changes are sent incrementally, positions are counted in UTF-16 code units
+    let view_id = ViewId::default();
+    let transaction = Transaction::change(
+        doc.text(),
+        [(0, 1, Some("J".into())), (6, 6, Some("big 🌍 ".into()))].into_iter(),
+    );
+    assert!(doc.apply(&transaction, view_id));
+    let transaction = Transaction::change(doc.text(), [(12, 13, Some("W".into())), (17, 18, None)].into_iter());
+    assert!(doc.apply(&transaction, view_id));
+    assert_eq!(doc.text(), "Jello\nbig 🌍 World");
+    doc.save(false)?;
+
+    let url = doc.url().unwrap().to_string();
+    let state = client.call::<FakeState>(()).await?;
+    let root_uri = lsp_types::Url::from_file_path(dir).unwrap().to_string();
+    assert_eq!(state["rootUri"], json!(root_uri));
+    assert_eq!(state["documents"][&url], json!("Jello\nbig 🌍 World"));
+    assert_eq!(state["saved"][&url], json!("Jello\nbig 🌍 World"));
+
+    client.text_document_did_close(doc.identifier())?;
+    let state = client.call::<FakeState>(()).await?;
+    assert_eq!(state["documents"], json!({}));
+    assert_eq!(
+        state["notifications"],
+        json!([
+            "initialized",
+            "textDocument/didOpen",
+            "textDocument/didChange",
+            "textDocument/didChange",
+            "textDocument/didSave",
+            "textDocument/didClose",
+        ])
+    );
+
+    // the transport reports when the server exits, after the diagnostics it published
+    client.shutdown_and_exit().await?;
+    let mut method = next_method(&mut registry).await;
+    while method.as_deref() == Some("textDocument/publishDiagnostics") {
+        method = next_method(&mut registry).await;
+    }
+    assert_eq!(method.as_deref(), Some("exit"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diagnostics() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let dir = tmp.path();
+    std::fs::write(dir.join("fake.toml"), "")?;
+    let path = dir.join("main.fake");
+    std::fs::write(&path, "let a = 1; // TODO\nlet b = FIXME;\n")?;
+
+    let mut app = AppBuilder::new()
+        .with_file(&path)
+        .with_lang_config(Configuration {
+            language: vec![fake_language()],
+        })
+        .with_size(200, 16)
+        .build()?;
+
+    // the server reports the diagnostics once the document is opened in it
+    wait_for_language_servers(&mut app, |app| doc!(app.editor).diagnostics().len() == 2).await?;
+
+    // the message of a diagnostic is hidden while a cursor is on its line
+    test_key_sequence(&mut app, "]d", |app| {
+        assert_eq!(selection(app).primary(), Range::new(14, 18));
+        let screen = screen(app);
+        let lines: Vec<_> = screen.lines().collect();
+        assert_eq!(lines[0], "● 1  let a = 1; // TODO");
+        assert_eq!(lines[1], "● 2  let b = FIXME;  found a FIXME");
+    })
+    .await?;
+
+    test_key_sequence(&mut app, "]d", |app| {
+        assert_eq!(selection(app).primary(), Range::new(27, 32));
+        let screen = screen(app);
+        let lines: Vec<_> = screen.lines().collect();
+        assert_eq!(lines[0], "● 1  let a = 1; // TODO  found a TODO");
+        assert_eq!(lines[1], "● 2  let b = FIXME;");
+    })
+    .await?;
+
+    // moving backwards selects the diagnostic in reverse
+    test_key_sequence(&mut app, "[d", |app| {
+        assert_eq!(selection(app).primary(), Range::new(18, 14));
+    })
+    .await?;
+
+    // the workspace picker lists the errors first and selects the picked diagnostic
+    test_key_sequence(&mut app, "<space>D", |app| {
+        let screen = screen(app);
+        let error = screen.find("error   ").expect("the error is listed");
+        let warning = screen.find("warning ").expect("the warning is listed");
+        assert!(error < warning);
+        assert!(screen.contains("main.fake:2:"));
+    })
+    .await?;
+    test_key_sequence(&mut app, "<ret>", |app| {
+        assert_eq!(selection(app).primary(), Range::new(27, 32));
+    })
+    .await?;
+
+    // the diagnostics follow the edits until the server publishes new ones
+    test_key_sequence(&mut app, "[dix<esc>", |app| {
+        assert_eq!(doc_text(app), "let a = 1; // xTODO\nlet b = FIXME;\n");
+        let starts: Vec<_> = doc!(app.editor).diagnostics().iter().map(|diag| diag.range.start).collect();
+        assert_eq!(starts, vec![15, 28]);
+    })
+    .await?;
+    wait_for_language_servers(&mut app, |app| {
+        let mut diagnostics = app.editor.diagnostics.values().flatten();
+        diagnostics.any(|(diagnostic, _)| diagnostic.range.start.character == 15)
+    })
+    .await?;
+    let starts: Vec<_> = doc!(app.editor).diagnostics().iter().map(|diag| diag.range.start).collect();
+    assert_eq!(starts, vec![15, 28]);
+
+    app.editor.close_language_servers(None).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn completion() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let dir = tmp.path();
+    std::fs::write(dir.join("fake.toml"), "")?;
+    let path = dir.join("main.fake");
+    std::fs::write(&path, "\n")?;
+
+    let mut app = AppBuilder::new()
+        .with_file(&path)
+        .with_lang_config(Configuration {
+            language: vec![fake_language()],
+        })
+        .with_size(80, 16)
+        .build()?;
+    wait_for_language_servers(&mut app, |app| doc!(app.editor).language_server().is_some()).await?;
+
+    // the menu lists the items matching the word before the cursor
+    test_key_sequence(&mut app, "iap<C-x>", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("apricot")).await?;
+    let menu = screen(&app);
+    assert!(menu.contains("apple    function  fn apple() -> Fruit"));
+    assert!(!menu.contains("banana"));
+
+    // an additional edit is applied along with the item
+    test_key_sequence(&mut app, "<tab><tab><ret>", |app| {
+        assert_eq!(doc_text(app), "use fruits::apricot;\napricot\n");
+        assert_eq!(selection(app).primary().cursor(doc!(app.editor).text().slice(..)), 28);
+        assert!(!screen(app).contains("apple"));
+    })
+    .await?;
+
+    // the menu opens when the editor is idle after a long enough word, and the documentation
+    // of the selected item is resolved when it is missing
+    test_key_sequence(&mut app, " ap", |_| {}).await?;
+    app.handle_idle_timeout().await;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("apple")).await?;
+    test_key_sequence(&mut app, "<tab>", |app| {
+        assert!(screen(app).contains("fn apple() -> Fruit"));
+    })
+    .await?;
+    app.handle_idle_timeout().await;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("Picks an apple")).await?;
+    test_key_sequence(&mut app, "<ret>", |app| {
+        assert_eq!(doc_text(app), "use fruits::apricot;\napricot apple()\n");
+    })
+    .await?;
+
+    // a trigger character opens the menu, the placeholders of a snippet are selected
+    test_key_sequence(&mut app, ".", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("banana")).await?;
+    test_key_sequence(&mut app, "b<tab><ret>", |app| {
+        assert_eq!(doc_text(app), "use fruits::apricot;\napricot apple().banana(count, ripe)\n");
+        assert_eq!(selection(app).primary(), Range::new(49, 44));
+    })
+    .await?;
+
+    let state = doc!(app.editor).language_server().unwrap().call::<FakeState>(()).await?;
+    assert_eq!(
+        state["completions"],
+        json!([
+            { "triggerKind": 1 },
+            { "triggerKind": 1 },
+            { "triggerKind": 2, "triggerCharacter": "." },
+        ])
+    );
+
+    app.editor.close_language_servers(None).await?;
+    Ok(())
+}
+
+/// The char index of the `nth` match of `pattern` in `text`.
+fn char_offset(text: &str, pattern: &str, nth: usize) -> usize {
+    let (byte, _) = text.match_indices(pattern).nth(nth).unwrap();
+    text[..byte].chars().count()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn navigation() -> anyhow::Result<()> {
+    let tmp = tempfile::tempdir()?;
+    let dir = tmp.path();
+    std::fs::write(dir.join("fake.toml"), "")?;
+    let path = dir.join("main.fake");
+    let text = "struct Fruit;\nimpl Fruit {\n    fn peel() {}\n}\nimpl Fruit {\n    fn ripe() {}\n}\nfn main() { let 🍎 = peel(); ripe(); peel(); }\n";
+    std::fs::write(&path, text)?;
+
+    let mut app = AppBuilder::new()
+        .with_file(&path)
+        .with_lang_config(Configuration {
+            language: vec![fake_language()],
+        })
+        .with_size(200, 16)
+        .build()?;
+    wait_for_language_servers(&mut app, |app| doc!(app.editor).language_server().is_some()).await?;
+    let move_to = |app: &mut TestApplication, pos: usize| {
+        let (view, doc) = current!(app.editor);
+        doc.set_selection(view.id, Selection::point(pos));
+    };
+
+    let definition = char_offset(text, "peel", 0);
+    let definition = Range::new(definition, definition + 4);
+    let call = char_offset(text, "peel", 1);
+
+    // a single location is jumped to, given as a location or a link
+    move_to(&mut app, call);
+    test_key_sequence(&mut app, "gd", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| selection(app).primary() == definition).await?;
+    move_to(&mut app, call);
+    test_key_sequence(&mut app, "gD", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| selection(app).primary() == definition).await?;
+
+    // several locations are listed in a picker, their UTF-16 positions are converted to chars
+    test_key_sequence(&mut app, "gr", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("main.fake:8")).await?;
+    test_key_sequence(&mut app, "<tab><ret>", |app| {
+        assert_eq!(selection(app).primary(), Range::new(call, call + 4));
+    })
+    .await?;
+
+    move_to(&mut app, char_offset(text, "Fruit", 0));
+    test_key_sequence(&mut app, "gi", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("main.fake:2")).await?;
+    test_key_sequence(&mut app, "<tab><ret>", |app| {
+        let implementation = char_offset(text, "Fruit", 2);
+        assert_eq!(selection(app).primary(), Range::new(implementation, implementation + 5));
+    })
+    .await?;
+
+    test_key_sequence(&mut app, "gy", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("no location found")).await?;
+
+    // the methods of the document symbols are listed after their parent
+    test_key_sequence(&mut app, "<space>s", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("function    main")).await?;
+    let symbols = screen(&app);
+    let positions: Vec<_> = [
+        "struct      Fruit",
+        "method      peel",
+        "method      ripe",
+        "function    main",
+    ]
+    .into_iter()
+    .map(|symbol| symbols.find(symbol).expect("the symbol is listed"))
+    .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    test_key_sequence(&mut app, "ripe<ret>", |app| {
+        let ripe = char_offset(text, "ripe", 0);
+        assert_eq!(selection(app).primary(), Range::new(ripe, ripe + 4));
+    })
+    .await?;
+
+    // the workspace symbols are requested again once typing the query paused, not on every key
+    test_key_sequence(&mut app, "<space>S", |_| {}).await?;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("4/4")).await?;
+    test_key_sequence(&mut app, "pee", |_| {}).await?;
+    let state = doc!(app.editor).language_server().unwrap().call::<FakeState>(()).await?;
+    assert_eq!(state["symbolQueries"], json!([""]));
+    app.handle_idle_timeout().await;
+    wait_for_language_servers(&mut app, |app| screen(app).contains("1/1")).await?;
+    test_key_sequence(&mut app, "<ret>", |app| {
+        assert_eq!(selection(app).primary(), definition);
+    })
+    .await?;
+
+    let state = doc!(app.editor).language_server().unwrap().call::<FakeState>(()).await?;
+    assert_eq!(state["symbolQueries"], json!(["", "pee"]));
+
+    app.editor.close_language_servers(None).await?;
+    Ok(())
+}